@@ -0,0 +1,49 @@
+// Benchmarks des opérations exécutées à chaque requête : comptage des tokens et clé de cache
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+
+use codecrafter::llm::cache::cache_key;
+use codecrafter::llm::tokenizer::{Tokenizer, TokenizerKind};
+use codecrafter::llm::{LLMMessage, LLMRequest, Role};
+
+fn sample_request() -> LLMRequest {
+    let code = "fn main() {\n    println!(\"Hello, world!\");\n}\n".repeat(50);
+    LLMRequest {
+        messages: vec![LLMMessage {
+            role: Role::User,
+            content: format!("Relis ce code :\n{}", code).into(),
+            metadata: None,
+            tool_calls: Vec::new(),
+            tool_call_id: None,
+        }],
+        parameters: None,
+        stream: false,
+        fim: None,
+        tools: Vec::new(),
+        tool_choice: None,
+        cache: false,
+    }
+}
+
+fn tokenizers(c: &mut Criterion) {
+    let request = sample_request();
+    for (name, kind) in [
+        ("cl100k_base", TokenizerKind::Cl100kBase),
+        ("o200k_base", TokenizerKind::O200kBase),
+    ] {
+        let tokenizer = Tokenizer::new(kind);
+        c.bench_function(&format!("count_request/{}", name), |b| {
+            b.iter(|| tokenizer.count_request(black_box(&request)))
+        });
+    }
+}
+
+fn cache_keys(c: &mut Criterion) {
+    let request = sample_request();
+    c.bench_function("cache_key", |b| {
+        b.iter(|| cache_key("claude", "claude-sonnet-4-5", black_box(&request)))
+    });
+}
+
+criterion_group!(benches, tokenizers, cache_keys);
+criterion_main!(benches);
@@ -0,0 +1,42 @@
+// Exemple minimal : charge la configuration et envoie une requête au provider par défaut
+//
+//     cargo run --example basic_usage -- "Explique les lifetimes en Rust"
+
+use codecrafter::llm::config::CodeCrafterConfig;
+use codecrafter::llm::manager::BUDGET_WARNINGS_KEY;
+use codecrafter::llm::{LLMMessage, LLMRequest, Role};
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    let prompt = std::env::args()
+        .nth(1)
+        .unwrap_or_else(|| "Écris une fonction Rust qui inverse une chaîne.".to_string());
+
+    let manager = CodeCrafterConfig::load()?.build_manager().await?;
+    let request = LLMRequest {
+        messages: vec![LLMMessage {
+            role: Role::User,
+            content: prompt.into(),
+            metadata: None,
+            tool_calls: Vec::new(),
+            tool_call_id: None,
+        }],
+        parameters: None,
+        stream: false,
+        fim: None,
+        tools: Vec::new(),
+        tool_choice: None,
+        cache: false,
+    };
+
+    let response = manager.generate(request).await?;
+    println!("{}", response.content);
+    println!(
+        "\n[{} tokens, modèle {}]",
+        response.usage.total_tokens, response.model
+    );
+    if let Some(warnings) = response.metadata.as_ref().and_then(|m| m.get(BUDGET_WARNINGS_KEY)) {
+        eprintln!("Attention, seuil de budget franchi : {}", warnings);
+    }
+    Ok(())
+}
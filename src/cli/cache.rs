@@ -0,0 +1,46 @@
+// Commande `codecrafter cache` : consultation et purge du cache des réponses
+
+use clap::{Args, Subcommand};
+use std::path::PathBuf;
+
+use crate::llm::cache::ResponseCache;
+
+#[derive(Args)]
+pub struct CacheArgs {
+    #[command(subcommand)]
+    command: CacheCommand,
+
+    /// Base du cache (par défaut dans le répertoire de données utilisateur)
+    #[arg(long, global = true, env = "CODECRAFTER_CACHE_DB")]
+    db: Option<PathBuf>,
+}
+
+#[derive(Subcommand)]
+enum CacheCommand {
+    /// Nombre de réponses en cache et taille occupée
+    Stats,
+    /// Supprime toutes les réponses en cache
+    Clear,
+}
+
+pub async fn run(args: CacheArgs) -> anyhow::Result<()> {
+    let path = match args.db {
+        Some(path) => path,
+        None => ResponseCache::default_path()
+            .ok_or_else(|| anyhow::anyhow!("Répertoire de données utilisateur introuvable"))?,
+    };
+    let cache = ResponseCache::open(&path, 1).await?;
+
+    match args.command {
+        CacheCommand::Stats => {
+            let stats = cache.stats().await?;
+            println!("{}", path.display());
+            println!("{} réponse(s), {:.1} Kio", stats.entries, stats.bytes as f64 / 1024.0);
+        }
+        CacheCommand::Clear => {
+            let removed = cache.clear().await?;
+            println!("{} réponse(s) supprimée(s)", removed);
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,59 @@
+// Commande `codecrafter config` : vérification de la configuration
+
+use clap::{Args, Subcommand};
+use colored::Colorize;
+
+use crate::llm::config::ConfigLoader;
+use crate::llm::validation::Severity;
+
+#[derive(Args)]
+pub struct ConfigArgs {
+    #[command(subcommand)]
+    command: ConfigCommand,
+}
+
+#[derive(Subcommand)]
+enum ConfigCommand {
+    /// Vérifie les fichiers de configuration et signale tous les problèmes
+    Check {
+        /// Profil à vérifier (par défaut: CODECRAFTER_PROFILE)
+        #[arg(long)]
+        profile: Option<String>,
+    },
+}
+
+pub fn run(args: ConfigArgs) -> anyhow::Result<()> {
+    match args.command {
+        ConfigCommand::Check { profile } => check(profile),
+    }
+}
+
+fn check(profile: Option<String>) -> anyhow::Result<()> {
+    let report = ConfigLoader::new().profile(profile).check();
+
+    if report.files.is_empty() {
+        println!("Aucun fichier de configuration trouvé");
+    }
+    for file in &report.files {
+        println!("{} {}", "fichier".dimmed(), file.display());
+    }
+    if let Some(profile) = &report.profile {
+        println!("{} {}", "profil".dimmed(), profile);
+    }
+
+    for issue in &report.issues {
+        let label = match issue.severity {
+            Severity::Error => "erreur".red().bold(),
+            Severity::Warning => "avertissement".yellow().bold(),
+        };
+        println!("{}: {}", label, issue);
+    }
+
+    let errors = report.errors().count();
+    let warnings = report.warnings().count();
+    if errors > 0 {
+        anyhow::bail!("{} erreur(s), {} avertissement(s)", errors, warnings);
+    }
+    println!("{} ({} avertissement(s))", "Configuration valide".green(), warnings);
+    Ok(())
+}
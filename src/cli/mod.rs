@@ -0,0 +1,44 @@
+// Interface en ligne de commande (clap)
+
+use clap::{Parser, Subcommand};
+
+mod cache;
+mod config;
+mod provider;
+mod secret;
+mod usage;
+
+
+/// Assistant de code IA multi-provider
+#[derive(Parser)]
+#[command(name = "codecrafter", version, about)]
+pub struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Cache des réponses déterministes
+    Cache(cache::CacheArgs),
+    /// Gestion de la configuration
+    Config(config::ConfigArgs),
+    /// Providers configurés (secrets masqués)
+    Provider(provider::ProviderArgs),
+    /// Trousseau chiffré des clés API
+    Secret(secret::SecretArgs),
+    /// Rapport de consommation des LLM (tokens et coût)
+    Usage(usage::UsageArgs),
+}
+
+/// Analyse les arguments du processus et exécute la commande demandée
+pub async fn run() -> anyhow::Result<()> {
+    let cli = Cli::parse();
+    match cli.command {
+        Command::Cache(args) => cache::run(args).await,
+        Command::Config(args) => config::run(args),
+        Command::Provider(args) => provider::run(args),
+        Command::Secret(args) => secret::run(args),
+        Command::Usage(args) => usage::run(args).await,
+    }
+}
@@ -0,0 +1,79 @@
+// Commande `codecrafter provider` : consultation des providers configurés
+
+use clap::{Args, Subcommand};
+use colored::Colorize;
+
+use crate::llm::config::ConfigLoader;
+use crate::llm::secrets::{is_sensitive_header, REDACTED};
+use crate::llm::LLMProviderConfig;
+
+#[derive(Args)]
+pub struct ProviderArgs {
+    #[command(subcommand)]
+    command: ProviderCommand,
+}
+
+#[derive(Subcommand)]
+enum ProviderCommand {
+    /// Affiche la configuration effective des providers, secrets masqués
+    Show {
+        /// Provider à afficher (par défaut: tous)
+        name: Option<String>,
+
+        /// Profil à appliquer (par défaut: CODECRAFTER_PROFILE)
+        #[arg(long)]
+        profile: Option<String>,
+    },
+}
+
+pub fn run(args: ProviderArgs) -> anyhow::Result<()> {
+    match args.command {
+        ProviderCommand::Show { name, profile } => show(name, profile),
+    }
+}
+
+fn show(name: Option<String>, profile: Option<String>) -> anyhow::Result<()> {
+    let config = ConfigLoader::new().profile(profile).load()?;
+
+    let providers: Vec<_> = match &name {
+        Some(name) => match config.provider(name) {
+            Some(provider) => vec![(name, provider)],
+            None => anyhow::bail!("Provider inconnu: {}", name),
+        },
+        None => config.providers.iter().collect(),
+    };
+    if providers.is_empty() {
+        println!("Aucun provider configuré");
+    }
+
+    for (name, provider) in providers {
+        let default = config.default_provider.as_ref() == Some(name);
+        let header = format!("[providers.{}]", name);
+        if default {
+            println!("{} {}", header.bold(), "# par défaut".dimmed());
+        } else {
+            println!("{}", header.bold());
+        }
+        println!("{}", toml::to_string(&redacted(provider))?);
+    }
+    Ok(())
+}
+
+/// Copie affichable : la clé API est masquée par sa sérialisation, les headers et options
+/// sensibles ainsi que le mot de passe de l'URL le sont ici
+fn redacted(config: &LLMProviderConfig) -> LLMProviderConfig {
+    let mut config = config.clone();
+    for (name, value) in config.headers.iter_mut().chain(config.options.iter_mut()) {
+        if is_sensitive_header(name) {
+            *value = REDACTED.to_string();
+        }
+    }
+    if let Some(base_url) = &mut config.base_url {
+        if let Ok(mut url) = url::Url::parse(base_url) {
+            if url.password().is_some() && url.set_password(Some(REDACTED)).is_ok() {
+                *base_url = url.to_string();
+            }
+        }
+    }
+    config
+}
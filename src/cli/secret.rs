@@ -0,0 +1,71 @@
+// Commande `codecrafter secret` : gestion du trousseau chiffré des clés API
+
+use clap::{Args, Subcommand};
+use dialoguer::Password;
+use std::path::{Path, PathBuf};
+
+use crate::llm::secrets::{Keystore, Secret, KEYSTORE_PASSPHRASE_ENV};
+
+#[derive(Args)]
+pub struct SecretArgs {
+    #[command(subcommand)]
+    command: SecretCommand,
+
+    /// Fichier du trousseau (par défaut dans le répertoire de données utilisateur)
+    #[arg(long, global = true)]
+    keystore: Option<PathBuf>,
+}
+
+#[derive(Subcommand)]
+enum SecretCommand {
+    /// Enregistre un secret, référencé ensuite par `api_key = { keystore = "<nom>" }`
+    Set { name: String },
+    /// Supprime un secret
+    Remove { name: String },
+    /// Liste les noms des secrets (jamais leurs valeurs)
+    List,
+}
+
+pub fn run(args: SecretArgs) -> anyhow::Result<()> {
+    let path = match args.keystore {
+        Some(path) => path,
+        None => Keystore::default_path()
+            .ok_or_else(|| anyhow::anyhow!("Répertoire de données utilisateur introuvable"))?,
+    };
+    let mut keystore = Keystore::open(&path, passphrase(&path)?)?;
+
+    match args.command {
+        SecretCommand::Set { name } => {
+            let value = Password::new().with_prompt(format!("Valeur de {}", name)).interact()?;
+            keystore.set(name.as_str(), Secret::new(value));
+            keystore.save()?;
+            println!("Secret {} enregistré dans {}", name, keystore.path().display());
+        }
+        SecretCommand::Remove { name } => {
+            if !keystore.remove(&name) {
+                anyhow::bail!("Secret inconnu: {}", name);
+            }
+            keystore.save()?;
+            println!("Secret {} supprimé", name);
+        }
+        SecretCommand::List => {
+            for name in keystore.names() {
+                println!("{}", name);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Phrase de passe de l'environnement, sinon demandée (avec confirmation à la création)
+fn passphrase(path: &Path) -> anyhow::Result<Secret> {
+    if let Ok(passphrase) = std::env::var(KEYSTORE_PASSPHRASE_ENV) {
+        return Ok(Secret::new(passphrase));
+    }
+
+    let mut prompt = Password::new().with_prompt("Phrase de passe du trousseau");
+    if !path.exists() {
+        prompt = prompt.with_confirmation("Confirmer", "Les phrases de passe diffèrent");
+    }
+    Ok(Secret::new(prompt.interact()?))
+}
@@ -0,0 +1,97 @@
+// Commande `codecrafter usage` : rapport du journal de consommation
+
+use chrono::{Duration, Utc};
+use clap::{Args, ValueEnum};
+use colored::Colorize;
+use std::path::PathBuf;
+
+use crate::llm::usage::{UsageGrouping, UsageLedger, UsageSummary};
+
+#[derive(Args)]
+pub struct UsageArgs {
+    /// Regroupement des lignes du rapport
+    #[arg(long, value_enum, default_value_t = GroupBy::Day)]
+    by: GroupBy,
+
+    /// Limite le rapport aux N derniers jours
+    #[arg(long)]
+    days: Option<u32>,
+
+    /// Base du journal (par défaut dans le répertoire de données utilisateur)
+    #[arg(long, env = "CODECRAFTER_USAGE_DB")]
+    db: Option<PathBuf>,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum GroupBy {
+    Day,
+    Provider,
+    Command,
+}
+
+impl From<GroupBy> for UsageGrouping {
+    fn from(group: GroupBy) -> Self {
+        match group {
+            GroupBy::Day => UsageGrouping::Day,
+            GroupBy::Provider => UsageGrouping::Provider,
+            GroupBy::Command => UsageGrouping::Command,
+        }
+    }
+}
+
+pub async fn run(args: UsageArgs) -> anyhow::Result<()> {
+    let ledger = match &args.db {
+        Some(path) => UsageLedger::open(path).await?,
+        None => UsageLedger::open_default().await?,
+    };
+    let since = args.days.map(|days| Utc::now() - Duration::days(days as i64));
+    let rows = ledger.report(args.by.into(), since).await?;
+
+    if rows.is_empty() {
+        println!("Aucune consommation enregistrée");
+        return Ok(());
+    }
+
+    let label = match args.by {
+        GroupBy::Day => "JOUR",
+        GroupBy::Provider => "PROVIDER",
+        GroupBy::Command => "COMMANDE",
+    };
+    println!(
+        "{}",
+        format!(
+            "{:<24} {:>9} {:>14} {:>14} {:>12}",
+            label, "REQUÊTES", "PROMPT", "COMPLÉTION", "COÛT (USD)"
+        )
+        .bold()
+    );
+    for row in &rows {
+        print_row(row);
+    }
+
+    let total = rows.iter().fold(
+        UsageSummary {
+            key: "TOTAL".to_string(),
+            requests: 0,
+            prompt_tokens: 0,
+            completion_tokens: 0,
+            cost: 0.0,
+        },
+        |mut total, row| {
+            total.requests += row.requests;
+            total.prompt_tokens += row.prompt_tokens;
+            total.completion_tokens += row.completion_tokens;
+            total.cost += row.cost;
+            total
+        },
+    );
+    print_row(&total);
+    Ok(())
+}
+
+fn print_row(row: &UsageSummary) {
+    println!(
+        "{:<24} {:>9} {:>14} {:>14} {:>12.4}",
+        row.key, row.requests, row.prompt_tokens, row.completion_tokens, row.cost
+    );
+}
@@ -0,0 +1,4 @@
+// CodeCrafter - Assistant de code IA multi-provider
+
+pub mod cli;
+pub mod llm;
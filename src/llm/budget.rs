@@ -0,0 +1,366 @@
+// Budgets de consommation : limites souples (avertissement) et strictes (refus)
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+use super::models::ModelInfo;
+use super::providers::resolve_parameters;
+use super::tokenizer::Tokenizer;
+use super::usage::{UsageLedger, UsageTotals};
+use super::{LLMError, LLMProviderConfig, LLMRequest, TokenUsage};
+
+
+/// Période sur laquelle la consommation est cumulée
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum BudgetPeriod {
+    /// Depuis minuit (UTC)
+    #[default]
+    Day,
+    /// Tout l'historique du journal
+    Total,
+}
+
+/// Unité d'une limite
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum BudgetUnit {
+    /// Tokens du prompt et de la réponse
+    Tokens,
+    /// Coût en USD (tarifs du catalogue des modèles)
+    Usd,
+}
+
+/// Limite de consommation.
+///
+/// Sans `provider` ni `project`, la limite porte sur l'ensemble des appels.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct BudgetLimit {
+    /// Nom du provider dans le `LLMManager`
+    #[serde(default)]
+    pub provider: Option<String>,
+    /// Projet (voir `UsageContext`)
+    #[serde(default)]
+    pub project: Option<String>,
+    #[serde(default)]
+    pub period: BudgetPeriod,
+    pub unit: BudgetUnit,
+    /// Seuil au-delà duquel un avertissement est émis
+    #[serde(default)]
+    pub soft: Option<f64>,
+    /// Seuil au-delà duquel les requêtes sont refusées
+    #[serde(default)]
+    pub hard: Option<f64>,
+}
+
+impl BudgetLimit {
+    fn applies_to(&self, provider: &str, project: Option<&str>) -> bool {
+        self.provider.iter().all(|name| name == provider)
+            && self.project.iter().all(|name| Some(name.as_str()) == project)
+    }
+
+    fn since(&self, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        match self.period {
+            BudgetPeriod::Day => now.date_naive().and_hms_opt(0, 0, 0).map(|t| t.and_utc()),
+            BudgetPeriod::Total => None,
+        }
+    }
+
+    fn amount(&self, totals: &UsageTotals) -> f64 {
+        match self.unit {
+            BudgetUnit::Tokens => totals.tokens as f64,
+            BudgetUnit::Usd => totals.cost,
+        }
+    }
+}
+
+impl fmt::Display for BudgetLimit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let unit = match self.unit {
+            BudgetUnit::Tokens => "tokens",
+            BudgetUnit::Usd => "USD",
+        };
+        let period = match self.period {
+            BudgetPeriod::Day => "par jour",
+            BudgetPeriod::Total => "au total",
+        };
+        write!(f, "budget {} {}", unit, period)?;
+        if let Some(provider) = &self.provider {
+            write!(f, ", provider {}", provider)?;
+        }
+        if let Some(project) = &self.project {
+            write!(f, ", projet {}", project)?;
+        }
+        Ok(())
+    }
+}
+
+
+/// Consommation maximale estimée d'une requête
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct SpendEstimate {
+    pub tokens: u64,
+    pub cost: f64,
+}
+
+impl SpendEstimate {
+    /// Estimation pessimiste : prompt compté par le tokenizer du modèle et réponse
+    /// de `max_tokens` tokens
+    pub fn for_request(
+        config: &LLMProviderConfig,
+        request: &LLMRequest,
+        tokenizer: &Tokenizer,
+        model: Option<&ModelInfo>,
+    ) -> Self {
+        let usage = TokenUsage {
+            prompt_tokens: tokenizer.count_request(request),
+            completion_tokens: resolve_parameters(config, request).max_tokens,
+            total_tokens: 0,
+        };
+
+        SpendEstimate {
+            tokens: usage.prompt_tokens as u64 + usage.completion_tokens as u64,
+            cost: model.map_or(0.0, |model| model.cost(&usage)),
+        }
+    }
+
+    fn amount(&self, unit: BudgetUnit) -> f64 {
+        match unit {
+            BudgetUnit::Tokens => self.tokens as f64,
+            BudgetUnit::Usd => self.cost,
+        }
+    }
+}
+
+/// Limite souple franchie par une requête acceptée
+#[derive(Debug, Clone, PartialEq)]
+pub struct BudgetWarning {
+    pub limit: BudgetLimit,
+    /// Consommation de la période, requête comprise
+    pub projected: f64,
+    pub threshold: f64,
+}
+
+impl fmt::Display for BudgetWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} : {:.2} prévus pour un seuil d'alerte de {:.2}",
+            self.limit, self.projected, self.threshold
+        )
+    }
+}
+
+
+/// Ensemble des limites appliquées par le `LLMManager`
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Budgets {
+    limits: Vec<BudgetLimit>,
+}
+
+impl Budgets {
+    pub fn new(limits: Vec<BudgetLimit>) -> Self {
+        Budgets { limits }
+    }
+
+    pub fn add(&mut self, limit: BudgetLimit) {
+        self.limits.push(limit);
+    }
+
+    pub fn limits(&self) -> &[BudgetLimit] {
+        &self.limits
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.limits.is_empty()
+    }
+
+    /// Vérifie qu'une requête estimée à `estimate` reste dans les limites applicables.
+    ///
+    /// Retourne `LLMError::BudgetExceeded` si une limite stricte serait dépassée, sinon
+    /// les limites souples franchies.
+    pub async fn check(
+        &self,
+        ledger: &UsageLedger,
+        provider: &str,
+        project: Option<&str>,
+        estimate: &SpendEstimate,
+    ) -> Result<Vec<BudgetWarning>, LLMError> {
+        let now = Utc::now();
+        let mut warnings = Vec::new();
+
+        for limit in self.limits.iter().filter(|limit| limit.applies_to(provider, project)) {
+            let totals = ledger
+                .totals(limit.provider.as_deref(), limit.project.as_deref(), limit.since(now))
+                .await?;
+            let projected = limit.amount(&totals) + estimate.amount(limit.unit);
+
+            if let Some(hard) = limit.hard.filter(|hard| projected > *hard) {
+                return Err(LLMError::BudgetExceeded(format!(
+                    "{} : {:.2} prévus pour une limite de {:.2}",
+                    limit, projected, hard
+                )));
+            }
+            if let Some(threshold) = limit.soft.filter(|soft| projected > *soft) {
+                warnings.push(BudgetWarning {
+                    limit: limit.clone(),
+                    projected,
+                    threshold,
+                });
+            }
+        }
+
+        Ok(warnings)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::manager::BUDGET_WARNINGS_KEY;
+    use crate::llm::streaming::collect_response;
+    use crate::llm::test_support::{user_request, ScriptedProvider};
+    use crate::llm::usage::{UsageContext, UsageRecord};
+    use crate::llm::LLMManager;
+    use chrono::Duration;
+
+    fn limit(provider: Option<&str>, unit: BudgetUnit, soft: f64, hard: f64) -> BudgetLimit {
+        BudgetLimit {
+            provider: provider.map(str::to_string),
+            project: None,
+            period: BudgetPeriod::Day,
+            unit,
+            soft: Some(soft),
+            hard: Some(hard),
+        }
+    }
+
+    fn context() -> UsageContext {
+        UsageContext {
+            command: "generate".to_string(),
+            project: Some("codecrafter".to_string()),
+        }
+    }
+
+    /// Journal contenant 1000 tokens (1 USD) consommés par "claude" aujourd'hui et
+    /// autant hier
+    async fn ledger() -> UsageLedger {
+        let ledger = UsageLedger::in_memory().await.unwrap();
+        for timestamp in [Utc::now(), Utc::now() - Duration::days(1)] {
+            let record = UsageRecord {
+                timestamp,
+                provider: "claude".to_string(),
+                model: "claude-sonnet-4".to_string(),
+                prompt_tokens: 800,
+                completion_tokens: 200,
+                cost: 1.0,
+                command: "generate".to_string(),
+                project: Some("codecrafter".to_string()),
+            };
+            ledger.record(&record).await.unwrap();
+        }
+        ledger
+    }
+
+    #[tokio::test]
+    async fn hard_and_soft_limits() {
+        let ledger = ledger().await;
+        let estimate = SpendEstimate {
+            tokens: 100,
+            cost: 0.25,
+        };
+        let check = |limits: Vec<BudgetLimit>, provider: &'static str| {
+            let ledger = ledger.clone();
+            async move {
+                Budgets::new(limits)
+                    .check(&ledger, provider, Some("codecrafter"), &estimate)
+                    .await
+            }
+        };
+
+        let warnings = check(vec![limit(None, BudgetUnit::Tokens, 1000.0, 2000.0)], "claude")
+            .await
+            .unwrap();
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].projected, 1100.0);
+
+        let refused = check(vec![limit(None, BudgetUnit::Usd, 0.5, 1.2)], "claude").await;
+        assert!(matches!(refused, Err(LLMError::BudgetExceeded(_))));
+
+        // Limite propre à un autre provider
+        let scoped = check(vec![limit(Some("claude"), BudgetUnit::Usd, 0.1, 0.2)], "local").await;
+        assert_eq!(scoped.unwrap(), Vec::new());
+    }
+
+    #[tokio::test]
+    async fn period_total_includes_history() {
+        let ledger = ledger().await;
+        let total = BudgetLimit {
+            period: BudgetPeriod::Total,
+            ..limit(None, BudgetUnit::Tokens, 1500.0, 5000.0)
+        };
+
+        let warnings = Budgets::new(vec![total])
+            .check(&ledger, "claude", None, &SpendEstimate::default())
+            .await
+            .unwrap();
+        assert_eq!(warnings[0].projected, 2000.0);
+    }
+
+    fn manager(ledger: UsageLedger, limits: Vec<BudgetLimit>, names: &[&str]) -> LLMManager {
+        let mut manager = LLMManager::new();
+        for name in names {
+            manager.register(*name, Box::new(ScriptedProvider::new(name, Vec::new())));
+        }
+        manager.set_fallback_chain(names.iter().copied()).unwrap();
+        manager.set_usage_ledger(ledger, context());
+        manager.set_budgets(Budgets::new(limits));
+        manager
+    }
+
+    #[tokio::test]
+    async fn exhausted_provider_falls_back_to_the_next() {
+        let limits = vec![limit(Some("claude"), BudgetUnit::Usd, 0.5, 0.8)];
+        let manager = manager(ledger().await, limits, &["claude", "local"]);
+
+        let response = manager.generate(user_request("Bonjour")).await.unwrap();
+        let metadata = response.metadata.unwrap();
+        assert_eq!(metadata["provider"], "local");
+        assert_eq!(metadata["fallback_from"], "claude");
+    }
+
+    #[tokio::test]
+    async fn budget_error_when_no_provider_remains() {
+        let limits = vec![limit(Some("claude"), BudgetUnit::Usd, 0.5, 0.8)];
+        let manager = manager(ledger().await, limits, &["claude"]);
+
+        let error = manager.generate(user_request("Bonjour")).await.unwrap_err();
+        assert!(matches!(error, LLMError::BudgetExceeded(_)));
+    }
+
+    #[tokio::test]
+    async fn soft_limit_is_reported_to_the_caller() {
+        let limits = vec![limit(None, BudgetUnit::Tokens, 500.0, 10_000.0)];
+        let manager = manager(ledger().await, limits, &["claude"]);
+
+        let response = manager.generate(user_request("Bonjour")).await.unwrap();
+        let warnings = &response.metadata.unwrap()[BUDGET_WARNINGS_KEY];
+        assert!(warnings.contains("budget tokens par jour"), "{}", warnings);
+
+        let stream = manager.generate_stream(user_request("Bonjour")).await.unwrap();
+        let response = collect_response(stream, "m").await.unwrap();
+        assert!(response.metadata.unwrap().contains_key(BUDGET_WARNINGS_KEY));
+    }
+
+    #[tokio::test]
+    async fn no_warning_below_the_threshold() {
+        let limits = vec![limit(None, BudgetUnit::Tokens, 5_000.0, 10_000.0)];
+        let manager = manager(ledger().await, limits, &["claude"]);
+
+        let response = manager.generate(user_request("Bonjour")).await.unwrap();
+        assert!(!response.metadata.unwrap().contains_key(BUDGET_WARNINGS_KEY));
+    }
+}
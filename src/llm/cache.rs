@@ -0,0 +1,522 @@
+// Cache des réponses LLM : en mémoire (moka) et persisté dans SQLite (compressé zstd)
+
+use async_trait::async_trait;
+use chrono::{SecondsFormat, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::sqlite::{SqliteConnectOptions, SqlitePool, SqlitePoolOptions};
+use std::path::{Path, PathBuf};
+
+use super::middleware::{Middleware, MiddlewareContext};
+use super::{
+    FillInTheMiddle, FinishReason, LLMError, LLMRequest, LLMResponse, MessageContent,
+    ModelParameters, Role, TokenUsage, ToolCall, ToolChoice, ToolDefinition,
+};
+
+/// Nom de la base dans le répertoire de données de l'utilisateur
+const CACHE_FILE: &str = "cache.db";
+
+/// Version du format des clés : à incrémenter si la normalisation des requêtes change
+const KEY_VERSION: &str = "v1";
+
+/// Niveau de compression zstd des réponses persistées
+const COMPRESSION_LEVEL: i32 = 3;
+
+/// Attribut du contexte portant la clé de la requête en cours
+const KEY_ATTRIBUTE: &str = "cache_key";
+
+const CREATE_TABLE: &str = "CREATE TABLE IF NOT EXISTS llm_cache (
+    key TEXT PRIMARY KEY,
+    created_at TEXT NOT NULL,
+    provider TEXT NOT NULL,
+    model TEXT NOT NULL,
+    response BLOB NOT NULL
+)";
+
+
+/// Configuration du cache (`[cache]`)
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct CacheConfig {
+    pub enabled: bool,
+    /// Nombre maximal de réponses gardées en mémoire
+    pub capacity: u64,
+    /// Persiste les réponses entre deux exécutions
+    pub persist: bool,
+    /// Base SQLite (par défaut dans le répertoire de données utilisateur)
+    pub path: Option<PathBuf>,
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        CacheConfig {
+            enabled: true,
+            capacity: 1000,
+            persist: true,
+            path: None,
+        }
+    }
+}
+
+
+/// Statistiques du cache persistant
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct CacheStats {
+    pub entries: u64,
+    /// Taille compressée des réponses, en octets
+    pub bytes: u64,
+}
+
+/// Cache des réponses, indexé par un hash blake3 de la requête normalisée, du provider
+/// et du modèle.
+///
+/// Seules les requêtes déterministes (température nulle) ou marquées `LLMRequest.cache`
+/// sont concernées (voir `is_cacheable`).
+#[derive(Clone)]
+pub struct ResponseCache {
+    memory: moka::future::Cache<String, LLMResponse>,
+    store: Option<SqlitePool>,
+}
+
+impl ResponseCache {
+    /// Cache en mémoire seulement
+    pub fn in_memory(capacity: u64) -> Self {
+        ResponseCache {
+            memory: moka::future::Cache::new(capacity),
+            store: None,
+        }
+    }
+
+    /// Cache en mémoire adossé à une base SQLite (créée si besoin)
+    pub async fn open(path: &Path, capacity: u64) -> Result<Self, LLMError> {
+        if let Some(parent) = path.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent).map_err(|e| {
+                LLMError::InternalError(format!(
+                    "Création de {} impossible: {}",
+                    parent.display(),
+                    e
+                ))
+            })?;
+        }
+
+        let options = SqliteConnectOptions::new().filename(path).create_if_missing(true);
+        let pool = SqlitePoolOptions::new()
+            .max_connections(4)
+            .connect_with(options)
+            .await
+            .map_err(cache_error)?;
+        sqlx::query(CREATE_TABLE).execute(&pool).await.map_err(cache_error)?;
+
+        Ok(ResponseCache {
+            store: Some(pool),
+            ..Self::in_memory(capacity)
+        })
+    }
+
+    /// Cache décrit par la configuration (`None` s'il est désactivé)
+    pub async fn from_config(config: &CacheConfig) -> Result<Option<Self>, LLMError> {
+        if !config.enabled {
+            return Ok(None);
+        }
+        if !config.persist {
+            return Ok(Some(Self::in_memory(config.capacity)));
+        }
+
+        let path = match &config.path {
+            Some(path) => path.clone(),
+            None => Self::default_path().ok_or_else(|| {
+                cache_error("répertoire de données utilisateur introuvable")
+            })?,
+        };
+        Self::open(&path, config.capacity).await.map(Some)
+    }
+
+    /// Emplacement par défaut (`~/.local/share/codecrafter/cache.db` sous Linux)
+    pub fn default_path() -> Option<PathBuf> {
+        directories::ProjectDirs::from("", "", "codecrafter")
+            .map(|dirs| dirs.data_dir().join(CACHE_FILE))
+    }
+
+    pub async fn get(&self, key: &str) -> Result<Option<LLMResponse>, LLMError> {
+        if let Some(response) = self.memory.get(key).await {
+            return Ok(Some(response));
+        }
+        let Some(pool) = &self.store else {
+            return Ok(None);
+        };
+
+        let row: Option<(Vec<u8>,)> =
+            sqlx::query_as("SELECT response FROM llm_cache WHERE key = ?")
+                .bind(key)
+                .fetch_optional(pool)
+                .await
+                .map_err(cache_error)?;
+        let Some((compressed,)) = row else {
+            return Ok(None);
+        };
+
+        let response = decode(&compressed)?;
+        self.memory.insert(key.to_string(), response.clone()).await;
+        Ok(Some(response))
+    }
+
+    pub async fn insert(
+        &self,
+        key: &str,
+        provider: &str,
+        response: &LLMResponse,
+    ) -> Result<(), LLMError> {
+        self.memory.insert(key.to_string(), response.clone()).await;
+        let Some(pool) = &self.store else {
+            return Ok(());
+        };
+
+        sqlx::query(
+            "INSERT OR REPLACE INTO llm_cache (key, created_at, provider, model, response)
+             VALUES (?, ?, ?, ?, ?)",
+        )
+        .bind(key)
+        .bind(Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true))
+        .bind(provider)
+        .bind(&response.model)
+        .bind(encode(response)?)
+        .execute(pool)
+        .await
+        .map_err(cache_error)?;
+        Ok(())
+    }
+
+    /// Vide le cache (mémoire et base) et retourne le nombre de réponses persistées supprimées
+    pub async fn clear(&self) -> Result<u64, LLMError> {
+        self.memory.invalidate_all();
+        let Some(pool) = &self.store else {
+            return Ok(0);
+        };
+        let result = sqlx::query("DELETE FROM llm_cache")
+            .execute(pool)
+            .await
+            .map_err(cache_error)?;
+        Ok(result.rows_affected())
+    }
+
+    pub async fn stats(&self) -> Result<CacheStats, LLMError> {
+        let Some(pool) = &self.store else {
+            return Ok(CacheStats {
+                entries: self.memory.entry_count(),
+                bytes: 0,
+            });
+        };
+        let (entries, bytes): (i64, i64) = sqlx::query_as(
+            "SELECT COUNT(*), COALESCE(SUM(length(response)), 0) FROM llm_cache",
+        )
+        .fetch_one(pool)
+        .await
+        .map_err(cache_error)?;
+
+        Ok(CacheStats {
+            entries: entries as u64,
+            bytes: bytes as u64,
+        })
+    }
+}
+
+
+/// Une requête est mise en cache si elle est déterministe (température nulle) ou si
+/// l'appelant l'autorise explicitement
+pub fn is_cacheable(request: &LLMRequest) -> bool {
+    request.cache
+        || request
+            .parameters
+            .as_ref()
+            .is_some_and(|parameters| parameters.temperature == 0.0)
+}
+
+/// Requête normalisée : sans indicateur de streaming ni métadonnées, qui ne changent
+/// pas la réponse du modèle
+#[derive(Serialize)]
+struct KeyRequest<'a> {
+    version: &'static str,
+    provider: &'a str,
+    model: &'a str,
+    messages: Vec<KeyMessage<'a>>,
+    parameters: Option<&'a ModelParameters>,
+    fim: Option<&'a FillInTheMiddle>,
+    tools: &'a [ToolDefinition],
+    tool_choice: Option<&'a ToolChoice>,
+}
+
+#[derive(Serialize)]
+struct KeyMessage<'a> {
+    role: &'a Role,
+    content: &'a MessageContent,
+    tool_calls: &'a [ToolCall],
+    tool_call_id: Option<&'a str>,
+}
+
+/// Clé de cache : hash blake3 (hexadécimal) de la requête normalisée, du provider et
+/// du modèle
+pub fn cache_key(provider: &str, model: &str, request: &LLMRequest) -> String {
+    let key = KeyRequest {
+        version: KEY_VERSION,
+        provider,
+        model,
+        messages: request
+            .messages
+            .iter()
+            .map(|message| KeyMessage {
+                role: &message.role,
+                content: &message.content,
+                tool_calls: &message.tool_calls,
+                tool_call_id: message.tool_call_id.as_deref(),
+            })
+            .collect(),
+        parameters: request.parameters.as_ref(),
+        fim: request.fim.as_ref(),
+        tools: &request.tools,
+        tool_choice: request.tool_choice.as_ref(),
+    };
+
+    // La sérialisation d'une requête (sans map à ordre variable) ne peut pas échouer
+    let bytes = serde_json::to_vec(&key).unwrap_or_default();
+    blake3::hash(&bytes).to_hex().to_string()
+}
+
+fn encode(response: &LLMResponse) -> Result<Vec<u8>, LLMError> {
+    let json = serde_json::to_vec(response).map_err(cache_error)?;
+    zstd::encode_all(json.as_slice(), COMPRESSION_LEVEL).map_err(cache_error)
+}
+
+fn decode(compressed: &[u8]) -> Result<LLMResponse, LLMError> {
+    let json = zstd::decode_all(compressed).map_err(cache_error)?;
+    serde_json::from_slice(&json).map_err(cache_error)
+}
+
+fn cache_error(error: impl std::fmt::Display) -> LLMError {
+    LLMError::InternalError(format!("Cache des réponses: {}", error))
+}
+
+
+/// Middleware servant les réponses en cache ; une réponse servie par le cache ne
+/// consomme aucun token (usage nul, métadonnée `cache` = `hit`).
+///
+/// Placé en fin de pipeline, il voit la requête telle qu'envoyée au provider. Une erreur
+/// du cache n'est jamais bloquante.
+pub struct CacheMiddleware {
+    cache: ResponseCache,
+}
+
+impl CacheMiddleware {
+    pub fn new(cache: ResponseCache) -> Self {
+        CacheMiddleware { cache }
+    }
+
+    async fn store(&self, response: &LLMResponse, context: &MiddlewareContext) {
+        let Some(key) = context.attribute(KEY_ATTRIBUTE) else {
+            return;
+        };
+        // Réponses tronquées ou filtrées : une nouvelle tentative peut faire mieux
+        if !matches!(response.finish_reason, FinishReason::Stop | FinishReason::ToolUse) {
+            return;
+        }
+        if let Err(error) = self.cache.insert(&key, &context.provider, response).await {
+            tracing::warn!("Réponse non mise en cache: {}", error);
+        }
+    }
+}
+
+#[async_trait]
+impl Middleware for CacheMiddleware {
+    fn name(&self) -> &str {
+        "cache"
+    }
+
+    async fn on_request(
+        &self,
+        request: &mut LLMRequest,
+        context: &MiddlewareContext,
+    ) -> Result<Option<LLMResponse>, LLMError> {
+        if !is_cacheable(request) {
+            return Ok(None);
+        }
+
+        let key = cache_key(&context.provider, &context.model, request);
+        match self.cache.get(&key).await {
+            Ok(Some(mut response)) => {
+                tracing::debug!("Réponse servie par le cache ({})", key);
+                response.usage = TokenUsage::default();
+                response
+                    .metadata
+                    .get_or_insert_with(Default::default)
+                    .insert("cache".to_string(), "hit".to_string());
+                return Ok(Some(response));
+            }
+            Ok(None) => {}
+            Err(error) => tracing::warn!("Lecture du cache impossible: {}", error),
+        }
+
+        context.set_attribute(KEY_ATTRIBUTE, key);
+        Ok(None)
+    }
+
+    async fn on_response(
+        &self,
+        response: &mut LLMResponse,
+        context: &MiddlewareContext,
+    ) -> Result<(), LLMError> {
+        self.store(response, context).await;
+        Ok(())
+    }
+
+    async fn on_stream_end(&self, response: &LLMResponse, context: &MiddlewareContext) {
+        self.store(response, context).await;
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::middleware::MiddlewarePipeline;
+    use crate::llm::streaming::collect_response;
+    use crate::llm::test_support::{response, user_request, ScriptedProvider};
+    use std::collections::HashMap;
+    use std::sync::atomic::Ordering;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    fn with_temperature(temperature: f32) -> LLMRequest {
+        let mut request = user_request("Écris un test");
+        request.parameters = Some(ModelParameters {
+            temperature,
+            ..ModelParameters::default()
+        });
+        request
+    }
+
+    fn pipeline(cache: &ResponseCache) -> MiddlewarePipeline {
+        MiddlewarePipeline::new(vec![Arc::new(CacheMiddleware::new(cache.clone()))])
+    }
+
+    fn finished(content: &str, finish_reason: FinishReason) -> LLMResponse {
+        LLMResponse {
+            finish_reason,
+            ..response(content)
+        }
+    }
+
+    #[test]
+    fn only_deterministic_or_marked_requests_are_cacheable() {
+        assert!(is_cacheable(&with_temperature(0.0)));
+        assert!(!is_cacheable(&with_temperature(0.7)));
+        assert!(!is_cacheable(&user_request("sans paramètres")));
+
+        let mut marked = with_temperature(0.7);
+        marked.cache = true;
+        assert!(is_cacheable(&marked));
+    }
+
+    #[test]
+    fn key_ignores_streaming_but_not_provider_or_model() {
+        let request = with_temperature(0.0);
+        let key = cache_key("claude", "claude-sonnet-4-5", &request);
+
+        let mut streamed = request.clone();
+        streamed.stream = true;
+        streamed.messages[0].metadata = Some(HashMap::from([("id".into(), "1".into())]));
+        assert_eq!(cache_key("claude", "claude-sonnet-4-5", &streamed), key);
+
+        assert_ne!(cache_key("openai", "claude-sonnet-4-5", &request), key);
+        assert_ne!(cache_key("claude", "claude-opus-4", &request), key);
+        assert_ne!(cache_key("claude", "claude-sonnet-4-5", &with_temperature(0.5)), key);
+    }
+
+    #[tokio::test]
+    async fn hit_is_served_with_zero_usage() {
+        let cache = ResponseCache::in_memory(10);
+        let pipeline = pipeline(&cache);
+        let provider = ScriptedProvider::new("claude", vec![Ok(response("réponse"))]);
+
+        let first = pipeline.generate(&provider, with_temperature(0.0)).await.unwrap();
+        assert_eq!(first.usage.total_tokens, 15);
+        assert!(first.metadata.is_none());
+
+        let hit = pipeline.generate(&provider, with_temperature(0.0)).await.unwrap();
+        assert_eq!(hit.content, "réponse");
+        assert_eq!(hit.usage.prompt_tokens, 0);
+        assert_eq!(hit.usage.completion_tokens, 0);
+        assert_eq!(hit.usage.total_tokens, 0);
+        assert_eq!(hit.metadata.unwrap()["cache"], "hit");
+        assert_eq!(provider.calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn non_zero_temperature_is_not_cached() {
+        let cache = ResponseCache::in_memory(10);
+        let pipeline = pipeline(&cache);
+        let provider = ScriptedProvider::new("claude", vec![]);
+
+        for _ in 0..2 {
+            pipeline.generate(&provider, with_temperature(0.7)).await.unwrap();
+        }
+        assert_eq!(provider.calls.load(Ordering::SeqCst), 2);
+        assert_eq!(cache.stats().await.unwrap().entries, 0);
+    }
+
+    #[tokio::test]
+    async fn truncated_or_filtered_responses_are_not_stored() {
+        for finish_reason in [FinishReason::Length, FinishReason::ContentFilter] {
+            let cache = ResponseCache::in_memory(10);
+            let pipeline = pipeline(&cache);
+            let provider = ScriptedProvider::new(
+                "claude",
+                vec![Ok(finished("incomplet", finish_reason.clone())), Ok(response("complet"))],
+            );
+
+            pipeline.generate(&provider, with_temperature(0.0)).await.unwrap();
+            let second = pipeline.generate(&provider, with_temperature(0.0)).await.unwrap();
+            assert_eq!(second.content, "complet", "{:?}", finish_reason);
+            assert_eq!(provider.calls.load(Ordering::SeqCst), 2);
+
+            let third = pipeline.generate(&provider, with_temperature(0.0)).await.unwrap();
+            assert_eq!(third.content, "complet");
+            assert_eq!(provider.calls.load(Ordering::SeqCst), 2);
+        }
+    }
+
+    #[tokio::test]
+    async fn streamed_response_is_stored_at_the_end_of_the_stream() {
+        let cache = ResponseCache::in_memory(10);
+        let pipeline = pipeline(&cache);
+        let provider = ScriptedProvider::new("claude", vec![Ok(response("flux"))]);
+
+        let stream = pipeline.generate_stream(&provider, with_temperature(0.0)).await.unwrap();
+        collect_response(stream, "test-model").await.unwrap();
+
+        let key = cache_key("claude", "test-model", &with_temperature(0.0));
+        for _ in 0..100 {
+            if cache.get(&key).await.unwrap().is_some() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+
+        let stream = pipeline.generate_stream(&provider, with_temperature(0.0)).await.unwrap();
+        let hit = collect_response(stream, "test-model").await.unwrap();
+        assert_eq!(hit.content, "flux");
+        assert_eq!(provider.calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn persisted_responses_survive_a_restart() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("cache").join(CACHE_FILE);
+
+        let cache = ResponseCache::open(&path, 10).await.unwrap();
+        cache.insert("clé", "claude", &response("persistée")).await.unwrap();
+
+        let reopened = ResponseCache::open(&path, 10).await.unwrap();
+        assert_eq!(reopened.get("clé").await.unwrap().unwrap().content, "persistée");
+        assert_eq!(reopened.stats().await.unwrap().entries, 1);
+        assert_eq!(reopened.clear().await.unwrap(), 1);
+        assert!(reopened.get("absente").await.unwrap().is_none());
+    }
+}
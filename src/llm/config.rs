@@ -0,0 +1,612 @@
+// Chargement de la configuration des providers LLM
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use toml::{Table, Value};
+
+use super::budget::{BudgetLimit, Budgets};
+use super::cache::{CacheConfig, CacheMiddleware, ResponseCache};
+use super::deployment::resolve_deployment;
+use super::middleware::{MiddlewareConfig, MiddlewarePipeline};
+use super::models::ModelCatalog;
+use super::validation::{
+    display_key, locate_in, validate, ConfigIssue, ConfigReport, SourceLocation,
+};
+use super::{LLMError, LLMManager, LLMProviderConfig};
+
+/// Fichier de configuration global, dans le répertoire de configuration utilisateur
+pub const CONFIG_FILE: &str = "config.toml";
+
+/// Fichier de configuration d'un projet, recherché depuis le répertoire courant
+pub const PROJECT_CONFIG_FILE: &str = ".codecrafter.toml";
+
+/// Variable d'environnement remplaçant `default_provider`
+pub const PROVIDER_ENV: &str = "CODECRAFTER_PROVIDER";
+
+/// Variable d'environnement sélectionnant un profil
+pub const PROFILE_ENV: &str = "CODECRAFTER_PROFILE";
+
+
+/// Configuration de CodeCrafter après fusion des fichiers, du profil et de l'environnement.
+///
+/// ```toml
+/// default_provider = "claude"
+///
+/// [providers.claude]
+/// provider_type = "claude"
+/// model_name = "claude-sonnet-4-5-20250929"
+/// api_key = "${ANTHROPIC_API_KEY}"
+///
+/// [profiles.offline]
+/// default_provider = "ollama-local"
+/// ```
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CodeCrafterConfig {
+    /// Provider utilisé par défaut (sinon le premier par ordre alphabétique)
+    #[serde(default)]
+    pub default_provider: Option<String>,
+
+    /// Chaîne de fallback (voir `LLMManager::set_fallback_chain`)
+    #[serde(default)]
+    pub fallback: Vec<String>,
+
+    /// Providers nommés (`[providers.<nom>]`)
+    #[serde(default)]
+    pub providers: BTreeMap<String, LLMProviderConfig>,
+
+    /// Limites de consommation (`[[budgets]]`)
+    #[serde(default)]
+    pub budgets: Vec<BudgetLimit>,
+
+    /// Middlewares appliqués à tous les providers, dans l'ordre (`[[middleware]]`)
+    #[serde(default)]
+    pub middleware: Vec<MiddlewareConfig>,
+
+    /// Cache des réponses déterministes (`[cache]`)
+    #[serde(default)]
+    pub cache: CacheConfig,
+}
+
+impl CodeCrafterConfig {
+    /// Charge la configuration avec les emplacements par défaut (voir `ConfigLoader::new`)
+    pub fn load() -> Result<Self, LLMError> {
+        ConfigLoader::new().load()
+    }
+
+    pub fn provider(&self, name: &str) -> Option<&LLMProviderConfig> {
+        self.providers.get(name)
+    }
+
+    /// Construit le gestionnaire : providers (mode `Auto` résolu), provider par défaut,
+    /// fallback, budgets, middlewares, cache des réponses et catalogue des modèles (avec
+    /// les surcharges de l'utilisateur)
+    pub async fn build_manager(&self) -> Result<LLMManager, LLMError> {
+        let mut providers = BTreeMap::new();
+        for (name, config) in &self.providers {
+            providers.insert(name.clone(), resolve_deployment(config.clone()).await);
+        }
+
+        let mut manager = LLMManager::from_configs(providers)?;
+        if let Some(name) = &self.default_provider {
+            manager.set_default(name)?;
+        }
+        if !self.fallback.is_empty() {
+            manager.set_fallback_chain(self.fallback.clone())?;
+        }
+        manager.set_budgets(Budgets::new(self.budgets.clone()));
+
+        // Le cache ferme le pipeline : sa clé porte sur la requête envoyée au provider
+        let mut pipeline = MiddlewarePipeline::from_configs(&self.middleware)?;
+        match ResponseCache::from_config(&self.cache).await {
+            Ok(Some(cache)) => pipeline.push(Arc::new(CacheMiddleware::new(cache))),
+            Ok(None) => {}
+            Err(error) => tracing::warn!("Cache des réponses désactivé: {}", error),
+        }
+        manager.set_pipeline(pipeline);
+        manager.set_catalog(ModelCatalog::load()?);
+        Ok(manager)
+    }
+}
+
+
+/// Fichier de configuration lu, après interpolation des variables d'environnement
+#[derive(Debug, Clone)]
+pub struct ConfigLayer {
+    pub path: PathBuf,
+    /// Contenu brut (pour situer les clés lors de la validation)
+    pub content: String,
+    pub table: Table,
+}
+
+/// Chargement de la configuration par couches, de la moins à la plus prioritaire :
+///
+/// 1. fichier global (`~/.config/codecrafter/config.toml` sous Linux) ;
+/// 2. fichier du projet (`.codecrafter.toml`, dans le répertoire courant ou un parent) ;
+/// 3. profil sélectionné (`[profiles.<nom>]`, via `profile` ou `CODECRAFTER_PROFILE`) ;
+/// 4. variables d'environnement (`CODECRAFTER_PROVIDER`).
+///
+/// Les tables sont fusionnées clé par clé ; les autres valeurs sont remplacées.
+/// La configuration obtenue est validée (voir `check`) avant d'être retournée.
+#[derive(Debug, Clone)]
+pub struct ConfigLoader {
+    global_file: Option<PathBuf>,
+    project_dir: Option<PathBuf>,
+    profile: Option<String>,
+}
+
+impl Default for ConfigLoader {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ConfigLoader {
+    pub fn new() -> Self {
+        ConfigLoader {
+            global_file: global_config_path(),
+            project_dir: std::env::current_dir().ok(),
+            profile: non_empty_env(PROFILE_ENV),
+        }
+    }
+
+    /// Remplace le fichier global (`None` : pas de fichier global)
+    pub fn global_file(mut self, path: Option<PathBuf>) -> Self {
+        self.global_file = path;
+        self
+    }
+
+    /// Répertoire à partir duquel `.codecrafter.toml` est recherché (`None` : aucun)
+    pub fn project_dir(mut self, dir: Option<PathBuf>) -> Self {
+        self.project_dir = dir;
+        self
+    }
+
+    /// Sélectionne un profil (prioritaire sur `CODECRAFTER_PROFILE`)
+    pub fn profile(mut self, profile: Option<String>) -> Self {
+        if profile.is_some() {
+            self.profile = profile;
+        }
+        self
+    }
+
+    /// Fichiers existants, dans l'ordre de fusion
+    pub fn files(&self) -> Vec<PathBuf> {
+        let global = self.global_file.clone().filter(|path| path.is_file());
+        let project = self.project_dir.as_deref().and_then(find_project_config);
+        global.into_iter().chain(project).collect()
+    }
+
+    /// Lit et interpole chaque fichier de configuration
+    pub fn load_layers(&self) -> Result<Vec<ConfigLayer>, LLMError> {
+        let (layers, issues) = self.read_layers();
+        ConfigReport {
+            issues,
+            ..ConfigReport::default()
+        }
+        .into_result()?;
+        Ok(layers)
+    }
+
+    /// Vérifie la configuration et retourne tous les problèmes détectés
+    pub fn check(&self) -> ConfigReport {
+        self.evaluate().0
+    }
+
+    /// Charge la configuration ; les erreurs de validation sont regroupées dans un
+    /// seul `LLMError::InvalidConfig`, les avertissements sont journalisés
+    pub fn load(&self) -> Result<CodeCrafterConfig, LLMError> {
+        let (report, config) = self.evaluate();
+        for warning in report.warnings() {
+            tracing::warn!("Configuration: {}", warning);
+        }
+        report.into_result()?;
+        config.ok_or_else(|| LLMError::InternalError("Configuration non construite".to_string()))
+    }
+
+    /// Lecture, fusion, validation puis désérialisation
+    fn evaluate(&self) -> (ConfigReport, Option<CodeCrafterConfig>) {
+        let mut report = ConfigReport {
+            files: self.files(),
+            profile: self.profile.clone(),
+            issues: Vec::new(),
+        };
+
+        let (layers, issues) = self.read_layers();
+        if !issues.is_empty() {
+            report.issues = issues;
+            return (report, None);
+        }
+
+        let mut root = Table::new();
+        for layer in &layers {
+            merge_tables(&mut root, layer.table.clone());
+        }
+
+        let profiles = root.remove("profiles");
+        if let Some(name) = &self.profile {
+            match profiles.as_ref().and_then(|profiles| profiles.get(name)) {
+                Some(Value::Table(profile)) => merge_tables(&mut root, profile.clone()),
+                _ => {
+                    report.issues.push(unknown_profile(name, profiles.as_ref()));
+                    return (report, None);
+                }
+            }
+        }
+
+        let provider_env = non_empty_env(PROVIDER_ENV);
+        if let Some(provider) = &provider_env {
+            root.insert("default_provider".to_string(), Value::String(provider.clone()));
+        }
+
+        report.issues = validate(&root, &layers, self.profile.as_deref(), provider_env.is_some());
+        if report.has_errors() {
+            return (report, None);
+        }
+
+        match Value::Table(root).try_into() {
+            Ok(config) => (report, Some(config)),
+            Err(e) => {
+                let message = format!("Configuration invalide: {}", e);
+                report.issues.push(ConfigIssue::error("", message));
+                (report, None)
+            }
+        }
+    }
+
+    /// Lit chaque fichier ; les erreurs de syntaxe et d'interpolation sont des problèmes
+    /// situés dans leur fichier
+    fn read_layers(&self) -> (Vec<ConfigLayer>, Vec<ConfigIssue>) {
+        let mut layers = Vec::new();
+        let mut issues = Vec::new();
+
+        for path in self.files() {
+            let content = match std::fs::read_to_string(&path) {
+                Ok(content) => content,
+                Err(e) => {
+                    let message = format!("Lecture de {} impossible: {}", path.display(), e);
+                    issues.push(ConfigIssue::error("", message));
+                    continue;
+                }
+            };
+
+            let mut table: Table = match content.parse::<Table>() {
+                Ok(table) => table,
+                Err(e) => {
+                    let line = e.span().map(|span| line_of(&content, span.start));
+                    let issue = ConfigIssue::error("", e.message().to_string())
+                        .at(line.map(|line| SourceLocation { path: path.clone(), line }));
+                    issues.push(issue);
+                    continue;
+                }
+            };
+
+            let lookup = |name: &str| std::env::var(name).ok();
+            if let Err((key_path, message)) = interpolate_table(&mut table, &[], &lookup) {
+                let issue = ConfigIssue::error(display_key(&key_path), message)
+                    .at(locate_in(&path, &content, &key_path));
+                issues.push(issue);
+                continue;
+            }
+
+            layers.push(ConfigLayer { path, content, table });
+        }
+
+        (layers, issues)
+    }
+}
+
+/// Emplacement du fichier de configuration global
+pub fn global_config_path() -> Option<PathBuf> {
+    directories::ProjectDirs::from("", "", "codecrafter")
+        .map(|dirs| dirs.config_dir().join(CONFIG_FILE))
+}
+
+/// Cherche `.codecrafter.toml` dans `dir` puis dans ses parents
+pub fn find_project_config(dir: &Path) -> Option<PathBuf> {
+    dir.ancestors()
+        .map(|ancestor| ancestor.join(PROJECT_CONFIG_FILE))
+        .find(|path| path.is_file())
+}
+
+fn non_empty_env(name: &str) -> Option<String> {
+    std::env::var(name).ok().filter(|value| !value.is_empty())
+}
+
+fn unknown_profile(name: &str, profiles: Option<&Value>) -> ConfigIssue {
+    let available: Vec<&str> = profiles
+        .and_then(Value::as_table)
+        .map(|profiles| profiles.keys().map(String::as_str).collect())
+        .unwrap_or_default();
+    let issue = ConfigIssue::error(PROFILE_ENV, format!("profil inconnu `{}`", name));
+    if available.is_empty() {
+        issue.with_suggestion(format!("définir une table [profiles.{}]", name))
+    } else {
+        issue.with_suggestion(format!("profils disponibles: {}", available.join(", ")))
+    }
+}
+
+fn line_of(content: &str, offset: usize) -> usize {
+    content[..offset.min(content.len())].matches('\n').count() + 1
+}
+
+/// Fusionne `overlay` dans `base` : les tables sont fusionnées récursivement,
+/// les autres valeurs remplacées
+fn merge_tables(base: &mut Table, overlay: Table) {
+    for (key, value) in overlay {
+        match (base.get_mut(&key), value) {
+            (Some(Value::Table(existing)), Value::Table(table)) => merge_tables(existing, table),
+            (_, value) => {
+                base.insert(key, value);
+            }
+        }
+    }
+}
+
+
+/// Remplace `${VAR}` et `${VAR:-défaut}` dans les chaînes de la table (`$$` pour un `$`).
+///
+/// Une valeur réduite à une variable non définie est retirée, comme si elle était
+/// absente du fichier ; une variable non définie au milieu d'un texte est une erreur,
+/// retournée avec le chemin de la clé.
+fn interpolate_table(
+    table: &mut Table,
+    prefix: &[String],
+    lookup: &dyn Fn(&str) -> Option<String>,
+) -> Result<(), (Vec<String>, String)> {
+    let mut unset = Vec::new();
+    for (key, value) in table.iter_mut() {
+        let mut path = prefix.to_vec();
+        path.push(key.clone());
+        match value {
+            Value::String(text) => match interpolate(text, lookup).map_err(|e| (path.clone(), e))? {
+                Some(resolved) => *text = resolved,
+                None => {
+                    let full_key = display_key(&path);
+                    tracing::debug!("{}: variable non définie, valeur ignorée", full_key);
+                    unset.push(key.clone());
+                }
+            },
+            Value::Table(inner) => interpolate_table(inner, &path, lookup)?,
+            Value::Array(items) => interpolate_array(items, &path, lookup)?,
+            _ => {}
+        }
+    }
+    for key in unset {
+        table.remove(&key);
+    }
+    Ok(())
+}
+
+fn interpolate_array(
+    items: &mut [Value],
+    prefix: &[String],
+    lookup: &dyn Fn(&str) -> Option<String>,
+) -> Result<(), (Vec<String>, String)> {
+    for (i, item) in items.iter_mut().enumerate() {
+        let mut path = prefix.to_vec();
+        path.push(i.to_string());
+        match item {
+            Value::String(text) => {
+                let undefined = format!("variable non définie dans `{}`", text);
+                *text = interpolate(text, lookup)
+                    .map_err(|e| (path.clone(), e))?
+                    .ok_or((path, undefined))?;
+            }
+            Value::Table(inner) => interpolate_table(inner, &path, lookup)?,
+            Value::Array(inner) => interpolate_array(inner, &path, lookup)?,
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// Interpole une chaîne ; `None` si elle se réduit à une variable non définie
+fn interpolate(
+    text: &str,
+    lookup: &dyn Fn(&str) -> Option<String>,
+) -> Result<Option<String>, String> {
+    let mut resolved = String::with_capacity(text.len());
+    let mut rest = text;
+
+    while let Some(start) = rest.find('$') {
+        resolved.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+
+        if let Some(after) = after.strip_prefix('$') {
+            resolved.push('$');
+            rest = after;
+            continue;
+        }
+        let Some(body) = after.strip_prefix('{') else {
+            resolved.push('$');
+            rest = after;
+            continue;
+        };
+
+        let end = body.find('}').ok_or_else(|| format!("`${{` non fermé dans `{}`", text))?;
+        let expression = &body[..end];
+        let (name, default) = match expression.split_once(":-") {
+            Some((name, default)) => (name, Some(default)),
+            None => (expression, None),
+        };
+
+        match (lookup(name.trim()), default) {
+            (Some(value), _) => resolved.push_str(&value),
+            (None, Some(default)) => resolved.push_str(default),
+            (None, None) if text.trim() == format!("${{{}}}", expression) => return Ok(None),
+            (None, None) => {
+                return Err(format!("variable d'environnement {} non définie", name.trim()))
+            }
+        }
+        rest = &body[end + 1..];
+    }
+
+    resolved.push_str(rest);
+    Ok(Some(resolved))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn lookup(name: &str) -> Option<String> {
+        HashMap::from([("HOME_DIR", "/home/dev"), ("API_KEY", "sk-test")])
+            .get(name)
+            .map(|value| value.to_string())
+    }
+
+    fn write(path: &Path, content: &str) {
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, content).unwrap();
+    }
+
+    const GLOBAL: &str = r#"
+default_provider = "claude"
+
+[providers.claude]
+provider_type = "claude"
+model_name = "claude-sonnet-4-5"
+api_key = "${CODECRAFTER_TEST_UNSET_KEY:-sk-test}"
+
+[providers.claude.parameters]
+temperature = 0.2
+max_tokens = 2048
+
+[providers.local]
+provider_type = "ollama"
+model_name = "${CODECRAFTER_TEST_UNSET_MODEL:-qwen2.5-coder}"
+deployment = "local"
+
+[profiles.offline]
+default_provider = "local"
+"#;
+
+    const PROJECT: &str = r#"
+[providers.claude.parameters]
+max_tokens = 8192
+"#;
+
+    #[test]
+    fn interpolation() {
+        let interpolate = |text: &str| interpolate(text, &lookup);
+
+        assert_eq!(interpolate("${API_KEY}"), Ok(Some("sk-test".to_string())));
+        assert_eq!(
+            interpolate("${HOME_DIR}/projets/${ NOM :-demo}"),
+            Ok(Some("/home/dev/projets/demo".to_string()))
+        );
+        assert_eq!(interpolate("coût: 5$ ou $$5"), Ok(Some("coût: 5$ ou $5".to_string())));
+        assert_eq!(interpolate("${ABSENTE}"), Ok(None));
+        assert!(interpolate("clé-${ABSENTE}").unwrap_err().contains("ABSENTE"));
+        assert!(interpolate("${API_KEY").is_err());
+    }
+
+    #[test]
+    fn unset_values_are_removed_from_tables() {
+        let mut table: Table = r#"
+            api_key = "${ABSENTE}"
+            base_url = "${HOME_DIR:-x}"
+            stop = ["${API_KEY}"]
+        "#
+        .parse()
+        .unwrap();
+        interpolate_table(&mut table, &[], &lookup).unwrap();
+
+        assert!(!table.contains_key("api_key"));
+        assert_eq!(table["base_url"].as_str(), Some("/home/dev"));
+        assert_eq!(table["stop"][0].as_str(), Some("sk-test"));
+
+        let mut table: Table = "[a]\nkey = \"${ABSENTE}\"".parse().unwrap();
+        interpolate_table(&mut table, &[], &lookup).unwrap();
+        assert_eq!(table["a"].as_table().map(Table::len), Some(0));
+
+        let mut table: Table = "[a]\nstop = [\"${ABSENTE}\"]".parse().unwrap();
+        let (path, _) = interpolate_table(&mut table, &[], &lookup).unwrap_err();
+        assert_eq!(path, ["a", "stop", "0"]);
+    }
+
+    #[test]
+    fn tables_merge_key_by_key() {
+        let mut base: Table = "a = 1\n[t]\nx = 1\ny = [1]".parse().unwrap();
+        let overlay: Table = "a = 2\n[t]\ny = [2]\nz = 3".parse().unwrap();
+        merge_tables(&mut base, overlay);
+
+        let expected: Table = "a = 2\n[t]\nx = 1\ny = [2]\nz = 3".parse().unwrap();
+        assert_eq!(base, expected);
+    }
+
+    #[test]
+    fn project_file_overrides_global_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let global = dir.path().join("config").join(CONFIG_FILE);
+        write(&global, GLOBAL);
+        // Le fichier du projet est trouvé depuis un sous-répertoire
+        write(&dir.path().join("projet").join(PROJECT_CONFIG_FILE), PROJECT);
+        let nested = dir.path().join("projet").join("src");
+        std::fs::create_dir_all(&nested).unwrap();
+
+        let loader = ConfigLoader::new()
+            .global_file(Some(global.clone()))
+            .project_dir(Some(nested));
+        assert_eq!(loader.files().len(), 2);
+        assert_eq!(loader.files()[0], global);
+
+        let config = loader.load().unwrap();
+        assert_eq!(config.default_provider.as_deref(), Some("claude"));
+        let claude = config.provider("claude").unwrap();
+        assert_eq!(claude.parameters.max_tokens, 8192);
+        assert_eq!(claude.parameters.temperature, 0.2);
+        assert_eq!(claude.api_key, Some("sk-test".into()));
+        assert_eq!(config.provider("local").unwrap().model_name, "qwen2.5-coder");
+    }
+
+    #[test]
+    fn profile_is_applied_last() {
+        let dir = tempfile::tempdir().unwrap();
+        let global = dir.path().join(CONFIG_FILE);
+        write(&global, GLOBAL);
+        let loader = ConfigLoader::new().global_file(Some(global)).project_dir(None);
+
+        let config = loader.clone().profile(Some("offline".to_string())).load().unwrap();
+        assert_eq!(config.default_provider.as_deref(), Some("local"));
+
+        let report = loader.profile(Some("avion".to_string())).check();
+        let issue = report.errors().next().unwrap().to_string();
+        assert!(issue.contains("avion") && issue.contains("offline"), "{}", issue);
+    }
+
+    #[test]
+    fn unset_api_key_is_reported_as_missing() {
+        let dir = tempfile::tempdir().unwrap();
+        let global = dir.path().join(CONFIG_FILE);
+        write(&global, &GLOBAL.replace(":-sk-test", ""));
+
+        let loader = ConfigLoader::new().global_file(Some(global.clone())).project_dir(None);
+        let issue = loader.check().errors().next().unwrap().to_string();
+        assert!(issue.starts_with(&format!("{}:4: ", global.display())), "{}", issue);
+        assert!(issue.contains("api_key manquante"), "{}", issue);
+    }
+
+    #[test]
+    fn syntax_errors_are_located() {
+        let dir = tempfile::tempdir().unwrap();
+        let global = dir.path().join(CONFIG_FILE);
+        write(&global, "default_provider = \"claude\"\n\n[providers\n");
+
+        let loader = ConfigLoader::new().global_file(Some(global.clone())).project_dir(None);
+        let issue = loader.check().errors().next().unwrap().to_string();
+        assert!(issue.starts_with(&format!("{}:3: ", global.display())), "{}", issue);
+    }
+
+    #[test]
+    fn no_file_gives_an_empty_config() {
+        let config = ConfigLoader::new().global_file(None).project_dir(None).load().unwrap();
+        assert!(config.providers.is_empty());
+        assert!(config.default_provider.is_none());
+    }
+}
@@ -0,0 +1,298 @@
+// Résolution du mode de déploiement `Auto` : analyse de l'URL, puis sondage des serveurs
+// locaux connus
+
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::time::Duration;
+
+use super::{default_timeout_seconds, DeploymentMode, LLMProviderConfig, LLMProviderType};
+
+/// Timeout (secondes) appliqué aux modèles locaux quand la configuration garde la valeur
+/// par défaut : l'inférence sur CPU est bien plus lente qu'une API distante
+pub const LOCAL_TIMEOUT_SECONDS: u64 = 600;
+
+/// Durée maximale d'un sondage (résolution DNS ou requête HTTP)
+const PROBE_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// Serveurs locaux connus : URL de base et route de contrôle
+const OLLAMA_ENDPOINTS: &[(&str, &str)] = &[
+    ("http://localhost:11434", "/api/tags"),
+    ("http://127.0.0.1:11434", "/api/tags"),
+];
+const LLAMACPP_ENDPOINTS: &[(&str, &str)] = &[
+    ("http://localhost:8080", "/health"),
+    ("http://127.0.0.1:8080", "/health"),
+];
+
+/// Classe une URL de base sans accès réseau.
+///
+/// Sockets unix, adresses de boucle locale, privées ou de lien local et noms `localhost`,
+/// `*.localhost` ou `*.local` sont locaux ; les autres adresses IP sont distantes. Retourne
+/// `None` pour un nom d'hôte qu'il faudrait résoudre.
+pub fn classify_base_url(base_url: &str) -> Option<DeploymentMode> {
+    let base_url = base_url.trim();
+    if base_url.starts_with('/') {
+        return Some(DeploymentMode::Local);
+    }
+
+    let url = url::Url::parse(base_url).ok()?;
+    if matches!(url.scheme(), "unix" | "http+unix" | "https+unix") {
+        return Some(DeploymentMode::Local);
+    }
+
+    match url.host()? {
+        url::Host::Ipv4(ip) => Some(mode_for(is_local_ip(&IpAddr::V4(ip)))),
+        url::Host::Ipv6(ip) => Some(mode_for(is_local_ip(&IpAddr::V6(ip)))),
+        url::Host::Domain(host) => {
+            let host = host.trim_end_matches('.').to_ascii_lowercase();
+            let local = host == "localhost"
+                || host.ends_with(".localhost")
+                || host.ends_with(".local");
+            local.then_some(DeploymentMode::Local)
+        }
+    }
+}
+
+/// Adresse de boucle locale, privée, de lien local ou non spécifiée
+pub fn is_local_ip(ip: &IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => is_local_ipv4(ip),
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(ip) => is_local_ipv4(&ip),
+            None => is_local_ipv6(ip),
+        },
+    }
+}
+
+fn is_local_ipv4(ip: &Ipv4Addr) -> bool {
+    let [first, second, ..] = ip.octets();
+    ip.is_loopback()
+        || ip.is_private()
+        || ip.is_link_local()
+        || ip.is_unspecified()
+        // Espace partagé 100.64.0.0/10 (CGNAT, réseaux de type Tailscale)
+        || (first == 100 && (second & 0xc0) == 64)
+}
+
+fn is_local_ipv6(ip: &Ipv6Addr) -> bool {
+    let first = ip.segments()[0];
+    ip.is_loopback()
+        || ip.is_unspecified()
+        // Adresses uniques locales fc00::/7 et de lien local fe80::/10
+        || (first & 0xfe00) == 0xfc00
+        || (first & 0xffc0) == 0xfe80
+}
+
+fn mode_for(local: bool) -> DeploymentMode {
+    if local {
+        DeploymentMode::Local
+    } else {
+        DeploymentMode::Remote
+    }
+}
+
+/// Résout le mode `Auto` d'une configuration et applique les valeurs par défaut du mode.
+///
+/// Un mode explicite est conservé. Sinon, l'URL de base est classée (avec résolution DNS
+/// si nécessaire) ; sans URL, les serveurs Ollama et llama.cpp locaux sont sondés et
+/// l'URL de celui qui répond est retenue. En local, le timeout par défaut est allongé
+/// et aucune clé API n'est exigée.
+pub async fn resolve_deployment(mut config: LLMProviderConfig) -> LLMProviderConfig {
+    if config.deployment == DeploymentMode::Auto {
+        config.deployment = detect(&mut config).await;
+        tracing::debug!(
+            "Mode de déploiement détecté pour {}: {:?}",
+            config.model_name,
+            config.deployment
+        );
+    }
+
+    if config.deployment == DeploymentMode::Local
+        && config.timeout_seconds == default_timeout_seconds()
+    {
+        config.timeout_seconds = LOCAL_TIMEOUT_SECONDS;
+    }
+    config
+}
+
+async fn detect(config: &mut LLMProviderConfig) -> DeploymentMode {
+    let base_url = config.base_url.clone().filter(|url| !url.trim().is_empty());
+    if let Some(base_url) = base_url {
+        return match classify_base_url(&base_url) {
+            Some(mode) => mode,
+            None => resolve_host(&base_url).await,
+        };
+    }
+
+    let endpoints = match config.provider_type {
+        LLMProviderType::Ollama => OLLAMA_ENDPOINTS,
+        LLMProviderType::LlamaCpp => LLAMACPP_ENDPOINTS,
+        _ => return DeploymentMode::Remote,
+    };
+    match probe(endpoints).await {
+        Some(base_url) => {
+            config.base_url = Some(base_url.to_string());
+        }
+        None => tracing::warn!(
+            "Aucun serveur {:?} local ne répond ({}), mode local supposé",
+            config.provider_type,
+            endpoints
+                .iter()
+                .map(|(base_url, _)| *base_url)
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+    }
+    DeploymentMode::Local
+}
+
+/// Résout le nom d'hôte : local seulement si toutes les adresses obtenues le sont
+async fn resolve_host(base_url: &str) -> DeploymentMode {
+    let Some((host, port)) = url::Url::parse(base_url).ok().and_then(|url| {
+        Some((url.host_str()?.to_string(), url.port_or_known_default().unwrap_or(80)))
+    }) else {
+        return DeploymentMode::Remote;
+    };
+
+    let lookup = tokio::net::lookup_host((host.as_str(), port));
+    let addresses: Vec<_> = match tokio::time::timeout(PROBE_TIMEOUT, lookup).await {
+        Ok(Ok(addresses)) => addresses.map(|address| address.ip()).collect(),
+        _ => return DeploymentMode::Remote,
+    };
+    mode_for(!addresses.is_empty() && addresses.iter().all(is_local_ip))
+}
+
+/// Première URL de base dont la route de contrôle répond avec succès
+async fn probe(endpoints: &[(&'static str, &str)]) -> Option<&'static str> {
+    let client = reqwest::Client::builder().timeout(PROBE_TIMEOUT).build().ok()?;
+    for (base_url, path) in endpoints {
+        let response = client.get(format!("{}{}", base_url, path)).send().await;
+        if response.is_ok_and(|response| response.status().is_success()) {
+            return Some(base_url);
+        }
+    }
+    None
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::test_support::config;
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    #[test]
+    fn base_urls_are_classified_without_network() {
+        let cases = [
+            ("http://localhost:11434", Some(DeploymentMode::Local)),
+            ("http://LocalHost.:8080", Some(DeploymentMode::Local)),
+            ("http://gpu.localhost", Some(DeploymentMode::Local)),
+            ("http://workstation.local:8080", Some(DeploymentMode::Local)),
+            ("http://127.0.0.1:8080", Some(DeploymentMode::Local)),
+            ("http://192.168.1.20:11434", Some(DeploymentMode::Local)),
+            ("http://100.101.102.103:11434", Some(DeploymentMode::Local)),
+            ("http://[::1]:8080", Some(DeploymentMode::Local)),
+            ("http://[fd12:3456::1]", Some(DeploymentMode::Local)),
+            ("/run/ollama.sock", Some(DeploymentMode::Local)),
+            ("unix:///run/llama.sock", Some(DeploymentMode::Local)),
+            ("https://8.8.8.8", Some(DeploymentMode::Remote)),
+            ("http://[2001:db8::1]", Some(DeploymentMode::Remote)),
+            ("https://api.anthropic.com", None),
+            ("pas une url", None),
+        ];
+        for (url, expected) in cases {
+            assert_eq!(classify_base_url(url), expected, "{}", url);
+        }
+    }
+
+    #[test]
+    fn local_addresses() {
+        let local = [
+            "127.0.0.1",
+            "10.1.2.3",
+            "172.16.0.1",
+            "192.168.0.1",
+            "169.254.1.1",
+            "0.0.0.0",
+            "100.64.0.1",
+            "100.127.255.255",
+            "::1",
+            "::",
+            "fc00::1",
+            "fe80::1",
+            "::ffff:192.168.0.1",
+        ];
+        for ip in local {
+            assert!(is_local_ip(&ip.parse().unwrap()), "{}", ip);
+        }
+
+        let remote = ["8.8.8.8", "100.128.0.1", "172.32.0.1", "2001:db8::1", "::ffff:8.8.8.8"];
+        for ip in remote {
+            assert!(!is_local_ip(&ip.parse().unwrap()), "{}", ip);
+        }
+    }
+
+    #[tokio::test]
+    async fn explicit_mode_is_kept() {
+        let mut remote = config(LLMProviderType::Ollama, "llama3", "http://localhost:11434");
+        remote.deployment = DeploymentMode::Remote;
+        let resolved = resolve_deployment(remote).await;
+        assert_eq!(resolved.deployment, DeploymentMode::Remote);
+        assert_eq!(resolved.base_url.as_deref(), Some("http://localhost:11434"));
+    }
+
+    #[tokio::test]
+    async fn auto_mode_follows_the_base_url() {
+        let mut local = config(LLMProviderType::Ollama, "llama3", "http://127.0.0.1:11434");
+        local.deployment = DeploymentMode::Auto;
+        local.timeout_seconds = default_timeout_seconds();
+        let resolved = resolve_deployment(local).await;
+        assert_eq!(resolved.deployment, DeploymentMode::Local);
+        assert_eq!(resolved.timeout_seconds, LOCAL_TIMEOUT_SECONDS);
+
+        let mut remote = config(LLMProviderType::OpenAI, "gpt-4o", "https://8.8.8.8/v1");
+        remote.deployment = DeploymentMode::Auto;
+        remote.timeout_seconds = default_timeout_seconds();
+        let resolved = resolve_deployment(remote).await;
+        assert_eq!(resolved.deployment, DeploymentMode::Remote);
+        assert_eq!(resolved.timeout_seconds, default_timeout_seconds());
+    }
+
+    #[tokio::test]
+    async fn configured_timeout_is_kept_in_local_mode() {
+        let mut local = config(LLMProviderType::LlamaCpp, "qwen", "http://localhost:8080");
+        local.deployment = DeploymentMode::Local;
+        local.timeout_seconds = 30;
+        assert_eq!(resolve_deployment(local).await.timeout_seconds, 30);
+    }
+
+    #[tokio::test]
+    async fn cloud_provider_without_url_is_remote() {
+        let mut claude = config(LLMProviderType::Claude, "claude-sonnet-4-5", "");
+        claude.base_url = None;
+        claude.deployment = DeploymentMode::Auto;
+        assert_eq!(resolve_deployment(claude).await.deployment, DeploymentMode::Remote);
+    }
+
+    #[tokio::test]
+    async fn probe_returns_the_first_server_that_answers() {
+        let down = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/health"))
+            .respond_with(ResponseTemplate::new(503))
+            .mount(&down)
+            .await;
+        let up = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/health"))
+            .respond_with(ResponseTemplate::new(200))
+            .mount(&up)
+            .await;
+
+        let down_url: &'static str = Box::leak(down.uri().into_boxed_str());
+        let up_url: &'static str = Box::leak(up.uri().into_boxed_str());
+        let endpoints = [(down_url, "/health"), (up_url, "/health")];
+        assert_eq!(probe(&endpoints).await, Some(up_url));
+        assert_eq!(probe(&endpoints[..1]).await, None);
+    }
+}
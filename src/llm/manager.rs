@@ -0,0 +1,757 @@
+// Gestionnaire des providers LLM : providers nommés, provider par défaut et fallback
+
+use futures::StreamExt;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use super::budget::{Budgets, SpendEstimate};
+use super::middleware::{MiddlewarePipeline, PreparedCall};
+use super::models::{ModelCatalog, ModelInfo};
+use super::providers::create_provider;
+use super::retry::{is_retryable, RetryProvider};
+use super::streaming::tee_response;
+use super::usage::{UsageContext, UsageLedger, UsageRecord};
+use super::{LLMError, LLMProvider, LLMProviderConfig, LLMRequest, LLMResponse, LLMStream};
+
+/// Durée pendant laquelle le résultat d'un `health_check` est réutilisé
+const HEALTH_TTL: Duration = Duration::from_secs(60);
+
+/// Clé des métadonnées portant les seuils d'alerte de budget franchis
+pub const BUDGET_WARNINGS_KEY: &str = "budget_warnings";
+
+
+/// Gestionnaire des providers LLM.
+///
+/// Chaque provider est enregistré sous un nom (ex: "claude", "local"). Une requête
+/// est exécutée sur la chaîne de fallback (ex: Claude distant → Ollama local) : on
+/// passe au provider suivant lorsque le précédent renvoie une erreur transitoire
+/// (voir `retry::is_retryable`) ou échoue à son `health_check`.
+///
+/// Avant l'envoi, la requête est vérifiée contre le catalogue des modèles (`max_tokens`,
+/// fenêtre de contexte, outils, images) ; un provider dont le modèle ne peut pas la
+/// traiter est écarté comme un provider indisponible.
+///
+/// Si un journal d'usage est configuré, chaque réponse y est enregistrée avec son coût
+/// et les budgets sont vérifiés avant l'envoi. Un provider dont une limite stricte
+/// serait dépassée est écarté comme un provider indisponible : la requête passe au
+/// suivant de la chaîne (ex: un modèle local gratuit) et `LLMError::BudgetExceeded`
+/// n'est retournée que si aucun autre provider ne peut répondre. Les limites souples
+/// franchies sont signalées dans les métadonnées de la réponse (clé `budget_warnings`).
+///
+/// Les appels passent par le pipeline de middlewares (voir `middleware`), commun à
+/// tous les providers. Ses hooks de requête s'exécutent avant les vérifications de
+/// budget et de santé : une réponse servie par le cache est retournée même si le
+/// provider est indisponible ou son budget épuisé.
+pub struct LLMManager {
+    providers: HashMap<String, Box<dyn LLMProvider>>,
+    /// Configurations des providers créés par `add_config` (pour la validation)
+    configs: HashMap<String, LLMProviderConfig>,
+    catalog: ModelCatalog,
+    usage: Option<(UsageLedger, UsageContext)>,
+    budgets: Budgets,
+    pipeline: MiddlewarePipeline,
+    default: Option<String>,
+    fallback: Vec<String>,
+    health: Mutex<HashMap<String, (bool, Instant)>>,
+}
+
+impl Default for LLMManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LLMManager {
+    pub fn new() -> Self {
+        LLMManager {
+            providers: HashMap::new(),
+            configs: HashMap::new(),
+            catalog: ModelCatalog::builtin(),
+            usage: None,
+            budgets: Budgets::default(),
+            pipeline: MiddlewarePipeline::default(),
+            default: None,
+            fallback: Vec::new(),
+            health: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Construit le gestionnaire à partir de configurations nommées.
+    ///
+    /// Le premier provider devient le provider par défaut.
+    pub fn from_configs<I>(configs: I) -> Result<Self, LLMError>
+    where
+        I: IntoIterator<Item = (String, LLMProviderConfig)>,
+    {
+        let mut manager = Self::new();
+        for (name, config) in configs {
+            manager.add_config(name, config)?;
+        }
+        Ok(manager)
+    }
+
+    /// Crée le provider décrit par la configuration (avec `max_retries` et
+    /// `timeout_seconds` appliqués) et l'enregistre sous `name`
+    pub fn add_config(
+        &mut self,
+        name: impl Into<String>,
+        config: LLMProviderConfig,
+    ) -> Result<(), LLMError> {
+        let name = name.into();
+        let provider = RetryProvider::from_config(create_provider(config.clone())?, &config);
+        self.register(name.clone(), Box::new(provider));
+        self.configs.insert(name, config);
+        Ok(())
+    }
+
+    /// Enregistre un provider déjà construit (remplace un provider du même nom).
+    ///
+    /// Sa configuration n'étant pas connue, ses requêtes ne sont pas validées.
+    pub fn register(&mut self, name: impl Into<String>, provider: Box<dyn LLMProvider>) {
+        let name = name.into();
+        if self.default.is_none() {
+            self.default = Some(name.clone());
+        }
+        self.configs.remove(&name);
+        self.forget_health(&name);
+        self.providers.insert(name, provider);
+    }
+
+    /// Retire un provider ; il est aussi retiré de la chaîne de fallback
+    pub fn remove(&mut self, name: &str) -> Option<Box<dyn LLMProvider>> {
+        let provider = self.providers.remove(name)?;
+        self.configs.remove(name);
+        self.fallback.retain(|entry| entry != name);
+        if self.default.as_deref() == Some(name) {
+            self.default = None;
+        }
+        self.forget_health(name);
+        Some(provider)
+    }
+
+    pub fn get(&self, name: &str) -> Option<&dyn LLMProvider> {
+        self.providers.get(name).map(|provider| provider.as_ref())
+    }
+
+    /// Noms des providers enregistrés, triés
+    pub fn names(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = self.providers.keys().map(String::as_str).collect();
+        names.sort_unstable();
+        names
+    }
+
+    pub fn default_name(&self) -> Option<&str> {
+        self.default.as_deref()
+    }
+
+    pub fn default_provider(&self) -> Option<&dyn LLMProvider> {
+        self.default.as_deref().and_then(|name| self.get(name))
+    }
+
+    pub fn set_default(&mut self, name: &str) -> Result<(), LLMError> {
+        self.ensure_registered(name)?;
+        self.default = Some(name.to_string());
+        Ok(())
+    }
+
+    pub fn catalog(&self) -> &ModelCatalog {
+        &self.catalog
+    }
+
+    /// Remplace le catalogue utilisé pour valider les requêtes (ex: `ModelCatalog::load()`)
+    pub fn set_catalog(&mut self, catalog: ModelCatalog) {
+        self.catalog = catalog;
+    }
+
+    /// Description du modèle d'un provider, s'il figure dans le catalogue
+    pub fn model_info(&self, name: &str) -> Option<&ModelInfo> {
+        let config = self.configs.get(name)?;
+        self.catalog.lookup(&config.provider_type, &config.model_name)
+    }
+
+    /// Enregistre la consommation des appels suivants dans `ledger`
+    pub fn set_usage_ledger(&mut self, ledger: UsageLedger, context: UsageContext) {
+        self.usage = Some((ledger, context));
+    }
+
+    /// Limites de consommation vérifiées avant chaque requête (nécessite un journal d'usage)
+    pub fn set_budgets(&mut self, budgets: Budgets) {
+        self.budgets = budgets;
+    }
+
+    pub fn budgets(&self) -> &Budgets {
+        &self.budgets
+    }
+
+    /// Middlewares appliqués à chaque appel, dans l'ordre du pipeline
+    pub fn set_pipeline(&mut self, pipeline: MiddlewarePipeline) {
+        self.pipeline = pipeline;
+    }
+
+    pub fn pipeline(&self) -> &MiddlewarePipeline {
+        &self.pipeline
+    }
+
+    /// Chaîne de fallback effective : celle configurée, sinon le seul provider par défaut
+    pub fn fallback_chain(&self) -> Vec<&str> {
+        if self.fallback.is_empty() {
+            self.default.as_deref().into_iter().collect()
+        } else {
+            self.fallback.iter().map(String::as_str).collect()
+        }
+    }
+
+    /// Définit l'ordre des providers essayés par `generate` et `generate_stream`
+    pub fn set_fallback_chain<I, S>(&mut self, names: I) -> Result<(), LLMError>
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        let names: Vec<String> = names.into_iter().map(Into::into).collect();
+        for name in &names {
+            self.ensure_registered(name)?;
+        }
+        self.fallback = names;
+        Ok(())
+    }
+
+    /// Exécute la requête sur la chaîne de fallback
+    pub async fn generate(&self, request: LLMRequest) -> Result<LLMResponse, LLMError> {
+        self.generate_with(&self.fallback_chain(), request).await
+    }
+
+    /// Exécute la requête sur une chaîne explicite.
+    ///
+    /// Le nom du provider ayant répondu est ajouté aux métadonnées (clé `provider`),
+    /// ainsi que les providers écartés (clé `fallback_from`) et les seuils d'alerte
+    /// franchis (clé `budget_warnings`).
+    pub async fn generate_with(
+        &self,
+        chain: &[&str],
+        request: LLMRequest,
+    ) -> Result<LLMResponse, LLMError> {
+        let mut skipped = Vec::new();
+        let mut last_error = None;
+
+        for &name in chain {
+            let provider = self.provider(name)?;
+            if let Err(error) = self.ensure_supported(name, &request) {
+                skipped.push(name);
+                last_error = Some(error);
+                continue;
+            }
+            let effective = self.effective_request(name, &request);
+            let call = match self.pipeline.prepare(provider, effective, false).await {
+                Ok(call) => call,
+                Err(error) => {
+                    self.on_failure(name, provider, &error).await?;
+                    skipped.push(name);
+                    last_error = Some(error);
+                    continue;
+                }
+            };
+            let warnings = match self.admit(name, provider, &call).await {
+                Ok(warnings) => warnings,
+                Err(error) => {
+                    call.abort(&error);
+                    skipped.push(name);
+                    last_error = Some(error);
+                    continue;
+                }
+            };
+
+            match call.send().await {
+                Ok(mut response) => {
+                    self.record_usage(name, &response).await;
+                    let metadata = response.metadata.get_or_insert_with(HashMap::new);
+                    metadata.insert("provider".to_string(), name.to_string());
+                    if !skipped.is_empty() {
+                        metadata.insert("fallback_from".to_string(), skipped.join(","));
+                    }
+                    if let Some(warnings) = warnings {
+                        metadata.insert(BUDGET_WARNINGS_KEY.to_string(), warnings);
+                    }
+                    return Ok(response);
+                }
+                Err(error) => {
+                    self.on_failure(name, provider, &error).await?;
+                    skipped.push(name);
+                    last_error = Some(error);
+                }
+            }
+        }
+
+        Err(last_error.unwrap_or_else(empty_chain))
+    }
+
+    /// Ouvre un flux sur la chaîne de fallback.
+    ///
+    /// Le fallback ne s'applique qu'à l'établissement du flux : une erreur survenant
+    /// en cours de flux est transmise telle quelle. La consommation n'est enregistrée
+    /// que pour un flux lu jusqu'au bout. Les seuils d'alerte franchis sont ajoutés
+    /// aux métadonnées du chunk final (clé `budget_warnings`).
+    pub async fn generate_stream(&self, request: LLMRequest) -> Result<LLMStream, LLMError> {
+        let mut last_error = None;
+
+        for name in self.fallback_chain() {
+            let provider = self.provider(name)?;
+            if let Err(error) = self.ensure_supported(name, &request) {
+                last_error = Some(error);
+                continue;
+            }
+            let effective = self.effective_request(name, &request);
+            let call = match self.pipeline.prepare(provider, effective, true).await {
+                Ok(call) => call,
+                Err(error) => {
+                    self.on_failure(name, provider, &error).await?;
+                    last_error = Some(error);
+                    continue;
+                }
+            };
+            let warnings = match self.admit(name, provider, &call).await {
+                Ok(warnings) => warnings,
+                Err(error) => {
+                    call.abort(&error);
+                    last_error = Some(error);
+                    continue;
+                }
+            };
+
+            match call.send_stream().await {
+                Ok(stream) => {
+                    let stream = with_budget_warnings(stream, warnings);
+                    return Ok(self.track_stream(name, provider, stream));
+                }
+                Err(error) => {
+                    self.on_failure(name, provider, &error).await?;
+                    last_error = Some(error);
+                }
+            }
+        }
+
+        Err(last_error.unwrap_or_else(empty_chain))
+    }
+
+    /// Lance le `health_check` de tous les providers (sans utiliser le cache)
+    pub async fn health_check_all(&self) -> Vec<(String, Result<(), LLMError>)> {
+        let mut results = Vec::new();
+        for name in self.names() {
+            let result = self.providers[name].health_check().await;
+            self.record_health(name, result.is_ok());
+            results.push((name.to_string(), result));
+        }
+        results
+    }
+
+    /// Enregistre la consommation d'une réponse ; un échec d'écriture dans le journal
+    /// ne fait pas échouer la requête
+    async fn record_usage(&self, name: &str, response: &LLMResponse) {
+        let Some((ledger, context)) = &self.usage else {
+            return;
+        };
+
+        let record = UsageRecord::from_response(name, response, self.model_info(name), context);
+        if let Err(error) = ledger.record(&record).await {
+            tracing::warn!("Consommation non enregistrée: {}", error);
+        }
+    }
+
+    /// Duplique le flux pour enregistrer sa consommation une fois terminé
+    fn track_stream(&self, name: &str, provider: &dyn LLMProvider, stream: LLMStream) -> LLMStream {
+        let Some((ledger, context)) = self.usage.clone() else {
+            return stream;
+        };
+
+        let (stream, collector) = tee_response(stream, provider.model_name());
+        let name = name.to_string();
+        let model_info = self.model_info(&name).cloned();
+        tokio::spawn(async move {
+            let Ok(response) = collector.response().await else {
+                return;
+            };
+            let record =
+                UsageRecord::from_response(&name, &response, model_info.as_ref(), &context);
+            if let Err(error) = ledger.record(&record).await {
+                tracing::warn!("Consommation non enregistrée: {}", error);
+            }
+        });
+        stream
+    }
+
+    /// Requête complétée par les paramètres de la configuration du provider si elle n'en
+    /// précise pas, avec `max_tokens` ajusté au modèle (voir `ModelCatalog::max_tokens`) :
+    /// les middlewares (dont le cache) voient les paramètres effectifs
+    fn effective_request(&self, name: &str, request: &LLMRequest) -> LLMRequest {
+        let mut effective = request.clone();
+        if let (None, Some(config)) = (&request.parameters, self.configs.get(name)) {
+            let mut parameters = config.parameters.clone();
+            parameters.max_tokens = self.catalog.max_tokens(config, request);
+            effective.parameters = Some(parameters);
+        }
+        effective
+    }
+
+    fn provider(&self, name: &str) -> Result<&dyn LLMProvider, LLMError> {
+        self.get(name)
+            .ok_or_else(|| LLMError::InvalidConfig(format!("Provider inconnu: {}", name)))
+    }
+
+    fn ensure_registered(&self, name: &str) -> Result<(), LLMError> {
+        self.provider(name).map(|_| ())
+    }
+
+    /// Vérifie la requête contre le catalogue, pour les providers créés par `add_config`
+    fn ensure_supported(&self, name: &str, request: &LLMRequest) -> Result<(), LLMError> {
+        let Some(config) = self.configs.get(name) else {
+            return Ok(());
+        };
+
+        let result = self.catalog.validate(config, request);
+        if let Err(error) = &result {
+            tracing::warn!(provider = name, "Requête refusée avant envoi: {}", error);
+        }
+        result
+    }
+
+    /// Vérifie le budget puis la santé du provider avant l'envoi et retourne les seuils
+    /// d'alerte franchis. Une réponse fournie par un middleware (cache) ne consomme rien
+    /// et n'appelle pas le provider : aucune vérification n'est alors nécessaire.
+    async fn admit(
+        &self,
+        name: &str,
+        provider: &dyn LLMProvider,
+        call: &PreparedCall<'_>,
+    ) -> Result<Option<String>, LLMError> {
+        if call.is_answered() {
+            return Ok(None);
+        }
+        let warnings = self.ensure_within_budget(name, call.request()).await?;
+        self.ensure_healthy(name, provider).await?;
+        Ok(warnings)
+    }
+
+    /// Vérifie les budgets applicables au provider et retourne les seuils d'alerte
+    /// franchis, prêts pour les métadonnées de la réponse.
+    ///
+    /// Sans configuration connue (provider ajouté par `register`), seule la consommation
+    /// déjà enregistrée est comparée aux limites. Un journal illisible n'empêche pas
+    /// la requête.
+    async fn ensure_within_budget(
+        &self,
+        name: &str,
+        request: &LLMRequest,
+    ) -> Result<Option<String>, LLMError> {
+        let Some((ledger, context)) = self.usage.as_ref().filter(|_| !self.budgets.is_empty())
+        else {
+            return Ok(None);
+        };
+
+        let estimate = self
+            .configs
+            .get(name)
+            .map(|config| {
+                let tokenizer = self.catalog.tokenizer(config);
+                SpendEstimate::for_request(config, request, &tokenizer, self.model_info(name))
+            })
+            .unwrap_or_default();
+
+        match self.budgets.check(ledger, name, context.project.as_deref(), &estimate).await {
+            Ok(warnings) => {
+                for warning in &warnings {
+                    tracing::warn!(provider = name, "Seuil d'alerte franchi: {}", warning);
+                }
+                let warnings: Vec<String> = warnings.iter().map(ToString::to_string).collect();
+                Ok((!warnings.is_empty()).then(|| warnings.join(" ; ")))
+            }
+            Err(error @ LLMError::BudgetExceeded(_)) => {
+                tracing::warn!(provider = name, "Requête refusée avant envoi: {}", error);
+                Err(error)
+            }
+            Err(error) => {
+                tracing::warn!("Budgets non vérifiés: {}", error);
+                Ok(None)
+            }
+        }
+    }
+
+    /// Vérifie la santé du provider, en réutilisant un résultat récent
+    async fn ensure_healthy(&self, name: &str, provider: &dyn LLMProvider) -> Result<(), LLMError> {
+        match self.cached_health(name) {
+            Some(true) => return Ok(()),
+            Some(false) => {
+                return Err(LLMError::NetworkError(format!(
+                    "Provider {} indisponible (health check récent en échec)",
+                    name
+                )))
+            }
+            None => {}
+        }
+
+        let result = provider.health_check().await;
+        self.record_health(name, result.is_ok());
+        if let Err(error) = &result {
+            tracing::warn!(provider = name, "Health check en échec, provider suivant: {}", error);
+        }
+        result
+    }
+
+    /// Après une erreur : retourne l'erreur si elle ne justifie pas un fallback,
+    /// sinon revérifie la santé du provider avant de passer au suivant
+    async fn on_failure(
+        &self,
+        name: &str,
+        provider: &dyn LLMProvider,
+        error: &LLMError,
+    ) -> Result<(), LLMError> {
+        if !is_retryable(error) {
+            return Err(error.clone());
+        }
+
+        tracing::warn!(provider = name, "Échec du provider, passage au suivant: {}", error);
+        let healthy = provider.health_check().await.is_ok();
+        self.record_health(name, healthy);
+        Ok(())
+    }
+
+    fn cached_health(&self, name: &str) -> Option<bool> {
+        let health = self.health.lock().unwrap_or_else(|e| e.into_inner());
+        health
+            .get(name)
+            .filter(|(_, checked_at)| checked_at.elapsed() < HEALTH_TTL)
+            .map(|(healthy, _)| *healthy)
+    }
+
+    fn record_health(&self, name: &str, healthy: bool) {
+        let mut health = self.health.lock().unwrap_or_else(|e| e.into_inner());
+        health.insert(name.to_string(), (healthy, Instant::now()));
+    }
+
+    fn forget_health(&self, name: &str) {
+        let mut health = self.health.lock().unwrap_or_else(|e| e.into_inner());
+        health.remove(name);
+    }
+}
+
+/// Ajoute les seuils d'alerte franchis aux métadonnées du chunk final
+fn with_budget_warnings(stream: LLMStream, warnings: Option<String>) -> LLMStream {
+    let Some(warnings) = warnings else {
+        return stream;
+    };
+
+    Box::new(stream.map(move |chunk| {
+        chunk.map(|mut chunk| {
+            if chunk.finish_reason.is_some() {
+                chunk
+                    .metadata
+                    .get_or_insert_with(HashMap::new)
+                    .insert(BUDGET_WARNINGS_KEY.to_string(), warnings.clone());
+            }
+            chunk
+        })
+    }))
+}
+
+fn empty_chain() -> LLMError {
+    LLMError::InvalidConfig("Aucun provider configuré dans la chaîne de fallback".to_string())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::budget::{BudgetLimit, BudgetPeriod, BudgetUnit};
+    use crate::llm::cache::{CacheMiddleware, ResponseCache};
+    use crate::llm::streaming::collect_response;
+    use crate::llm::test_support::{config, response, user_request, ScriptedProvider};
+    use crate::llm::{LLMProviderType, ModelParameters};
+    use std::sync::atomic::Ordering;
+    use std::sync::Arc;
+
+    fn unavailable() -> LLMError {
+        LLMError::APIError {
+            status: 503,
+            message: "surchargé".to_string(),
+            retry_after: None,
+        }
+    }
+
+    fn manager(providers: Vec<ScriptedProvider>) -> LLMManager {
+        let mut manager = LLMManager::new();
+        let names: Vec<String> = providers.iter().map(|p| p.provider_name().to_string()).collect();
+        for provider in providers {
+            manager.register(provider.provider_name().to_string(), Box::new(provider));
+        }
+        manager.set_fallback_chain(names).unwrap();
+        manager
+    }
+
+    #[tokio::test]
+    async fn first_provider_answers() {
+        let manager = manager(vec![
+            ScriptedProvider::new("distant", vec![Ok(response("distant"))]),
+            ScriptedProvider::new("local", Vec::new()),
+        ]);
+
+        let response = manager.generate(user_request("Bonjour")).await.unwrap();
+        assert_eq!(response.content, "distant");
+        let metadata = response.metadata.unwrap();
+        assert_eq!(metadata["provider"], "distant");
+        assert!(!metadata.contains_key("fallback_from"));
+    }
+
+    #[tokio::test]
+    async fn transient_error_falls_back() {
+        let manager = manager(vec![
+            ScriptedProvider::new("distant", vec![Err(unavailable())]),
+            ScriptedProvider::new("local", vec![Ok(response("local"))]),
+        ]);
+
+        let response = manager.generate(user_request("Bonjour")).await.unwrap();
+        assert_eq!(response.content, "local");
+        let metadata = response.metadata.unwrap();
+        assert_eq!(metadata["provider"], "local");
+        assert_eq!(metadata["fallback_from"], "distant");
+    }
+
+    #[tokio::test]
+    async fn permanent_error_stops_the_chain() {
+        let local = ScriptedProvider::new("local", Vec::new());
+        let local_calls = Arc::clone(&local.calls);
+        let error = LLMError::AuthenticationError("clé invalide".to_string());
+        let manager = manager(vec![ScriptedProvider::new("distant", vec![Err(error)]), local]);
+
+        let error = manager.generate(user_request("Bonjour")).await.unwrap_err();
+        assert!(matches!(error, LLMError::AuthenticationError(_)));
+        assert_eq!(local_calls.load(Ordering::SeqCst), 0);
+    }
+
+    #[tokio::test]
+    async fn unhealthy_provider_is_skipped_and_cached() {
+        let mut distant = ScriptedProvider::new("distant", Vec::new());
+        distant.health = Err(LLMError::NetworkError("injoignable".to_string()));
+        let distant_calls = Arc::clone(&distant.calls);
+        let manager = manager(vec![distant, ScriptedProvider::new("local", Vec::new())]);
+
+        for _ in 0..2 {
+            let response = manager.generate(user_request("Bonjour")).await.unwrap();
+            assert_eq!(response.metadata.unwrap()["provider"], "local");
+        }
+        assert_eq!(distant_calls.load(Ordering::SeqCst), 0);
+        assert_eq!(manager.cached_health("distant"), Some(false));
+    }
+
+    #[tokio::test]
+    async fn last_error_is_returned_when_all_fail() {
+        let manager = manager(vec![
+            ScriptedProvider::new("distant", vec![Err(unavailable())]),
+            ScriptedProvider::new("local", vec![Err(LLMError::Timeout)]),
+        ]);
+
+        let error = manager.generate(user_request("Bonjour")).await.unwrap_err();
+        assert!(matches!(error, LLMError::Timeout));
+    }
+
+    #[tokio::test]
+    async fn stream_falls_back_when_opening_fails() {
+        let manager = manager(vec![
+            ScriptedProvider::new("distant", vec![Err(unavailable())]),
+            ScriptedProvider::new("local", vec![Ok(response("flux local"))]),
+        ]);
+
+        let stream = manager.generate_stream(user_request("Bonjour")).await.unwrap();
+        let response = crate::llm::streaming::collect_response(stream, "m").await.unwrap();
+        assert_eq!(response.content, "flux local");
+    }
+
+    #[tokio::test]
+    async fn empty_chain_is_a_configuration_error() {
+        let manager = LLMManager::new();
+        let error = manager.generate(user_request("Bonjour")).await.unwrap_err();
+        assert!(matches!(error, LLMError::InvalidConfig(_)));
+    }
+
+    #[test]
+    fn registry_default_and_chain() {
+        let mut manager = manager(vec![
+            ScriptedProvider::new("distant", Vec::new()),
+            ScriptedProvider::new("local", Vec::new()),
+        ]);
+        assert_eq!(manager.names(), ["distant", "local"]);
+        assert_eq!(manager.default_name(), Some("distant"));
+        assert!(manager.set_fallback_chain(["inconnu"]).is_err());
+        assert!(manager.set_default("inconnu").is_err());
+
+        assert!(manager.remove("distant").is_some());
+        assert_eq!(manager.default_name(), None);
+        assert_eq!(manager.fallback_chain(), ["local"]);
+    }
+
+    #[test]
+    fn configured_max_tokens_is_fitted_to_the_model() {
+        let mut manager = manager(vec![ScriptedProvider::new("local", Vec::new())]);
+        let catalog = r#"
+            [[models]]
+            provider = "ollama"
+            name = "minuscule"
+            context_window = 1000
+            max_output_tokens = 1000
+        "#;
+        manager.set_catalog(ModelCatalog::from_toml(catalog).unwrap());
+        let config = config(LLMProviderType::Ollama, "minuscule", "http://localhost:11434");
+        manager.configs.insert("local".to_string(), config);
+
+        let effective = manager.effective_request("local", &user_request("Bonjour"));
+        let max_tokens = effective.parameters.unwrap().max_tokens;
+        assert!(max_tokens > 900 && max_tokens < 1000, "{}", max_tokens);
+    }
+
+    #[tokio::test]
+    async fn cache_hit_skips_health_and_budget_checks() {
+        let provider = ScriptedProvider::new("distant", vec![Ok(response("en cache"))]);
+        let calls = Arc::clone(&provider.calls);
+        let mut manager = manager(vec![provider]);
+        let cache = ResponseCache::in_memory(10);
+        manager.set_pipeline(MiddlewarePipeline::new(vec![Arc::new(CacheMiddleware::new(
+            cache,
+        ))]));
+        let context = UsageContext {
+            command: "generate".to_string(),
+            project: None,
+        };
+        manager.set_usage_ledger(UsageLedger::in_memory().await.unwrap(), context);
+
+        let mut request = user_request("Bonjour");
+        request.parameters = Some(ModelParameters {
+            temperature: 0.0,
+            ..ModelParameters::default()
+        });
+        manager.generate(request.clone()).await.unwrap();
+
+        // Provider indisponible et budget épuisé par le premier appel (15 tokens)
+        manager.record_health("distant", false);
+        manager.set_budgets(Budgets::new(vec![BudgetLimit {
+            provider: None,
+            project: None,
+            period: BudgetPeriod::Day,
+            unit: BudgetUnit::Tokens,
+            soft: None,
+            hard: Some(10.0),
+        }]));
+
+        let hit = manager.generate(request.clone()).await.unwrap();
+        let metadata = hit.metadata.unwrap();
+        assert_eq!(metadata["cache"], "hit");
+        assert_eq!(metadata["provider"], "distant");
+        assert_eq!(hit.usage.total_tokens, 0);
+
+        let stream = manager.generate_stream(request).await.unwrap();
+        let streamed = collect_response(stream, "test-model").await.unwrap();
+        assert_eq!(streamed.content, "en cache");
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+        let missed = manager.generate(user_request("Autre question")).await;
+        assert!(matches!(missed, Err(LLMError::BudgetExceeded(_))), "{:?}", missed);
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+}
@@ -1,12 +1,30 @@
 // Module principal pour la gestion des LLM (Large Language Models)
 
 use async_trait::async_trait;
+use base64::Engine;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::path::Path;
 
 pub mod providers;
 pub mod config;
+pub mod validation;
 pub mod streaming;
+pub mod retry;
+pub mod manager;
+pub mod tokenizer;
+pub mod models;
+pub mod usage;
+pub mod budget;
+pub mod deployment;
+pub mod secrets;
+pub mod middleware;
+pub mod cache;
+pub mod replay;
+#[cfg(test)]
+mod test_support;
+
+pub use manager::LLMManager;
 
 
 /// Type de provider LLM supporté
@@ -25,6 +43,7 @@ pub enum LLMProviderType {
 
 /// Configuration d'un provider LLM
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct LLMProviderConfig {
     /// Type de provider
     pub provider_type: LLMProviderType,
@@ -33,31 +52,52 @@ pub struct LLMProviderConfig {
     pub model_name: String,
 
     /// Mode local ou distant
+    #[serde(default)]
     pub deployment: DeploymentMode,
 
     /// URL de base de l'API (pour les providers distants)
     pub base_url: Option<String>,
 
-    /// Clé API (pour les providers distants)
-    pub api_key: Option<String>,
+    /// Clé API (pour les providers distants) : valeur ou référence à une source de secret
+    pub api_key: Option<secrets::SecretSource>,
 
     /// Headers additionnels pour les requêtes API
+    #[serde(default)]
     pub headers: HashMap<String, String>,
 
     /// Paramètres spécifiques au provider/modèle
+    #[serde(default)]
     pub parameters: ModelParameters,
 
     /// Timeout en secondes
+    #[serde(default = "default_timeout_seconds")]
     pub timeout_seconds: u64,
 
     /// Nombre de tentatives en cas d'échec
+    #[serde(default = "default_max_retries")]
     pub max_retries: u32,
 
+    /// Options spécifiques au provider (ex: `api_version` pour Azure OpenAI)
+    #[serde(default)]
+    pub options: HashMap<String, String>,
+
+    /// Gabarit requête/réponse (uniquement pour le provider Custom)
+    #[serde(default)]
+    pub template: Option<providers::custom::CustomTemplate>,
+
+}
+
+pub(crate) fn default_timeout_seconds() -> u64 {
+    120
+}
+
+fn default_max_retries() -> u32 {
+    3
 }
 
 
 /// Mode de déploiement du modèle
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
 #[serde(rename_all = "lowercase")]
 pub enum DeploymentMode {
     /// Modèle exécuté localement
@@ -67,18 +107,22 @@ pub enum DeploymentMode {
     Remote,
 
     /// Détection automatique du mode basée sur l'URL ou la configuration
+    #[default]
     Auto,
 }
 
 
 /// Paramètres spécifiques au modèle LLM / Paramètres de génération du modèle
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
 pub struct ModelParameters {
     /// Température pour la génération de texte (0.0 - 2.0)
     pub temperature: f32,
 
-    /// Top P (sampling) pour la génération de texte (0.0 - 1.0) / Nucleus Sampling
-    pub top_p: f32,
+    /// Top P (sampling) pour la génération de texte (0.0 - 1.0) / Nucleus Sampling.
+    /// Absent, la valeur par défaut du modèle s'applique (certaines API refusent
+    /// `temperature` et `top_p` ensemble)
+    pub top_p: Option<f32>,
 
     /// Nombre maximal de tokens à générer
     pub max_tokens: u32,
@@ -98,7 +142,7 @@ impl Default for ModelParameters {
     fn default() -> Self {
         ModelParameters {
             temperature: 0.7,
-            top_p: 0.95,
+            top_p: None,
             max_tokens: 4096,
             presence_penalty: 0.0,
             frequency_penalty: 0.0,
@@ -113,10 +157,172 @@ impl Default for ModelParameters {
 pub struct LLMMessage {
     /// Rôle de l'auteur du message (user, assistant, system)
     pub role: Role,
-    /// Contenu du message
-    pub content: String,
+    /// Contenu du message (texte ou parts multimodales)
+    pub content: MessageContent,
     /// Métadonnées additionnelles (optionnel)
     pub metadata: Option<HashMap<String, String>>,
+    /// Appels d'outils demandés par l'assistant
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tool_calls: Vec<ToolCall>,
+    /// Identifiant de l'appel auquel répond un message `Role::Tool`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool_call_id: Option<String>,
+}
+
+impl LLMMessage {
+    /// Message `Role::Tool` portant le résultat d'un appel d'outil
+    pub fn tool_result(call: &ToolCall, content: impl Into<MessageContent>) -> Self {
+        LLMMessage {
+            role: Role::Tool,
+            content: content.into(),
+            metadata: None,
+            tool_calls: Vec::new(),
+            tool_call_id: Some(call.id.clone()),
+        }
+    }
+}
+
+
+/// Contenu d'un message : texte simple ou liste de parts (texte, images, fichiers).
+///
+/// Un contenu texte est sérialisé comme une simple chaîne, ce qui reste compatible
+/// avec les conversations enregistrées avant l'ajout des parts.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(untagged)]
+pub enum MessageContent {
+    Text(String),
+    Parts(Vec<ContentPart>),
+}
+
+impl Default for MessageContent {
+    fn default() -> Self {
+        MessageContent::Text(String::new())
+    }
+}
+
+impl From<String> for MessageContent {
+    fn from(text: String) -> Self {
+        MessageContent::Text(text)
+    }
+}
+
+impl From<&str> for MessageContent {
+    fn from(text: &str) -> Self {
+        MessageContent::Text(text.to_string())
+    }
+}
+
+impl From<Vec<ContentPart>> for MessageContent {
+    fn from(parts: Vec<ContentPart>) -> Self {
+        MessageContent::Parts(parts)
+    }
+}
+
+impl MessageContent {
+    /// Texte du message ; les parts textuelles sont jointes par un saut de ligne
+    pub fn text(&self) -> String {
+        match self {
+            MessageContent::Text(text) => text.clone(),
+            MessageContent::Parts(parts) => parts
+                .iter()
+                .filter_map(|part| match part {
+                    ContentPart::Text { text } => Some(text.as_str()),
+                    _ => None,
+                })
+                .collect::<Vec<_>>()
+                .join("\n"),
+        }
+    }
+
+    /// Parts du contenu (un texte vide n'en produit aucune)
+    pub fn parts(&self) -> Vec<ContentPart> {
+        match self {
+            MessageContent::Text(text) if text.is_empty() => Vec::new(),
+            MessageContent::Text(text) => vec![ContentPart::text(text.clone())],
+            MessageContent::Parts(parts) => parts.clone(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        match self {
+            MessageContent::Text(text) => text.is_empty(),
+            MessageContent::Parts(parts) => parts.is_empty(),
+        }
+    }
+
+    /// Premier élément non textuel du contenu, s'il y en a un
+    pub fn first_non_text(&self) -> Option<&ContentPart> {
+        match self {
+            MessageContent::Text(_) => None,
+            MessageContent::Parts(parts) => parts
+                .iter()
+                .find(|part| !matches!(part, ContentPart::Text { .. })),
+        }
+    }
+}
+
+/// Élément d'un message multimodal
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ContentPart {
+    Text { text: String },
+    /// Image encodée en base64 (ex: capture d'écran d'un bug d'interface)
+    Image { media_type: String, data: String },
+    /// Référence à un fichier distant : URL ou identifiant de fichier téléversé
+    File {
+        uri: String,
+        #[serde(default)]
+        media_type: Option<String>,
+    },
+}
+
+impl ContentPart {
+    pub fn text(text: impl Into<String>) -> Self {
+        ContentPart::Text { text: text.into() }
+    }
+
+    /// Image à partir de ses octets bruts
+    pub fn image_bytes(media_type: impl Into<String>, bytes: &[u8]) -> Self {
+        ContentPart::Image {
+            media_type: media_type.into(),
+            data: base64::engine::general_purpose::STANDARD.encode(bytes),
+        }
+    }
+
+    /// Image lue depuis le disque ; le type est déduit de l'extension
+    pub fn image_file(path: impl AsRef<Path>) -> Result<Self, LLMError> {
+        let path = path.as_ref();
+        let extension = path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .map(str::to_ascii_lowercase);
+        let media_type = match extension.as_deref() {
+            Some("png") => "image/png",
+            Some("jpg") | Some("jpeg") => "image/jpeg",
+            Some("gif") => "image/gif",
+            Some("webp") => "image/webp",
+            _ => {
+                return Err(LLMError::InvalidConfig(format!(
+                    "Format d'image non supporté: {} (png, jpeg, gif ou webp attendu)",
+                    path.display()
+                )))
+            }
+        };
+
+        let bytes = std::fs::read(path).map_err(|e| {
+            LLMError::InvalidConfig(format!("Lecture impossible de {}: {}", path.display(), e))
+        })?;
+        Ok(Self::image_bytes(media_type, &bytes))
+    }
+
+    /// Nom du type de part, pour les messages d'erreur
+    pub fn kind(&self) -> &'static str {
+        match self {
+            ContentPart::Text { .. } => "texte",
+            ContentPart::Image { .. } => "image",
+            ContentPart::File { .. } => "fichier",
+        }
+    }
 }
 
 
@@ -127,12 +333,51 @@ pub enum Role {
     User,
     Assistant,
     System,
+    /// Résultat d'un appel d'outil (voir `LLMMessage.tool_call_id`)
+    Tool,
+}
+
+
+/// Outil que le modèle peut appeler
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ToolDefinition {
+    /// Nom de l'outil (ex: "read_file")
+    pub name: String,
+    /// Description destinée au modèle
+    pub description: String,
+    /// Schéma JSON des arguments
+    pub parameters: serde_json::Value,
+}
+
+/// Appel d'outil émis par l'assistant
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ToolCall {
+    /// Identifiant de l'appel, repris par le message `Role::Tool` de réponse
+    pub id: String,
+    /// Nom de l'outil appelé
+    pub name: String,
+    /// Arguments (objet JSON conforme au schéma de l'outil)
+    pub arguments: serde_json::Value,
+}
+
+/// Contrainte sur l'utilisation des outils
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ToolChoice {
+    /// Le modèle décide (comportement par défaut)
+    Auto,
+    /// Aucun outil ne doit être appelé
+    None,
+    /// Au moins un outil doit être appelé
+    Required,
+    /// L'outil nommé doit être appelé
+    Tool(String),
 }
 
 
 
 /// Requête pour générer une réponse du LLM
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct LLMRequest {
     /// Messages de la conversation
     pub messages: Vec<LLMMessage>,
@@ -140,6 +385,28 @@ pub struct LLMRequest {
     pub parameters: Option<ModelParameters>,
     /// Indicateur de streaming
     pub stream: bool,
+    /// Complétion "fill-in-the-middle" (optionnel), à la place des messages
+    #[serde(default)]
+    pub fim: Option<FillInTheMiddle>,
+    /// Outils mis à disposition du modèle
+    #[serde(default)]
+    pub tools: Vec<ToolDefinition>,
+    /// Contrainte sur l'utilisation des outils (optionnel)
+    #[serde(default)]
+    pub tool_choice: Option<ToolChoice>,
+    /// Autorise la réutilisation d'une réponse en cache même avec une température non
+    /// nulle (voir `cache::ResponseCache`)
+    #[serde(default)]
+    pub cache: bool,
+}
+
+/// Code entourant le curseur pour une complétion "fill-in-the-middle"
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FillInTheMiddle {
+    /// Code précédant le curseur
+    pub prefix: String,
+    /// Code suivant le curseur (optionnel)
+    pub suffix: Option<String>,
 }
 
 /// Réponse du LLM
@@ -155,11 +422,14 @@ pub struct LLMResponse {
     pub model: String,
     /// Métadonnées additionnelles (optionnel)
     pub metadata: Option<HashMap<String, String>>,
+    /// Appels d'outils demandés (avec `FinishReason::ToolUse`)
+    #[serde(default)]
+    pub tool_calls: Vec<ToolCall>,
 }
 
 
 /// Raison de fin de la génération
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub enum FinishReason {
     Stop,
     Length,
@@ -169,7 +439,7 @@ pub enum FinishReason {
 
 
 /// Utilisation des tokens dans la requête/réponse
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct TokenUsage {
     /// Nombre de tokens dans la requête
     pub prompt_tokens: u32,
@@ -190,24 +460,50 @@ pub struct LLMStreamChunk {
     pub finish_reason: Option<FinishReason>,
     /// Métadonnées additionnelles (optionnel)
     pub metadata: Option<HashMap<String, String>>,
+    /// Appels d'outils complets, portés par le chunk final
+    #[serde(default)]
+    pub tool_calls: Vec<ToolCall>,
 }
 
 
+/// Flux de chunks renvoyé par `LLMProvider::generate_stream`
+pub type LLMStream = Box<dyn futures::Stream<Item = Result<LLMStreamChunk, LLMError>> + Unpin + Send>;
+
+
 /// Trait principal pour tous les providers LLM
 #[async_trait]
 pub trait LLMProvider: Send + Sync {
-    /// Générer une réponse du LLM (non streaming) complète
-    async fn generate(&self, request: LLMRequest) -> Result<LLMResponse, LLMError>;
+    /// Générer une réponse du LLM (non streaming) complète.
+    ///
+    /// Par défaut, la réponse est reconstituée à partir de `generate_stream` : les
+    /// providers qui ne supportent que le streaming n'ont pas à l'implémenter.
+    async fn generate(&self, request: LLMRequest) -> Result<LLMResponse, LLMError> {
+        let request = LLMRequest {
+            stream: true,
+            ..request
+        };
+        let stream = self.generate_stream(request).await?;
+        streaming::collect_response(stream, self.model_name()).await
+    }
 
     /// Générer une réponse du LLM en streaming
-    async fn generate_stream(
-        &self,
-        request: LLMRequest,
-    ) -> Result<Box<dyn futures::stream<Item = Result<LLMStreamChunk, LLMError>> + Unpin + Send>, LLMError>;
+    async fn generate_stream(&self, request: LLMRequest) -> Result<LLMStream, LLMError>;
 
     /// Compte les tokens dans une liste de messages
     fn count_tokens(&self, text: &str) -> Result<u32, LLMError>;
 
+    /// Compte les tokens d'une conversation : texte des messages (via `count_tokens`),
+    /// gabarit de chaque message et amorce de la réponse
+    fn count_messages(&self, messages: &[LLMMessage]) -> Result<u32, LLMError> {
+        tokenizer::conversation_tokens(messages, |text| self.count_tokens(text))
+    }
+
+    /// Compte exact selon le tokenizer du modèle servi, pour les providers qui l'exposent
+    /// (ex: `/tokenize` de llama.cpp) ; par défaut `count_tokens`
+    async fn count_tokens_exact(&self, text: &str) -> Result<u32, LLMError> {
+        self.count_tokens(text)
+    }
+
     /// Retourne le nom du provider 
     fn provider_name(&self) -> &str;
 
@@ -221,7 +517,7 @@ pub trait LLMProvider: Send + Sync {
 
 
 /// Erreur générique pour les opérations LLM
-#[derive(Debug, thiserror::Error)]
+#[derive(Debug, Clone, thiserror::Error)]
 pub enum LLMError {
     #[error("Configuration invalide: {0}")]
     InvalidConfig(String),
@@ -233,10 +529,18 @@ pub enum LLMError {
     NetworkError(String),
     
     #[error("Erreur API: {status} - {message}")]
-    APIError { status: u16, message: String },
+    APIError {
+        status: u16,
+        message: String,
+        /// Délai demandé par le serveur avant une nouvelle tentative (header `Retry-After`)
+        retry_after: Option<std::time::Duration>,
+    },
     
     #[error("Limite de tokens dépassée")]
     TokenLimitExceeded,
+
+    #[error("Budget dépassé: {0}")]
+    BudgetExceeded(String),
     
     #[error("Timeout de la requête")]
     Timeout,
@@ -249,5 +553,87 @@ pub enum LLMError {
     
     #[error("Erreur interne: {0}")]
     InternalError(String),
+
+    #[error("Aucun échange enregistré ne correspond à la requête: {0}")]
+    ReplayMiss(String),
 }
 
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn text_content_serializes_as_a_string() {
+        let content = MessageContent::from("Bonjour");
+        assert_eq!(serde_json::to_value(&content).unwrap(), json!("Bonjour"));
+
+        let parsed: MessageContent = serde_json::from_value(json!("Bonjour")).unwrap();
+        assert_eq!(parsed, content);
+    }
+
+    #[test]
+    fn parts_round_trip() {
+        let content = MessageContent::from(vec![
+            ContentPart::text("Que montre cette capture ?"),
+            ContentPart::image_bytes("image/png", b"png"),
+            ContentPart::File {
+                uri: "file-abc".to_string(),
+                media_type: None,
+            },
+        ]);
+        let value = serde_json::to_value(&content).unwrap();
+        assert_eq!(
+            value,
+            json!([
+                { "type": "text", "text": "Que montre cette capture ?" },
+                { "type": "image", "media_type": "image/png", "data": "cG5n" },
+                { "type": "file", "uri": "file-abc", "media_type": null }
+            ])
+        );
+        assert_eq!(serde_json::from_value::<MessageContent>(value).unwrap(), content);
+
+        assert_eq!(content.text(), "Que montre cette capture ?");
+        assert_eq!(content.first_non_text().map(ContentPart::kind), Some("image"));
+    }
+
+    #[test]
+    fn empty_text_has_no_parts() {
+        assert!(MessageContent::default().parts().is_empty());
+        assert!(MessageContent::default().is_empty());
+        assert_eq!(MessageContent::from("a").parts(), [ContentPart::text("a")]);
+    }
+
+    #[test]
+    fn image_file_detects_the_media_type() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("capture.JPG");
+        std::fs::write(&path, b"jpeg").unwrap();
+
+        let part = ContentPart::image_file(&path).unwrap();
+        assert_eq!(part, ContentPart::image_bytes("image/jpeg", b"jpeg"));
+
+        let error = ContentPart::image_file(dir.path().join("capture.bmp")).unwrap_err();
+        assert!(matches!(error, LLMError::InvalidConfig(_)));
+        let error = ContentPart::image_file(dir.path().join("absente.png")).unwrap_err();
+        assert!(matches!(error, LLMError::InvalidConfig(_)));
+    }
+
+    #[test]
+    fn count_messages_defaults_to_count_tokens() {
+        use test_support::{message, ScriptedProvider};
+
+        let provider = ScriptedProvider::new("stub", Vec::new());
+        let messages = [message(Role::System, "sois bref"), message(Role::User, "deux mots")];
+        // 3 par message + 3 pour l'amorce de la réponse
+        assert_eq!(provider.count_messages(&messages).unwrap(), 3 + (3 + 2) + (3 + 2));
+    }
+
+    #[tokio::test]
+    async fn exact_count_defaults_to_count_tokens() {
+        let provider = test_support::ScriptedProvider::new("stub", Vec::new());
+        assert_eq!(provider.count_tokens_exact("trois mots ici").await.unwrap(), 3);
+    }
+}
@@ -0,0 +1,451 @@
+// Catalogue des modèles : fenêtre de contexte, limites, capacités et tarifs
+
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+use super::providers::resolve_parameters;
+use super::tokenizer::{Tokenizer, TokenizerRegistry};
+use super::{ContentPart, LLMError, LLMProviderConfig, LLMProviderType, LLMRequest, TokenUsage};
+
+/// Catalogue intégré au binaire (voir `models.toml`)
+const BUILTIN_CATALOG: &str = include_str!("models.toml");
+
+/// Nom du fichier de surcharge dans le répertoire de configuration utilisateur
+const USER_CATALOG_FILE: &str = "models.toml";
+
+
+/// Description d'un modèle (ou d'une famille de modèles)
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ModelInfo {
+    pub provider: LLMProviderType,
+    /// Nom du modèle, comparé comme préfixe (`claude-sonnet-4` couvre les versions datées)
+    pub name: String,
+    /// Taille de la fenêtre de contexte (prompt + réponse), en tokens
+    pub context_window: u32,
+    /// Nombre maximal de tokens générés par réponse
+    pub max_output_tokens: u32,
+    #[serde(default)]
+    pub supports_tools: bool,
+    #[serde(default)]
+    pub supports_vision: bool,
+    #[serde(default)]
+    pub supports_json_mode: bool,
+    /// Prix des tokens du prompt, en USD par million
+    #[serde(default)]
+    pub input_price: f64,
+    /// Prix des tokens générés, en USD par million
+    #[serde(default)]
+    pub output_price: f64,
+}
+
+impl ModelInfo {
+    /// Coût d'un appel, en USD
+    pub fn cost(&self, usage: &TokenUsage) -> f64 {
+        (usage.prompt_tokens as f64 * self.input_price
+            + usage.completion_tokens as f64 * self.output_price)
+            / 1_000_000.0
+    }
+
+    /// Ramène `max_tokens` à la limite de sortie du modèle et au contexte restant après
+    /// le prompt (au moins un token : un prompt trop long reste refusé)
+    pub fn fit_max_tokens(&self, max_tokens: u32, prompt_tokens: u32) -> u32 {
+        let remaining = self.context_window.saturating_sub(prompt_tokens).max(1);
+        max_tokens.min(self.max_output_tokens).min(remaining)
+    }
+
+    /// Vérifie qu'une requête respecte les limites et capacités du modèle.
+    ///
+    /// `max_tokens` est la limite de génération effective, `prompt_tokens` la taille
+    /// estimée du prompt.
+    pub fn check_request(
+        &self,
+        request: &LLMRequest,
+        max_tokens: u32,
+        prompt_tokens: u32,
+    ) -> Result<(), LLMError> {
+        if max_tokens > self.max_output_tokens {
+            return Err(LLMError::InvalidConfig(format!(
+                "max_tokens ({}) dépasse la limite de sortie de {} ({} tokens)",
+                max_tokens, self.name, self.max_output_tokens
+            )));
+        }
+
+        if !request.tools.is_empty() && !self.supports_tools {
+            return Err(LLMError::InvalidConfig(format!(
+                "Le modèle {} ne supporte pas les appels d'outils",
+                self.name
+            )));
+        }
+
+        let has_image = request.messages.iter().any(|message| {
+            message
+                .content
+                .parts()
+                .iter()
+                .any(|part| matches!(part, ContentPart::Image { .. }))
+        });
+        if has_image && !self.supports_vision {
+            return Err(LLMError::InvalidConfig(format!(
+                "Le modèle {} ne supporte pas les images",
+                self.name
+            )));
+        }
+
+        if prompt_tokens.saturating_add(max_tokens) > self.context_window {
+            tracing::warn!(
+                model = %self.name,
+                "Prompt de {} tokens + max_tokens {} > contexte de {} tokens",
+                prompt_tokens,
+                max_tokens,
+                self.context_window
+            );
+            return Err(LLMError::TokenLimitExceeded);
+        }
+
+        Ok(())
+    }
+}
+
+
+/// Contenu d'un fichier catalogue
+#[derive(Deserialize)]
+struct CatalogFile {
+    #[serde(default)]
+    models: Vec<ModelInfo>,
+}
+
+/// Catalogue des modèles connus, indexé par type de provider et nom de modèle.
+///
+/// `ModelCatalog::default()` contient le catalogue intégré ; `load` y ajoute les
+/// surcharges de l'utilisateur (`~/.config/codecrafter/models.toml` sous Linux).
+///
+/// Le catalogue porte aussi le registre des tokenizers utilisé pour estimer la
+/// taille des prompts.
+#[derive(Debug, Clone)]
+pub struct ModelCatalog {
+    models: Vec<ModelInfo>,
+    tokenizers: TokenizerRegistry,
+}
+
+impl Default for ModelCatalog {
+    fn default() -> Self {
+        Self::builtin()
+    }
+}
+
+impl ModelCatalog {
+    /// Catalogue vide (aucune validation ne sera effectuée)
+    pub fn empty() -> Self {
+        ModelCatalog {
+            models: Vec::new(),
+            tokenizers: TokenizerRegistry::default(),
+        }
+    }
+
+    /// Catalogue intégré au binaire
+    pub fn builtin() -> Self {
+        static BUILTIN: OnceLock<ModelCatalog> = OnceLock::new();
+        BUILTIN
+            .get_or_init(|| {
+                ModelCatalog::from_toml(BUILTIN_CATALOG).expect("catalogue intégré invalide")
+            })
+            .clone()
+    }
+
+    /// Catalogue intégré complété par le fichier de l'utilisateur, s'il existe
+    pub fn load() -> Result<Self, LLMError> {
+        let mut catalog = Self::builtin();
+        if let Some(path) = Self::user_catalog_path().filter(|path| path.exists()) {
+            catalog.load_overrides(&path)?;
+        }
+        Ok(catalog)
+    }
+
+    /// Emplacement du fichier de surcharge de l'utilisateur
+    pub fn user_catalog_path() -> Option<PathBuf> {
+        directories::ProjectDirs::from("", "", "codecrafter")
+            .map(|dirs| dirs.config_dir().join(USER_CATALOG_FILE))
+    }
+
+    pub fn from_toml(content: &str) -> Result<Self, LLMError> {
+        let file: CatalogFile = toml::from_str(content).map_err(|e| {
+            LLMError::InvalidConfig(format!("Catalogue de modèles invalide: {}", e))
+        })?;
+        Ok(ModelCatalog {
+            models: file.models,
+            tokenizers: TokenizerRegistry::default(),
+        })
+    }
+
+    /// Ajoute ou remplace les modèles décrits dans un fichier TOML
+    pub fn load_overrides(&mut self, path: &Path) -> Result<(), LLMError> {
+        let content = std::fs::read_to_string(path).map_err(|e| {
+            LLMError::InvalidConfig(format!("Lecture de {} impossible: {}", path.display(), e))
+        })?;
+        let overrides = Self::from_toml(&content).map_err(|e| match e {
+            LLMError::InvalidConfig(message) => {
+                LLMError::InvalidConfig(format!("{}: {}", path.display(), message))
+            }
+            other => other,
+        })?;
+        self.merge(overrides);
+        Ok(())
+    }
+
+    /// Ajoute les modèles d'un autre catalogue, prioritaires sur les entrées existantes
+    pub fn merge(&mut self, other: ModelCatalog) {
+        for model in other.models {
+            self.insert(model);
+        }
+    }
+
+    /// Ajoute un modèle (remplace l'entrée de même provider et de même nom)
+    pub fn insert(&mut self, model: ModelInfo) {
+        self.models.retain(|existing| {
+            existing.provider != model.provider || !existing.name.eq_ignore_ascii_case(&model.name)
+        });
+        self.models.push(model);
+    }
+
+    pub fn models(&self) -> &[ModelInfo] {
+        &self.models
+    }
+
+    /// Remplace le registre des tokenizers (ex: règles ajoutées par `register`)
+    pub fn set_tokenizers(&mut self, tokenizers: TokenizerRegistry) {
+        self.tokenizers = tokenizers;
+    }
+
+    pub fn tokenizers(&self) -> &TokenizerRegistry {
+        &self.tokenizers
+    }
+
+    /// Tokenizer du modèle configuré
+    pub fn tokenizer(&self, config: &LLMProviderConfig) -> Tokenizer {
+        self.tokenizers.tokenizer(&config.provider_type, &config.model_name)
+    }
+
+    /// Recherche un modèle : l'entrée dont le nom est le plus long préfixe du nom demandé.
+    ///
+    /// Les déploiements Azure OpenAI sans entrée propre utilisent les entrées OpenAI.
+    pub fn lookup(&self, provider_type: &LLMProviderType, model_name: &str) -> Option<&ModelInfo> {
+        let model = model_name.to_ascii_lowercase();
+        // Les noms Ollama / Gemini peuvent porter un espace de noms (`models/...`)
+        let model = model.rsplit('/').next().unwrap_or(&model);
+
+        let find = |provider: &LLMProviderType| {
+            self.models
+                .iter()
+                .filter(|entry| &entry.provider == provider)
+                .filter(|entry| model.starts_with(&entry.name.to_ascii_lowercase()))
+                .max_by_key(|entry| entry.name.len())
+        };
+
+        find(provider_type).or_else(|| match provider_type {
+            LLMProviderType::AzureOpenAI => find(&LLMProviderType::OpenAI),
+            _ => None,
+        })
+    }
+
+    /// Vérifie une requête avant son envoi au provider configuré, avec la limite de
+    /// génération de `max_tokens`.
+    ///
+    /// Un modèle absent du catalogue n'est pas vérifié.
+    pub fn validate(
+        &self,
+        config: &LLMProviderConfig,
+        request: &LLMRequest,
+    ) -> Result<(), LLMError> {
+        let Some(model) = self.lookup(&config.provider_type, &config.model_name) else {
+            tracing::debug!(model = %config.model_name, "Modèle hors catalogue, non vérifié");
+            return Ok(());
+        };
+
+        let prompt_tokens = self.tokenizer(config).count_request(request);
+        let max_tokens = Self::effective_max_tokens(model, config, request, prompt_tokens);
+        model.check_request(request, max_tokens, prompt_tokens)
+    }
+
+    /// Limite de génération à envoyer pour la requête.
+    ///
+    /// Des paramètres fixés par la requête sont respectés tels quels. Sinon, le
+    /// `max_tokens` de la configuration sert de plafond et est ramené à ce que le
+    /// modèle peut produire après le prompt.
+    pub fn max_tokens(&self, config: &LLMProviderConfig, request: &LLMRequest) -> u32 {
+        match self.lookup(&config.provider_type, &config.model_name) {
+            Some(model) => {
+                let prompt_tokens = self.tokenizer(config).count_request(request);
+                Self::effective_max_tokens(model, config, request, prompt_tokens)
+            }
+            None => resolve_parameters(config, request).max_tokens,
+        }
+    }
+
+    fn effective_max_tokens(
+        model: &ModelInfo,
+        config: &LLMProviderConfig,
+        request: &LLMRequest,
+        prompt_tokens: u32,
+    ) -> u32 {
+        let max_tokens = resolve_parameters(config, request).max_tokens;
+        if request.parameters.is_some() {
+            return max_tokens;
+        }
+        model.fit_max_tokens(max_tokens, prompt_tokens)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::test_support::{config, user_request};
+    use crate::llm::tokenizer::TokenizerKind;
+
+    fn small_model() -> ModelCatalog {
+        ModelCatalog::from_toml(
+            r#"
+            [[models]]
+            provider = "ollama"
+            name = "petit"
+            context_window = 4200
+            max_output_tokens = 4096
+
+            [[models]]
+            provider = "ollama"
+            name = "minuscule"
+            context_window = 1000
+            max_output_tokens = 1000
+            "#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn default_request_fits_every_catalogue_entry() {
+        let catalog = ModelCatalog::builtin();
+        let request = user_request("Explique ce code.");
+
+        for model in catalog.models() {
+            let config = config(model.provider.clone(), &model.name, "http://localhost");
+            assert_eq!(catalog.lookup(&model.provider, &model.name), Some(model));
+            assert!(
+                catalog.validate(&config, &request).is_ok(),
+                "{:?} {}",
+                model.provider,
+                model.name
+            );
+            assert!(catalog.max_tokens(&config, &request) <= model.max_output_tokens);
+        }
+    }
+
+    #[test]
+    fn configured_max_tokens_is_a_ceiling() {
+        let catalog = small_model();
+        let config = config(LLMProviderType::Ollama, "minuscule", "http://localhost:11434");
+        let request = user_request("Bonjour");
+        let prompt_tokens = catalog.tokenizer(&config).count_request(&request);
+
+        assert_eq!(catalog.max_tokens(&config, &request), 1000 - prompt_tokens);
+        assert!(catalog.validate(&config, &request).is_ok());
+
+        // Des paramètres explicites ne sont pas ajustés
+        let explicit = LLMRequest {
+            parameters: Some(config.parameters.clone()),
+            ..request
+        };
+        assert_eq!(catalog.max_tokens(&config, &explicit), 4096);
+        assert!(matches!(
+            catalog.validate(&config, &explicit),
+            Err(LLMError::InvalidConfig(_))
+        ));
+    }
+
+    #[test]
+    fn oversized_prompt_is_refused() {
+        let catalog = small_model();
+        let config = config(LLMProviderType::Ollama, "minuscule", "http://localhost:11434");
+        let request = user_request(&"mot ".repeat(2000));
+
+        assert_eq!(catalog.max_tokens(&config, &request), 1);
+        assert!(matches!(
+            catalog.validate(&config, &request),
+            Err(LLMError::TokenLimitExceeded)
+        ));
+    }
+
+    #[test]
+    fn lookup_prefers_the_longest_prefix() {
+        let catalog = ModelCatalog::builtin();
+        let name = |provider: LLMProviderType, model: &str| {
+            catalog.lookup(&provider, model).map(|entry| entry.name.as_str())
+        };
+
+        assert_eq!(name(LLMProviderType::OpenAI, "gpt-4o-mini-2024-07-18"), Some("gpt-4o-mini"));
+        assert_eq!(name(LLMProviderType::OpenAI, "gpt-4o-2024-11-20"), Some("gpt-4o"));
+        assert_eq!(name(LLMProviderType::AzureOpenAI, "gpt-4.1"), Some("gpt-4.1"));
+        assert_eq!(name(LLMProviderType::Gemini, "models/gemini-2.5-pro"), Some("gemini-2.5-pro"));
+        assert_eq!(name(LLMProviderType::Ollama, "llava:13b"), Some("llava"));
+        assert_eq!(name(LLMProviderType::Claude, "gpt-4o"), None);
+    }
+
+    #[test]
+    fn overrides_replace_entries() {
+        let mut catalog = ModelCatalog::builtin();
+        let count = catalog.models().len();
+        catalog.merge(
+            ModelCatalog::from_toml(
+                r#"
+                [[models]]
+                provider = "ollama"
+                name = "LLAVA"
+                context_window = 8192
+                max_output_tokens = 2048
+                "#,
+            )
+            .unwrap(),
+        );
+
+        assert_eq!(catalog.models().len(), count);
+        let llava = catalog.lookup(&LLMProviderType::Ollama, "llava").unwrap();
+        assert_eq!((llava.context_window, llava.max_output_tokens), (8192, 2048));
+        assert!(!llava.supports_vision);
+    }
+
+    #[test]
+    fn cost_uses_prices_per_million() {
+        let model = ModelInfo {
+            input_price: 3.0,
+            output_price: 15.0,
+            ..ModelCatalog::builtin().models()[0].clone()
+        };
+        let usage = TokenUsage {
+            prompt_tokens: 1_000_000,
+            completion_tokens: 100_000,
+            total_tokens: 1_100_000,
+        };
+        assert!((model.cost(&usage) - 4.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn registered_tokenizer_is_used_for_validation() {
+        let config = config(LLMProviderType::Ollama, "petit", "http://localhost:11434");
+        // 120 caractères : ~33 tokens estimés, ~120 avec un token par caractère
+        let request = LLMRequest {
+            parameters: Some(config.parameters.clone()),
+            ..user_request(&"a ".repeat(60))
+        };
+
+        let mut catalog = small_model();
+        assert!(catalog.validate(&config, &request).is_ok());
+
+        let mut tokenizers = TokenizerRegistry::new();
+        tokenizers.register("petit", TokenizerKind::Heuristic { chars_per_token: 1.0 });
+        catalog.set_tokenizers(tokenizers);
+        assert!(matches!(
+            catalog.validate(&config, &request),
+            Err(LLMError::TokenLimitExceeded)
+        ));
+    }
+}
@@ -0,0 +1,205 @@
+// Provider Azure OpenAI (déploiements Chat Completions)
+
+use async_trait::async_trait;
+
+use super::openai_compat::{chat_stream, ChatRequest, ChatResponse};
+use super::{configured_api_key, count_tokens, ensure_no_fim, resolve_parameters, HttpTransport};
+use crate::llm::{LLMError, LLMProvider, LLMProviderConfig, LLMRequest, LLMResponse, LLMStream};
+
+/// Version d'API utilisée si `options.api_version` n'est pas renseignée
+const DEFAULT_API_VERSION: &str = "2024-10-21";
+
+
+/// Provider pour Azure OpenAI.
+///
+/// `base_url` est l'endpoint de la ressource (`https://<ressource>.openai.azure.com`)
+/// et `model_name` le nom du déploiement. L'authentification passe par le header
+/// `api-key`, sauf si un header `Authorization` (jeton Entra ID) est configuré.
+pub struct AzureOpenAIProvider {
+    config: LLMProviderConfig,
+    transport: HttpTransport,
+    api_version: String,
+}
+
+impl AzureOpenAIProvider {
+    pub fn new(config: LLMProviderConfig) -> Result<Self, LLMError> {
+        if config.base_url.as_deref().filter(|url| !url.trim().is_empty()).is_none() {
+            return Err(LLMError::InvalidConfig(
+                "base_url (endpoint de la ressource Azure) est requis pour Azure OpenAI".to_string(),
+            ));
+        }
+
+        let api_key = configured_api_key(&config)?;
+        let has_bearer = config
+            .headers
+            .keys()
+            .any(|name| name.eq_ignore_ascii_case("authorization"));
+
+        let mut default_headers = Vec::new();
+        match api_key {
+            Some(api_key) => default_headers.push(("api-key", api_key)),
+            None if has_bearer => {}
+            None => {
+                return Err(LLMError::InvalidConfig(
+                    "Clé API (ou header Authorization) manquante pour Azure OpenAI".to_string(),
+                ))
+            }
+        }
+
+        let api_version = config
+            .options
+            .get("api_version")
+            .cloned()
+            .unwrap_or_else(|| DEFAULT_API_VERSION.to_string());
+
+        let transport = HttpTransport::new(&config, "", default_headers)?;
+
+        Ok(AzureOpenAIProvider {
+            config,
+            transport,
+            api_version,
+        })
+    }
+
+    /// Chemin d'une opération scopée au déploiement, avec le paramètre `api-version`
+    fn deployment_path(&self, operation: &str) -> String {
+        format!(
+            "/openai/deployments/{}/{}?api-version={}",
+            self.config.model_name, operation, self.api_version
+        )
+    }
+}
+
+#[async_trait]
+impl LLMProvider for AzureOpenAIProvider {
+    async fn generate(&self, request: LLMRequest) -> Result<LLMResponse, LLMError> {
+        ensure_no_fim(&request, self.provider_name())?;
+        // Le modèle est porté par l'URL du déploiement : il n'est pas envoyé dans le corps
+        let parameters = resolve_parameters(&self.config, &request);
+        let body = ChatRequest::new(None, &request.messages, parameters, false)?
+            .with_tools(&request.tools, request.tool_choice.as_ref());
+        let path = self.deployment_path("chat/completions");
+        let response = self.transport.post_json(&path, &body, false).await?;
+
+        let parsed: ChatResponse = response
+            .json()
+            .await
+            .map_err(|e| LLMError::ParseError(format!("Réponse Azure OpenAI invalide: {}", e)))?;
+
+        parsed.into_llm_response(&self.config.model_name)
+    }
+
+    async fn generate_stream(&self, request: LLMRequest) -> Result<LLMStream, LLMError> {
+        ensure_no_fim(&request, self.provider_name())?;
+        let parameters = resolve_parameters(&self.config, &request);
+        let body = ChatRequest::new(None, &request.messages, parameters, true)?
+            .with_tools(&request.tools, request.tool_choice.as_ref());
+        let path = self.deployment_path("chat/completions");
+        let response = self.transport.post_json(&path, &body, true).await?;
+
+        Ok(chat_stream(response))
+    }
+
+    fn count_tokens(&self, text: &str) -> Result<u32, LLMError> {
+        Ok(count_tokens(&self.config, text))
+    }
+
+    fn provider_name(&self) -> &str {
+        "azure-openai"
+    }
+
+    fn model_name(&self) -> &str {
+        &self.config.model_name
+    }
+
+    /// Valide l'endpoint et la clé en listant les modèles de la ressource, sans
+    /// génération facturée. L'existence du déploiement n'est vérifiée qu'au premier appel.
+    async fn health_check(&self) -> Result<(), LLMError> {
+        let path = format!("/openai/models?api-version={}", self.api_version);
+        self.transport.get(&path).await?;
+        Ok(())
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::test_support::{config, user_request};
+    use crate::llm::LLMProviderType;
+    use serde_json::{json, Value};
+    use wiremock::matchers::{header, method, path, query_param};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn provider(server: &MockServer) -> AzureOpenAIProvider {
+        let mut config = config(LLMProviderType::AzureOpenAI, "mon-gpt4o", &server.uri());
+        config.options.insert("api_version".to_string(), "2025-01-01".to_string());
+        AzureOpenAIProvider::new(config).unwrap()
+    }
+
+    #[tokio::test]
+    async fn generate_targets_the_deployment() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/openai/deployments/mon-gpt4o/chat/completions"))
+            .and(query_param("api-version", "2025-01-01"))
+            .and(header("api-key", "test-key"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "choices": [{ "message": { "content": "Bonjour" }, "finish_reason": "stop" }],
+                "usage": { "prompt_tokens": 5, "completion_tokens": 1, "total_tokens": 6 }
+            })))
+            .mount(&server)
+            .await;
+
+        let response = provider(&server).generate(user_request("Salut")).await.unwrap();
+        assert_eq!(response.content, "Bonjour");
+        assert_eq!(response.model, "mon-gpt4o");
+        assert_eq!(response.usage.total_tokens, 6);
+
+        let body: Value = server.received_requests().await.unwrap()[0].body_json().unwrap();
+        assert!(body.get("model").is_none());
+    }
+
+    #[tokio::test]
+    async fn health_check_does_not_generate() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/openai/models"))
+            .and(query_param("api-version", "2025-01-01"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "data": [] })))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(500))
+            .expect(0)
+            .mount(&server)
+            .await;
+
+        provider(&server).health_check().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn health_check_reports_invalid_key() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(401).set_body_json(json!({
+                "error": { "code": "401", "message": "Access denied due to invalid subscription key" }
+            })))
+            .mount(&server)
+            .await;
+
+        let result = provider(&server).health_check().await;
+        assert!(matches!(result, Err(LLMError::AuthenticationError(_))));
+    }
+
+    #[test]
+    fn base_url_is_required() {
+        let mut config = config(LLMProviderType::AzureOpenAI, "mon-gpt4o", "");
+        config.base_url = None;
+        assert!(matches!(
+            AzureOpenAIProvider::new(config),
+            Err(LLMError::InvalidConfig(_))
+        ));
+    }
+}
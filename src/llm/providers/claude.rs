@@ -0,0 +1,685 @@
+// Provider Claude (API Messages d'Anthropic)
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+
+use super::{
+    api_key, count_tokens, ensure_no_fim, error_for_status, resolve_parameters, text_only,
+    HttpTransport,
+};
+use crate::llm::streaming::{decode_response, StreamEvent, StreamFormat, StreamHandler, StreamUpdate};
+use crate::llm::{
+    ContentPart, FinishReason, LLMError, LLMMessage, LLMProvider, LLMProviderConfig, LLMRequest,
+    LLMResponse, LLMStream, Role, TokenUsage, ToolCall, ToolChoice, ToolDefinition,
+};
+
+const DEFAULT_BASE_URL: &str = "https://api.anthropic.com";
+const ANTHROPIC_VERSION: &str = "2023-06-01";
+
+
+/// Provider pour les modèles Claude via l'API Messages
+pub struct ClaudeProvider {
+    config: LLMProviderConfig,
+    transport: HttpTransport,
+}
+
+impl ClaudeProvider {
+    pub fn new(config: LLMProviderConfig) -> Result<Self, LLMError> {
+        let mut default_headers = vec![("anthropic-version", ANTHROPIC_VERSION.to_string())];
+        if let Some(api_key) = api_key(&config, "Claude")? {
+            default_headers.push(("x-api-key", api_key));
+        }
+
+        let transport = HttpTransport::new(&config, DEFAULT_BASE_URL, default_headers)?;
+
+        Ok(ClaudeProvider { config, transport })
+    }
+
+    /// Construit le corps de la requête Messages.
+    ///
+    /// Les messages `Role::System` sont regroupés dans le champ `system` de premier niveau
+    /// et les résultats d'outils consécutifs dans un même message `user`.
+    /// L'API ne supporte pas les pénalités de présence/fréquence : elles sont ignorées.
+    fn build_body(&self, request: &LLMRequest, stream: bool) -> Result<MessagesRequest, LLMError> {
+        let parameters = resolve_parameters(&self.config, request);
+
+        let system = request
+            .messages
+            .iter()
+            .filter(|m| m.role == Role::System)
+            .map(|m| text_only(&m.content, self.provider_name()))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut messages: Vec<WireMessage> = Vec::new();
+        for message in request.messages.iter().filter(|m| m.role != Role::System) {
+            let wire = WireMessage::from_message(message)?;
+            match messages.last_mut() {
+                Some(last) if message.role == Role::Tool && last.is_tool_results() => {
+                    last.content.extend(wire.content);
+                }
+                _ => messages.push(wire),
+            }
+        }
+
+        Ok(MessagesRequest {
+            model: self.config.model_name.clone(),
+            max_tokens: parameters.max_tokens,
+            messages,
+            system: (!system.is_empty()).then(|| system.join("\n\n")),
+            temperature: parameters.temperature,
+            top_p: parameters.top_p,
+            stop_sequences: parameters.stop_sequences,
+            stream,
+            tools: request.tools.iter().map(WireTool::from).collect(),
+            tool_choice: request.tool_choice.as_ref().map(|choice| match choice {
+                ToolChoice::Auto => json!({ "type": "auto" }),
+                ToolChoice::None => json!({ "type": "none" }),
+                ToolChoice::Required => json!({ "type": "any" }),
+                ToolChoice::Tool(name) => json!({ "type": "tool", "name": name }),
+            }),
+        })
+    }
+}
+
+#[async_trait]
+impl LLMProvider for ClaudeProvider {
+    async fn generate(&self, request: LLMRequest) -> Result<LLMResponse, LLMError> {
+        ensure_no_fim(&request, self.provider_name())?;
+        let body = self.build_body(&request, false)?;
+        let response = self.transport.post_json("/v1/messages", &body, false).await?;
+
+        let parsed: MessagesResponse = response
+            .json()
+            .await
+            .map_err(|e| LLMError::ParseError(format!("Réponse Claude invalide: {}", e)))?;
+
+        let mut content = String::new();
+        let mut tool_calls = Vec::new();
+        for block in parsed.content {
+            match block {
+                ContentBlock::Text { text } => content.push_str(&text),
+                ContentBlock::ToolUse { id, name, input } => tool_calls.push(ToolCall {
+                    id,
+                    name,
+                    arguments: input,
+                }),
+                ContentBlock::Other => {}
+            }
+        }
+
+        let mut metadata = HashMap::new();
+        metadata.insert("id".to_string(), parsed.id);
+        if let Some(stop_sequence) = parsed.stop_sequence {
+            metadata.insert("stop_sequence".to_string(), stop_sequence);
+        }
+
+        Ok(LLMResponse {
+            content,
+            finish_reason: map_stop_reason(parsed.stop_reason.as_deref()),
+            usage: parsed.usage.into(),
+            model: parsed.model,
+            metadata: Some(metadata),
+            tool_calls,
+        })
+    }
+
+    async fn generate_stream(&self, request: LLMRequest) -> Result<LLMStream, LLMError> {
+        ensure_no_fim(&request, self.provider_name())?;
+        let body = self.build_body(&request, true)?;
+        let response = self.transport.post_json("/v1/messages", &body, true).await?;
+
+        Ok(decode_response(response, StreamFormat::Sse, ClaudeStreamHandler::default()))
+    }
+
+    fn count_tokens(&self, text: &str) -> Result<u32, LLMError> {
+        Ok(count_tokens(&self.config, text))
+    }
+
+    fn provider_name(&self) -> &str {
+        "claude"
+    }
+
+    fn model_name(&self) -> &str {
+        &self.config.model_name
+    }
+
+    async fn health_check(&self) -> Result<(), LLMError> {
+        let path = format!("/v1/models/{}", self.config.model_name);
+        match self.transport.get(&path).await {
+            Ok(_) => Ok(()),
+            Err(LLMError::APIError { status: 404, .. }) => {
+                Err(LLMError::ModelNotFound(self.config.model_name.clone()))
+            }
+            Err(err) => Err(err),
+        }
+    }
+}
+
+
+/// Statut HTTP associé à un type d'erreur de l'API (erreurs transmises dans le flux)
+fn error_status(kind: &str) -> u16 {
+    match kind {
+        "invalid_request_error" => 400,
+        "authentication_error" => 401,
+        "permission_error" => 403,
+        "not_found_error" => 404,
+        "request_too_large" => 413,
+        "rate_limit_error" => 429,
+        "overloaded_error" => 529,
+        _ => 500,
+    }
+}
+
+fn map_stop_reason(reason: Option<&str>) -> FinishReason {
+    match reason {
+        Some("max_tokens") => FinishReason::Length,
+        Some("tool_use") => FinishReason::ToolUse,
+        Some("refusal") => FinishReason::ContentFilter,
+        _ => FinishReason::Stop,
+    }
+}
+
+
+/// Interprétation des événements SSE de l'API Messages.
+///
+/// Les arguments d'un bloc `tool_use` arrivent par fragments JSON (`input_json_delta`) :
+/// l'appel complet est émis à la fermeture du bloc.
+#[derive(Default)]
+struct ClaudeStreamHandler {
+    input_tokens: u32,
+    output_tokens: u32,
+    tool_uses: HashMap<usize, (ToolCall, String)>,
+}
+
+impl ClaudeStreamHandler {
+    fn usage(&self) -> TokenUsage {
+        TokenUsage {
+            prompt_tokens: self.input_tokens,
+            completion_tokens: self.output_tokens,
+            total_tokens: self.input_tokens + self.output_tokens,
+        }
+    }
+}
+
+impl StreamHandler for ClaudeStreamHandler {
+    fn on_event(&mut self, event: &StreamEvent) -> Result<StreamUpdate, LLMError> {
+        let parsed: WireEvent = serde_json::from_str(&event.data)
+            .map_err(|e| LLMError::ParseError(format!("Événement Claude invalide: {}", e)))?;
+
+        match parsed {
+            WireEvent::MessageStart { message } => {
+                self.input_tokens = message.usage.input_tokens;
+                self.output_tokens = message.usage.output_tokens;
+                Ok(StreamUpdate {
+                    usage: Some(self.usage()),
+                    ..Default::default()
+                })
+            }
+            WireEvent::ContentBlockStart {
+                index,
+                content_block: StartBlock::ToolUse { id, name },
+            } => {
+                let call = ToolCall {
+                    id,
+                    name,
+                    arguments: json!({}),
+                };
+                self.tool_uses.insert(index, (call, String::new()));
+                Ok(StreamUpdate::default())
+            }
+            WireEvent::ContentBlockStart { .. } => Ok(StreamUpdate::default()),
+            WireEvent::ContentBlockDelta { index, delta } => {
+                if let (Some(partial), Some((_, input))) =
+                    (delta.partial_json, self.tool_uses.get_mut(&index))
+                {
+                    input.push_str(&partial);
+                }
+                Ok(StreamUpdate::delta(delta.text.unwrap_or_default()))
+            }
+            WireEvent::ContentBlockStop { index } => {
+                let Some((mut call, input)) = self.tool_uses.remove(&index) else {
+                    return Ok(StreamUpdate::default());
+                };
+                if !input.trim().is_empty() {
+                    call.arguments = serde_json::from_str(&input).map_err(|e| {
+                        LLMError::ParseError(format!("Arguments d'outil Claude invalides: {}", e))
+                    })?;
+                }
+                Ok(StreamUpdate {
+                    tool_calls: vec![call],
+                    ..Default::default()
+                })
+            }
+            WireEvent::MessageDelta { delta, usage } => {
+                if let Some(usage) = usage {
+                    self.output_tokens = usage.output_tokens;
+                }
+                Ok(StreamUpdate {
+                    finish_reason: delta
+                        .stop_reason
+                        .as_deref()
+                        .map(|reason| map_stop_reason(Some(reason))),
+                    usage: Some(self.usage()),
+                    ..Default::default()
+                })
+            }
+            WireEvent::MessageStop => Ok(StreamUpdate {
+                done: true,
+                ..Default::default()
+            }),
+            WireEvent::Error { error } => Err(error_for_status(
+                error_status(&error.kind),
+                error.message,
+                None,
+            )),
+            WireEvent::Other => Ok(StreamUpdate::default()),
+        }
+    }
+}
+
+
+// Format "wire" de l'API Messages
+
+#[derive(Serialize)]
+struct MessagesRequest {
+    model: String,
+    max_tokens: u32,
+    messages: Vec<WireMessage>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    system: Option<String>,
+    temperature: f32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_p: Option<f32>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    stop_sequences: Vec<String>,
+    stream: bool,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    tools: Vec<WireTool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tool_choice: Option<Value>,
+}
+
+#[derive(Serialize)]
+struct WireTool {
+    name: String,
+    description: String,
+    input_schema: Value,
+}
+
+impl From<&ToolDefinition> for WireTool {
+    fn from(tool: &ToolDefinition) -> Self {
+        WireTool {
+            name: tool.name.clone(),
+            description: tool.description.clone(),
+            input_schema: tool.parameters.clone(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct WireMessage {
+    role: &'static str,
+    content: Vec<WireBlock>,
+}
+
+impl WireMessage {
+    fn is_tool_results(&self) -> bool {
+        self.role == "user"
+            && self
+                .content
+                .iter()
+                .all(|block| matches!(block, WireBlock::ToolResult { .. }))
+    }
+}
+
+impl WireMessage {
+    fn from_message(message: &LLMMessage) -> Result<Self, LLMError> {
+        let mut content = Vec::new();
+        match (&message.role, &message.tool_call_id) {
+            (Role::Tool, Some(tool_use_id)) => content.push(WireBlock::ToolResult {
+                tool_use_id: tool_use_id.clone(),
+                content: text_only(&message.content, "claude")?,
+            }),
+            _ => {
+                for part in message.content.parts() {
+                    content.push(WireBlock::try_from(part)?);
+                }
+                content.extend(message.tool_calls.iter().map(|call| WireBlock::ToolUse {
+                    id: call.id.clone(),
+                    name: call.name.clone(),
+                    input: call.arguments.clone(),
+                }));
+            }
+        }
+
+        Ok(WireMessage {
+            role: match message.role {
+                Role::Assistant => "assistant",
+                _ => "user",
+            },
+            content,
+        })
+    }
+}
+
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum WireBlock {
+    Text { text: String },
+    Image { source: WireSource },
+    Document { source: WireSource },
+    ToolUse { id: String, name: String, input: Value },
+    ToolResult { tool_use_id: String, content: String },
+}
+
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum WireSource {
+    Base64 { media_type: String, data: String },
+    Url { url: String },
+}
+
+/// Les fichiers doivent être des URL : une image si le type l'indique, un document sinon
+impl TryFrom<ContentPart> for WireBlock {
+    type Error = LLMError;
+
+    fn try_from(part: ContentPart) -> Result<Self, LLMError> {
+        match part {
+            ContentPart::Text { text } => Ok(WireBlock::Text { text }),
+            ContentPart::Image { media_type, data } => Ok(WireBlock::Image {
+                source: WireSource::Base64 { media_type, data },
+            }),
+            ContentPart::File { uri, media_type } => {
+                if !uri.starts_with("http://") && !uri.starts_with("https://") {
+                    return Err(LLMError::InvalidConfig(format!(
+                        "Le provider claude n'accepte que des fichiers par URL: {}",
+                        uri
+                    )));
+                }
+                let source = WireSource::Url { url: uri };
+                if media_type.is_some_and(|media_type| media_type.starts_with("image/")) {
+                    Ok(WireBlock::Image { source })
+                } else {
+                    Ok(WireBlock::Document { source })
+                }
+            }
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct MessagesResponse {
+    id: String,
+    model: String,
+    content: Vec<ContentBlock>,
+    stop_reason: Option<String>,
+    stop_sequence: Option<String>,
+    usage: WireUsage,
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ContentBlock {
+    Text { text: String },
+    ToolUse { id: String, name: String, input: Value },
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Deserialize, Default)]
+struct WireUsage {
+    #[serde(default)]
+    input_tokens: u32,
+    #[serde(default)]
+    output_tokens: u32,
+}
+
+impl From<WireUsage> for TokenUsage {
+    fn from(usage: WireUsage) -> Self {
+        TokenUsage {
+            prompt_tokens: usage.input_tokens,
+            completion_tokens: usage.output_tokens,
+            total_tokens: usage.input_tokens + usage.output_tokens,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum WireEvent {
+    MessageStart { message: StreamMessage },
+    ContentBlockStart {
+        index: usize,
+        content_block: StartBlock,
+    },
+    ContentBlockDelta { index: usize, delta: StreamDelta },
+    ContentBlockStop { index: usize },
+    MessageDelta {
+        delta: StreamMessageDelta,
+        usage: Option<WireUsage>,
+    },
+    MessageStop,
+    Error { error: StreamError },
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Deserialize)]
+struct StreamMessage {
+    #[serde(default)]
+    usage: WireUsage,
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum StartBlock {
+    ToolUse { id: String, name: String },
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Deserialize)]
+struct StreamDelta {
+    text: Option<String>,
+    partial_json: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct StreamMessageDelta {
+    stop_reason: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct StreamError {
+    #[serde(rename = "type", default)]
+    kind: String,
+    message: String,
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::test_support::{config, message, request, user_request};
+    use crate::llm::LLMProviderType;
+    use serde_json::Value;
+    use wiremock::matchers::{header, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn provider(server: &MockServer) -> ClaudeProvider {
+        let config = config(LLMProviderType::Claude, "claude-sonnet-4-5", &server.uri());
+        ClaudeProvider::new(config).unwrap()
+    }
+
+    fn message_response() -> Value {
+        json!({
+            "id": "msg_1",
+            "model": "claude-sonnet-4-5",
+            "content": [{ "type": "text", "text": "Bonjour" }],
+            "stop_reason": "max_tokens",
+            "stop_sequence": null,
+            "usage": { "input_tokens": 12, "output_tokens": 7 }
+        })
+    }
+
+    async fn sent_body(server: &MockServer) -> Value {
+        let requests = server.received_requests().await.unwrap();
+        requests[0].body_json().unwrap()
+    }
+
+    #[tokio::test]
+    async fn generate_sends_headers_and_lifts_system() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v1/messages"))
+            .and(header("x-api-key", "test-key"))
+            .and(header("anthropic-version", ANTHROPIC_VERSION))
+            .respond_with(ResponseTemplate::new(200).set_body_json(message_response()))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let request = request(vec![
+            message(Role::System, "Tu es concis."),
+            message(Role::User, "Salut"),
+        ]);
+        let response = provider(&server).generate(request).await.unwrap();
+
+        assert_eq!(response.content, "Bonjour");
+        assert_eq!(response.finish_reason, FinishReason::Length);
+        assert_eq!(response.usage.prompt_tokens, 12);
+        assert_eq!(response.usage.completion_tokens, 7);
+        assert_eq!(response.usage.total_tokens, 19);
+
+        let body = sent_body(&server).await;
+        assert_eq!(body["system"], "Tu es concis.");
+        assert_eq!(body["messages"].as_array().unwrap().len(), 1);
+        assert_eq!(body["messages"][0]["role"], "user");
+    }
+
+    #[tokio::test]
+    async fn default_parameters_omit_top_p() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(message_response()))
+            .mount(&server)
+            .await;
+
+        provider(&server).generate(user_request("Salut")).await.unwrap();
+
+        let body = sent_body(&server).await;
+        assert!(body.get("temperature").is_some());
+        assert!(body.get("top_p").is_none());
+    }
+
+    #[tokio::test]
+    async fn error_statuses_are_mapped() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(401).set_body_json(json!({
+                "type": "error",
+                "error": { "type": "authentication_error", "message": "invalid x-api-key" }
+            })))
+            .up_to_n_times(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .respond_with(
+                ResponseTemplate::new(429)
+                    .insert_header("retry-after", "3")
+                    .set_body_json(json!({
+                        "type": "error",
+                        "error": { "type": "rate_limit_error", "message": "slow down" }
+                    })),
+            )
+            .mount(&server)
+            .await;
+
+        let provider = provider(&server);
+        match provider.generate(user_request("Salut")).await {
+            Err(LLMError::AuthenticationError(message)) => {
+                assert_eq!(message, "invalid x-api-key")
+            }
+            other => panic!("AuthenticationError attendue: {:?}", other.map(|r| r.content)),
+        }
+        match provider.generate(user_request("Salut")).await {
+            Err(LLMError::APIError {
+                status,
+                retry_after,
+                ..
+            }) => {
+                assert_eq!(status, 429);
+                assert_eq!(retry_after, Some(std::time::Duration::from_secs(3)));
+            }
+            other => panic!("APIError attendue: {:?}", other.map(|r| r.content)),
+        }
+    }
+
+    fn error_event(kind: &str) -> StreamEvent {
+        StreamEvent {
+            event: Some("error".to_string()),
+            data: json!({ "type": "error", "error": { "type": kind, "message": "échec" } })
+                .to_string(),
+        }
+    }
+
+    #[test]
+    fn stream_errors_follow_the_error_type() {
+        let mut handler = ClaudeStreamHandler::default();
+
+        let error = handler.on_event(&error_event("invalid_request_error")).unwrap_err();
+        assert!(matches!(error, LLMError::APIError { status: 400, .. }));
+        assert!(!crate::llm::retry::is_retryable(&error));
+
+        let error = handler.on_event(&error_event("authentication_error")).unwrap_err();
+        assert!(matches!(error, LLMError::AuthenticationError(_)));
+
+        let error = handler.on_event(&error_event("overloaded_error")).unwrap_err();
+        assert!(matches!(error, LLMError::APIError { status: 529, .. }));
+        assert!(crate::llm::retry::is_retryable(&error));
+    }
+
+    #[test]
+    fn images_and_documents_become_blocks() {
+        let blocks = [
+            ContentPart::image_bytes("image/png", b"png"),
+            ContentPart::File {
+                uri: "https://exemple.fr/capture.png".to_string(),
+                media_type: Some("image/png".to_string()),
+            },
+            ContentPart::File {
+                uri: "https://exemple.fr/spec.pdf".to_string(),
+                media_type: None,
+            },
+        ]
+        .into_iter()
+        .map(|part| serde_json::to_value(WireBlock::try_from(part).unwrap()).unwrap())
+        .collect::<Vec<_>>();
+
+        assert_eq!(
+            blocks,
+            [
+                json!({
+                    "type": "image",
+                    "source": { "type": "base64", "media_type": "image/png", "data": "cG5n" }
+                }),
+                json!({
+                    "type": "image",
+                    "source": { "type": "url", "url": "https://exemple.fr/capture.png" }
+                }),
+                json!({
+                    "type": "document",
+                    "source": { "type": "url", "url": "https://exemple.fr/spec.pdf" }
+                }),
+            ]
+        );
+
+        let uploaded = ContentPart::File {
+            uri: "file-abc".to_string(),
+            media_type: None,
+        };
+        assert!(matches!(WireBlock::try_from(uploaded), Err(LLMError::InvalidConfig(_))));
+    }
+}
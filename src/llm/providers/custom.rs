@@ -0,0 +1,513 @@
+// Provider Custom : API décrite dans la configuration par un gabarit requête/réponse
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+
+use super::{
+    configured_api_key, count_tokens, ensure_no_fim, ensure_no_tools, ensure_text_messages,
+    resolve_parameters, usage_metadata, HttpTransport,
+};
+use crate::llm::streaming::{decode_response, StreamEvent, StreamFormat, StreamHandler, StreamUpdate};
+use crate::llm::{
+    FinishReason, LLMError, LLMProvider, LLMProviderConfig, LLMRequest, LLMResponse, LLMStream,
+    LLMStreamChunk, Role, TokenUsage,
+};
+
+
+/// Description déclarative d'une API pour le provider Custom.
+///
+/// Le corps `body` est un document JSON dont les chaînes peuvent contenir des
+/// placeholders `{{nom}}`. Une chaîne réduite à un seul placeholder est remplacée
+/// par la valeur typée (tableau, nombre...), sinon la valeur est interpolée dans
+/// le texte. Placeholders disponibles : `model`, `messages`, `prompt`, `system`,
+/// `temperature`, `top_p`, `max_tokens`, `presence_penalty`, `frequency_penalty`,
+/// `stop_sequences` et `stream` (`top_p` vaut `null` s'il n'est pas défini).
+///
+/// Les sélecteurs de réponse suivent une syntaxe proche de JSONPath :
+/// `$.choices[0].message.content` ou `output.text`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CustomTemplate {
+    /// Chemin de l'endpoint de génération, relatif à `base_url`
+    pub path: String,
+
+    /// Corps JSON de la requête, avec placeholders
+    pub body: Value,
+
+    /// Sélecteur du texte généré
+    pub content: String,
+
+    /// Sélecteur de la raison de fin (optionnel)
+    #[serde(default)]
+    pub finish_reason: Option<String>,
+
+    /// Sélecteurs de l'usage des tokens (optionnels)
+    #[serde(default)]
+    pub prompt_tokens: Option<String>,
+    #[serde(default)]
+    pub completion_tokens: Option<String>,
+    #[serde(default)]
+    pub total_tokens: Option<String>,
+
+    /// Correspondance entre les raisons de fin de l'API et `stop`, `length`,
+    /// `content_filter` ou `tool_use`
+    #[serde(default)]
+    pub finish_reasons: HashMap<String, String>,
+
+    /// Header portant la clé API (par défaut `Authorization: Bearer <clé>`)
+    #[serde(default)]
+    pub api_key_header: Option<String>,
+
+    /// Chemin interrogé en GET par `health_check` (optionnel)
+    #[serde(default)]
+    pub health_path: Option<String>,
+
+    /// Description du mode streaming ; sans elle, `generate_stream` renvoie la
+    /// réponse complète en un seul chunk
+    #[serde(default)]
+    pub stream: Option<CustomStreamTemplate>,
+}
+
+impl CustomTemplate {
+    /// Lit la raison de fin d'une réponse ou d'un événement
+    fn read_finish_reason(&self, value: &Value) -> Option<FinishReason> {
+        let selector = self.finish_reason.as_deref()?;
+        let raw = match select(value, selector)? {
+            Value::Null => return None,
+            Value::String(raw) => raw.clone(),
+            other => other.to_string(),
+        };
+        let name = self.finish_reasons.get(&raw).unwrap_or(&raw);
+
+        Some(match name.to_ascii_lowercase().as_str() {
+            "length" | "max_tokens" => FinishReason::Length,
+            "content_filter" | "safety" => FinishReason::ContentFilter,
+            "tool_use" | "tool_calls" => FinishReason::ToolUse,
+            _ => FinishReason::Stop,
+        })
+    }
+
+    /// Lit l'usage des tokens d'une réponse ou d'un événement
+    fn read_usage(&self, value: &Value) -> Option<TokenUsage> {
+        let read = |selector: &Option<String>| {
+            selector
+                .as_deref()
+                .and_then(|s| select(value, s))
+                .and_then(Value::as_u64)
+                .map(|n| n as u32)
+        };
+
+        let prompt_tokens = read(&self.prompt_tokens);
+        let completion_tokens = read(&self.completion_tokens);
+        let total_tokens = read(&self.total_tokens);
+        if prompt_tokens.is_none() && completion_tokens.is_none() && total_tokens.is_none() {
+            return None;
+        }
+
+        let prompt_tokens = prompt_tokens.unwrap_or(0);
+        let completion_tokens = completion_tokens.unwrap_or(0);
+        Some(TokenUsage {
+            prompt_tokens,
+            completion_tokens,
+            total_tokens: total_tokens.unwrap_or(prompt_tokens + completion_tokens),
+        })
+    }
+}
+
+/// Description du flux de réponse d'une API Custom
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CustomStreamTemplate {
+    /// Format du flux
+    pub format: StreamFormat,
+
+    /// Chemin de l'endpoint de streaming (par défaut celui de la génération)
+    #[serde(default)]
+    pub path: Option<String>,
+
+    /// Sélecteur du texte partiel dans chaque événement ; la raison de fin et
+    /// l'usage sont lus avec les sélecteurs du gabarit principal
+    pub delta: String,
+}
+
+
+/// Provider configuré entièrement par un `CustomTemplate`
+pub struct CustomProvider {
+    config: LLMProviderConfig,
+    template: CustomTemplate,
+    transport: HttpTransport,
+}
+
+impl CustomProvider {
+    pub fn new(config: LLMProviderConfig) -> Result<Self, LLMError> {
+        let template = config.template.clone().ok_or_else(|| {
+            LLMError::InvalidConfig("Section `template` manquante pour le provider custom".to_string())
+        })?;
+        if config.base_url.as_deref().filter(|url| !url.trim().is_empty()).is_none() {
+            return Err(LLMError::InvalidConfig(
+                "base_url est requis pour le provider custom".to_string(),
+            ));
+        }
+
+        let mut default_headers = Vec::new();
+        if let Some(api_key) = configured_api_key(&config)? {
+            match template.api_key_header.as_deref() {
+                Some(header) if !header.eq_ignore_ascii_case("authorization") => {
+                    default_headers.push((header, api_key))
+                }
+                _ => default_headers.push(("authorization", format!("Bearer {}", api_key))),
+            }
+        }
+
+        let transport = HttpTransport::new(&config, "", default_headers)?;
+
+        Ok(CustomProvider {
+            config,
+            template,
+            transport,
+        })
+    }
+
+    /// Valeurs des placeholders pour une requête
+    fn variables(&self, request: &LLMRequest, stream: bool) -> HashMap<&'static str, Value> {
+        let parameters = resolve_parameters(&self.config, request);
+
+        let messages: Vec<Value> = request
+            .messages
+            .iter()
+            .map(|m| {
+                let role = match m.role {
+                    Role::System => "system",
+                    Role::User => "user",
+                    Role::Assistant => "assistant",
+                    Role::Tool => "tool",
+                };
+                serde_json::json!({ "role": role, "content": m.content.text() })
+            })
+            .collect();
+
+        let system: Vec<String> = request
+            .messages
+            .iter()
+            .filter(|m| m.role == Role::System)
+            .map(|m| m.content.text())
+            .collect();
+        let prompt: Vec<String> = request
+            .messages
+            .iter()
+            .filter(|m| m.role != Role::System)
+            .map(|m| m.content.text())
+            .collect();
+
+        HashMap::from([
+            ("model", Value::from(self.config.model_name.clone())),
+            ("messages", Value::Array(messages)),
+            ("prompt", Value::from(prompt.join("\n\n"))),
+            (
+                "system",
+                if system.is_empty() { Value::Null } else { Value::from(system.join("\n\n")) },
+            ),
+            ("temperature", Value::from(parameters.temperature)),
+            ("top_p", Value::from(parameters.top_p)),
+            ("max_tokens", Value::from(parameters.max_tokens)),
+            ("presence_penalty", Value::from(parameters.presence_penalty)),
+            ("frequency_penalty", Value::from(parameters.frequency_penalty)),
+            ("stop_sequences", Value::from(parameters.stop_sequences)),
+            ("stream", Value::from(stream)),
+        ])
+    }
+}
+
+#[async_trait]
+impl LLMProvider for CustomProvider {
+    async fn generate(&self, request: LLMRequest) -> Result<LLMResponse, LLMError> {
+        ensure_no_fim(&request, self.provider_name())?;
+        ensure_no_tools(&request, self.provider_name())?;
+        ensure_text_messages(&request, self.provider_name())?;
+        let body = render(&self.template.body, &self.variables(&request, false))?;
+        let response = self.transport.post_json(&self.template.path, &body, false).await?;
+
+        let value: Value = response
+            .json()
+            .await
+            .map_err(|e| LLMError::ParseError(format!("Réponse custom invalide: {}", e)))?;
+
+        let content = select(&value, &self.template.content)
+            .and_then(Value::as_str)
+            .ok_or_else(|| {
+                LLMError::ParseError(format!(
+                    "Le sélecteur `{}` ne désigne aucun texte dans la réponse",
+                    self.template.content
+                ))
+            })?
+            .to_string();
+
+        Ok(LLMResponse {
+            content,
+            finish_reason: self.template.read_finish_reason(&value).unwrap_or(FinishReason::Stop),
+            usage: self.template.read_usage(&value).unwrap_or_default(),
+            model: self.config.model_name.clone(),
+            metadata: None,
+            tool_calls: Vec::new(),
+        })
+    }
+
+    async fn generate_stream(&self, request: LLMRequest) -> Result<LLMStream, LLMError> {
+        ensure_no_fim(&request, self.provider_name())?;
+        ensure_no_tools(&request, self.provider_name())?;
+        ensure_text_messages(&request, self.provider_name())?;
+
+        let Some(stream_template) = self.template.stream.clone() else {
+            // Pas de streaming natif : la réponse complète est renvoyée en un chunk final
+            let response = self.generate(request).await?;
+            let chunk = LLMStreamChunk {
+                delta: response.content,
+                finish_reason: Some(response.finish_reason),
+                metadata: Some(usage_metadata(&response.usage)),
+                tool_calls: Vec::new(),
+            };
+            return Ok(Box::new(futures::stream::iter(vec![Ok::<_, LLMError>(chunk)])));
+        };
+
+        let body = render(&self.template.body, &self.variables(&request, true))?;
+        let path = stream_template.path.as_deref().unwrap_or(&self.template.path);
+        let response = self.transport.post_json(path, &body, true).await?;
+
+        let handler = CustomStreamHandler {
+            template: self.template.clone(),
+            delta: stream_template.delta,
+        };
+
+        Ok(decode_response(response, stream_template.format, handler))
+    }
+
+    fn count_tokens(&self, text: &str) -> Result<u32, LLMError> {
+        Ok(count_tokens(&self.config, text))
+    }
+
+    fn provider_name(&self) -> &str {
+        "custom"
+    }
+
+    fn model_name(&self) -> &str {
+        &self.config.model_name
+    }
+
+    async fn health_check(&self) -> Result<(), LLMError> {
+        if let Some(path) = &self.template.health_path {
+            self.transport.get(path).await?;
+        }
+        Ok(())
+    }
+}
+
+
+/// Interprétation des événements du flux à l'aide des sélecteurs du gabarit
+struct CustomStreamHandler {
+    template: CustomTemplate,
+    delta: String,
+}
+
+impl StreamHandler for CustomStreamHandler {
+    fn on_event(&mut self, event: &StreamEvent) -> Result<StreamUpdate, LLMError> {
+        let value: Value = serde_json::from_str(&event.data)
+            .map_err(|e| LLMError::ParseError(format!("Événement custom invalide: {}", e)))?;
+
+        Ok(StreamUpdate {
+            delta: select(&value, &self.delta)
+                .and_then(Value::as_str)
+                .unwrap_or_default()
+                .to_string(),
+            finish_reason: self.template.read_finish_reason(&value),
+            usage: self.template.read_usage(&value),
+            ..Default::default()
+        })
+    }
+
+    /// Sans sélecteur de raison de fin, rien d'autre ne signale la fin du flux
+    fn ends_at_eof(&self) -> bool {
+        self.template.finish_reason.is_none()
+    }
+}
+
+
+/// Remplace les placeholders `{{nom}}` dans un gabarit JSON
+fn render(template: &Value, variables: &HashMap<&'static str, Value>) -> Result<Value, LLMError> {
+    match template {
+        Value::String(text) => render_string(text, variables),
+        Value::Array(items) => items
+            .iter()
+            .map(|item| render(item, variables))
+            .collect::<Result<Vec<_>, _>>()
+            .map(Value::Array),
+        Value::Object(fields) => {
+            let mut rendered = Map::new();
+            for (key, value) in fields {
+                rendered.insert(key.clone(), render(value, variables)?);
+            }
+            Ok(Value::Object(rendered))
+        }
+        other => Ok(other.clone()),
+    }
+}
+
+fn render_string(text: &str, variables: &HashMap<&'static str, Value>) -> Result<Value, LLMError> {
+    // Chaîne réduite à un placeholder : valeur typée
+    if let Some(name) = text
+        .trim()
+        .strip_prefix("{{")
+        .and_then(|rest| rest.strip_suffix("}}"))
+        .filter(|name| !name.contains("{{") && !name.contains("}}"))
+    {
+        return lookup(name, variables).cloned();
+    }
+
+    let mut rendered = String::new();
+    let mut rest = text;
+    while let Some(start) = rest.find("{{") {
+        rendered.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+        let end = after.find("}}").ok_or_else(|| {
+            LLMError::InvalidConfig(format!("Placeholder non fermé dans le gabarit: {}", text))
+        })?;
+
+        match lookup(&after[..end], variables)? {
+            Value::String(value) => rendered.push_str(value),
+            value => rendered.push_str(&value.to_string()),
+        }
+        rest = &after[end + 2..];
+    }
+    rendered.push_str(rest);
+
+    Ok(Value::String(rendered))
+}
+
+fn lookup<'a>(name: &str, variables: &'a HashMap<&'static str, Value>) -> Result<&'a Value, LLMError> {
+    let name = name.trim();
+    variables.get(name).ok_or_else(|| {
+        LLMError::InvalidConfig(format!("Placeholder inconnu dans le gabarit: {{{{{}}}}}", name))
+    })
+}
+
+/// Évalue un sélecteur de type `$.choices[0].message.content`
+fn select<'a>(value: &'a Value, selector: &str) -> Option<&'a Value> {
+    let path = selector.trim().trim_start_matches('$').trim_start_matches('.');
+    let mut current = value;
+
+    for segment in path.split('.').filter(|segment| !segment.is_empty()) {
+        let (key, indices) = match segment.find('[') {
+            Some(pos) => segment.split_at(pos),
+            None => (segment, ""),
+        };
+        if !key.is_empty() {
+            current = current.get(key)?;
+        }
+        for index in indices.split('[').filter(|index| !index.is_empty()) {
+            let index: usize = index.trim_end_matches(']').parse().ok()?;
+            current = current.get(index)?;
+        }
+    }
+
+    Some(current)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::test_support::{config, message, request};
+    use crate::llm::LLMProviderType;
+    use serde_json::json;
+    use wiremock::matchers::{header, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn template() -> CustomTemplate {
+        serde_json::from_value(json!({
+            "path": "/generate",
+            "body": {
+                "model": "{{model}}",
+                "input": { "system": "{{system}}", "prompt": "Question : {{prompt}}" },
+                "max_new_tokens": "{{max_tokens}}"
+            },
+            "content": "$.output[0].text",
+            "finish_reason": "$.output[0].reason",
+            "finish_reasons": { "budget": "length" },
+            "prompt_tokens": "usage.input",
+            "completion_tokens": "usage.output",
+            "api_key_header": "x-token"
+        }))
+        .unwrap()
+    }
+
+    fn variables() -> HashMap<&'static str, Value> {
+        HashMap::from([
+            ("model", json!("m1")),
+            ("max_tokens", json!(256)),
+            ("stop_sequences", json!(["\n\n"])),
+        ])
+    }
+
+    #[test]
+    fn render_keeps_types_of_lone_placeholders() {
+        let template = json!({ "n": "{{max_tokens}}", "stop": "{{ stop_sequences }}" });
+        let rendered = render(&template, &variables()).unwrap();
+        assert_eq!(rendered, json!({ "n": 256, "stop": ["\n\n"] }));
+    }
+
+    #[test]
+    fn render_interpolates_inside_text() {
+        let rendered = render(&json!("{{model}} ({{max_tokens}} tokens)"), &variables()).unwrap();
+        assert_eq!(rendered, json!("m1 (256 tokens)"));
+    }
+
+    #[test]
+    fn render_rejects_unknown_or_unclosed_placeholders() {
+        assert!(render(&json!("{{inconnu}}"), &variables()).is_err());
+        assert!(render(&json!("texte {{model"), &variables()).is_err());
+    }
+
+    #[test]
+    fn select_follows_keys_and_indices() {
+        let value = json!({ "choices": [{ "message": { "content": "ok" } }], "n": 3 });
+        assert_eq!(select(&value, "$.choices[0].message.content"), Some(&json!("ok")));
+        assert_eq!(select(&value, "n"), Some(&json!(3)));
+        assert_eq!(select(&value, "choices[1]"), None);
+    }
+
+    #[tokio::test]
+    async fn generate_renders_template_and_reads_selectors() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/generate"))
+            .and(header("x-token", "test-key"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "output": [{ "text": "42", "reason": "budget" }],
+                "usage": { "input": 7, "output": 1 }
+            })))
+            .mount(&server)
+            .await;
+
+        let mut config = config(LLMProviderType::Custom, "m1", &server.uri());
+        config.template = Some(template());
+        let provider = CustomProvider::new(config).unwrap();
+
+        let request = request(vec![
+            message(Role::System, "Sois bref."),
+            message(Role::User, "Le sens de la vie ?"),
+        ]);
+        let response = provider.generate(request).await.unwrap();
+        assert_eq!(response.content, "42");
+        assert_eq!(response.finish_reason, FinishReason::Length);
+        assert_eq!(response.usage.total_tokens, 8);
+
+        let body: Value = server.received_requests().await.unwrap()[0].body_json().unwrap();
+        assert_eq!(
+            body,
+            json!({
+                "model": "m1",
+                "input": { "system": "Sois bref.", "prompt": "Question : Le sens de la vie ?" },
+                "max_new_tokens": 4096
+            })
+        );
+    }
+}
@@ -0,0 +1,854 @@
+// Provider Google Gemini (API generateContent)
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+
+use super::{
+    api_key, count_tokens, ensure_no_fim, resolve_parameters, text_only, tool_name_for,
+    HttpTransport,
+};
+use crate::llm::streaming::{decode_response, StreamEvent, StreamFormat, StreamHandler, StreamUpdate};
+use crate::llm::{
+    ContentPart, FinishReason, LLMError, LLMMessage, LLMProvider, LLMProviderConfig, LLMRequest,
+    LLMResponse, LLMStream, Role, TokenUsage, ToolCall, ToolChoice,
+};
+
+const DEFAULT_BASE_URL: &str = "https://generativelanguage.googleapis.com";
+const API_VERSION: &str = "v1beta";
+
+
+/// Provider pour les modèles Gemini
+pub struct GeminiProvider {
+    config: LLMProviderConfig,
+    transport: HttpTransport,
+}
+
+impl GeminiProvider {
+    pub fn new(config: LLMProviderConfig) -> Result<Self, LLMError> {
+        let default_headers = match api_key(&config, "Gemini")? {
+            Some(api_key) => vec![("x-goog-api-key", api_key)],
+            None => Vec::new(),
+        };
+
+        let transport = HttpTransport::new(&config, DEFAULT_BASE_URL, default_headers)?;
+
+        Ok(GeminiProvider { config, transport })
+    }
+
+    /// Chemin de la ressource du modèle (`models/<nom>`)
+    fn model_path(&self) -> String {
+        let model = &self.config.model_name;
+        if model.starts_with("models/") {
+            format!("/{}/{}", API_VERSION, model)
+        } else {
+            format!("/{}/models/{}", API_VERSION, model)
+        }
+    }
+
+    /// Construit la requête : `Role::Assistant` devient `model`, les messages
+    /// `Role::System` sont regroupés dans `systemInstruction` et les résultats
+    /// d'outils deviennent des parts `functionResponse`.
+    fn build_body(&self, request: &LLMRequest) -> Result<GenerateRequest, LLMError> {
+        let parameters = resolve_parameters(&self.config, request);
+
+        let system = request
+            .messages
+            .iter()
+            .filter(|m| m.role == Role::System)
+            .map(|m| text_only(&m.content, self.provider_name()).map(Part::text))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let tools = (!request.tools.is_empty()).then(|| {
+            vec![WireTool {
+                function_declarations: request
+                    .tools
+                    .iter()
+                    .map(|tool| FunctionDeclaration {
+                        name: tool.name.clone(),
+                        description: tool.description.clone(),
+                        parameters: tool.parameters.clone(),
+                    })
+                    .collect(),
+            }]
+        });
+
+        // Les résultats d'outils consécutifs forment un seul tour `user`
+        let mut contents: Vec<Content> = Vec::new();
+        for message in request.messages.iter().filter(|m| m.role != Role::System) {
+            let content = Content::from_message(message, &request.messages)?;
+            match contents.last_mut() {
+                Some(last) if message.role == Role::Tool && last.is_tool_results() => {
+                    last.parts.extend(content.parts);
+                }
+                _ => contents.push(content),
+            }
+        }
+
+        Ok(GenerateRequest {
+            contents,
+            tools,
+            tool_config: request.tool_choice.as_ref().map(ToolConfig::from),
+            system_instruction: (!system.is_empty()).then(|| SystemInstruction { parts: system }),
+            generation_config: GenerationConfig {
+                temperature: parameters.temperature,
+                top_p: parameters.top_p,
+                max_output_tokens: parameters.max_tokens,
+                presence_penalty: parameters.presence_penalty,
+                frequency_penalty: parameters.frequency_penalty,
+                stop_sequences: parameters.stop_sequences,
+            },
+        })
+    }
+}
+
+#[async_trait]
+impl LLMProvider for GeminiProvider {
+    async fn generate(&self, request: LLMRequest) -> Result<LLMResponse, LLMError> {
+        ensure_no_fim(&request, self.provider_name())?;
+        let body = self.build_body(&request)?;
+        let path = format!("{}:generateContent", self.model_path());
+        let response = self.transport.post_json(&path, &body, false).await?;
+
+        let parsed: GenerateResponse = response
+            .json()
+            .await
+            .map_err(|e| LLMError::ParseError(format!("Réponse Gemini invalide: {}", e)))?;
+
+        let candidate = parsed.candidates.first();
+        let tool_calls = candidate.map(|c| c.tool_calls(0)).unwrap_or_default();
+        let finish_reason = match parsed.finish_reason() {
+            FinishReason::Stop if !tool_calls.is_empty() => FinishReason::ToolUse,
+            reason => reason,
+        };
+
+        Ok(LLMResponse {
+            content: candidate.map(Candidate::text).unwrap_or_default(),
+            finish_reason,
+            usage: parsed.usage(),
+            model: parsed
+                .model_version
+                .clone()
+                .unwrap_or_else(|| self.config.model_name.clone()),
+            metadata: Some(parsed.safety_metadata()),
+            tool_calls,
+        })
+    }
+
+    async fn generate_stream(&self, request: LLMRequest) -> Result<LLMStream, LLMError> {
+        ensure_no_fim(&request, self.provider_name())?;
+        let body = self.build_body(&request)?;
+        let path = format!("{}:streamGenerateContent?alt=sse", self.model_path());
+        let response = self.transport.post_json(&path, &body, true).await?;
+
+        Ok(decode_response(response, StreamFormat::Sse, GeminiStreamHandler::default()))
+    }
+
+    fn count_tokens(&self, text: &str) -> Result<u32, LLMError> {
+        Ok(count_tokens(&self.config, text))
+    }
+
+    fn provider_name(&self) -> &str {
+        "gemini"
+    }
+
+    fn model_name(&self) -> &str {
+        &self.config.model_name
+    }
+
+    async fn health_check(&self) -> Result<(), LLMError> {
+        match self.transport.get(&self.model_path()).await {
+            Ok(_) => Ok(()),
+            Err(LLMError::APIError { status: 404, .. }) => {
+                Err(LLMError::ModelNotFound(self.config.model_name.clone()))
+            }
+            Err(err) => Err(err),
+        }
+    }
+}
+
+
+/// Les blocages de sécurité (candidat ou prompt) sont rapportés comme `ContentFilter`
+fn map_finish_reason(reason: Option<&str>) -> FinishReason {
+    match reason {
+        Some("MAX_TOKENS") => FinishReason::Length,
+        Some("SAFETY") | Some("RECITATION") | Some("BLOCKLIST") | Some("PROHIBITED_CONTENT")
+        | Some("SPII") | Some("IMAGE_SAFETY") => FinishReason::ContentFilter,
+        _ => FinishReason::Stop,
+    }
+}
+
+/// Interprétation des événements SSE ; celui qui porte `finishReason` (ou un blocage
+/// du prompt) termine le flux avec l'usage et les évaluations de sécurité
+#[derive(Default)]
+struct GeminiStreamHandler {
+    /// Nombre d'appels d'outils déjà reçus (pour numéroter les identifiants)
+    tool_calls: usize,
+}
+
+impl StreamHandler for GeminiStreamHandler {
+    fn on_event(&mut self, event: &StreamEvent) -> Result<StreamUpdate, LLMError> {
+        let parsed: GenerateResponse = serde_json::from_str(&event.data)
+            .map_err(|e| LLMError::ParseError(format!("Chunk Gemini invalide: {}", e)))?;
+
+        let mut update =
+            StreamUpdate::delta(parsed.candidates.first().map(Candidate::text).unwrap_or_default());
+        if let Some(candidate) = parsed.candidates.first() {
+            update.tool_calls = candidate.tool_calls(self.tool_calls);
+            self.tool_calls += update.tool_calls.len();
+        }
+        if parsed.usage_metadata.is_some() {
+            update.usage = Some(parsed.usage());
+        }
+
+        let finished = parsed.is_blocked()
+            || parsed
+                .candidates
+                .first()
+                .is_some_and(|candidate| candidate.finish_reason.is_some());
+        if finished {
+            update.finish_reason = Some(match parsed.finish_reason() {
+                FinishReason::Stop if self.tool_calls > 0 => FinishReason::ToolUse,
+                reason => reason,
+            });
+            update.metadata = parsed.safety_metadata();
+            update.done = true;
+        }
+
+        Ok(update)
+    }
+}
+
+
+// Format "wire" de l'API Gemini
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct GenerateRequest {
+    contents: Vec<Content>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    system_instruction: Option<SystemInstruction>,
+    generation_config: GenerationConfig,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tools: Option<Vec<WireTool>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tool_config: Option<ToolConfig>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct WireTool {
+    function_declarations: Vec<FunctionDeclaration>,
+}
+
+#[derive(Serialize)]
+struct FunctionDeclaration {
+    name: String,
+    description: String,
+    parameters: Value,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ToolConfig {
+    function_calling_config: FunctionCallingConfig,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct FunctionCallingConfig {
+    mode: &'static str,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    allowed_function_names: Vec<String>,
+}
+
+impl From<&ToolChoice> for ToolConfig {
+    fn from(choice: &ToolChoice) -> Self {
+        let (mode, allowed_function_names) = match choice {
+            ToolChoice::Auto => ("AUTO", Vec::new()),
+            ToolChoice::None => ("NONE", Vec::new()),
+            ToolChoice::Required => ("ANY", Vec::new()),
+            ToolChoice::Tool(name) => ("ANY", vec![name.clone()]),
+        };
+        ToolConfig {
+            function_calling_config: FunctionCallingConfig {
+                mode,
+                allowed_function_names,
+            },
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct Content {
+    #[serde(default)]
+    role: String,
+    #[serde(default)]
+    parts: Vec<Part>,
+}
+
+impl Content {
+    /// `history` sert à retrouver le nom de l'outil d'un message `Role::Tool`,
+    /// l'API identifiant les résultats par nom et non par identifiant d'appel
+    fn from_message(message: &LLMMessage, history: &[LLMMessage]) -> Result<Self, LLMError> {
+        let role = match message.role {
+            Role::Assistant => "model",
+            _ => "user",
+        };
+
+        let mut parts = Vec::new();
+        if message.role == Role::Tool {
+            let id = message.tool_call_id.as_deref().unwrap_or_default();
+            let name = tool_name_for(history, id).ok_or_else(|| {
+                LLMError::InvalidConfig(format!(
+                    "Résultat d'outil sans appel correspondant (tool_call_id: {:?})",
+                    id
+                ))
+            })?;
+            // La réponse doit être un objet JSON
+            let text = text_only(&message.content, "gemini")?;
+            let response = match serde_json::from_str::<Value>(&text) {
+                Ok(value @ Value::Object(_)) => value,
+                _ => json!({ "content": text }),
+            };
+            parts.push(Part {
+                function_response: Some(FunctionResponse {
+                    name: name.to_string(),
+                    response,
+                }),
+                ..Default::default()
+            });
+        } else {
+            for part in message.content.parts() {
+                parts.push(Part::try_from(part)?);
+            }
+            parts.extend(message.tool_calls.iter().map(|call| Part {
+                function_call: Some(FunctionCall {
+                    name: call.name.clone(),
+                    args: call.arguments.clone(),
+                }),
+                ..Default::default()
+            }));
+        }
+
+        Ok(Content {
+            role: role.to_string(),
+            parts,
+        })
+    }
+
+    fn is_tool_results(&self) -> bool {
+        !self.parts.is_empty() && self.parts.iter().all(|part| part.function_response.is_some())
+    }
+}
+
+#[derive(Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+struct Part {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    text: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    function_call: Option<FunctionCall>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    function_response: Option<FunctionResponse>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    inline_data: Option<Blob>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    file_data: Option<FileData>,
+}
+
+impl Part {
+    fn text(text: String) -> Self {
+        Part {
+            text: Some(text),
+            ..Default::default()
+        }
+    }
+}
+
+/// Les images sont envoyées en `inlineData`, les fichiers (URI Files API ou
+/// Cloud Storage) en `fileData`, qui exige un type MIME
+impl TryFrom<ContentPart> for Part {
+    type Error = LLMError;
+
+    fn try_from(part: ContentPart) -> Result<Self, LLMError> {
+        match part {
+            ContentPart::Text { text } => Ok(Part::text(text)),
+            ContentPart::Image { media_type, data } => Ok(Part {
+                inline_data: Some(Blob {
+                    mime_type: media_type,
+                    data,
+                }),
+                ..Default::default()
+            }),
+            ContentPart::File { uri, media_type } => {
+                let mime_type = media_type.ok_or_else(|| {
+                    LLMError::InvalidConfig(format!(
+                        "Le provider gemini exige le type MIME du fichier {}",
+                        uri
+                    ))
+                })?;
+                Ok(Part {
+                    file_data: Some(FileData {
+                        mime_type,
+                        file_uri: uri,
+                    }),
+                    ..Default::default()
+                })
+            }
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Blob {
+    mime_type: String,
+    data: String,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct FileData {
+    mime_type: String,
+    file_uri: String,
+}
+
+#[derive(Serialize, Deserialize)]
+struct FunctionCall {
+    name: String,
+    #[serde(default)]
+    args: Value,
+}
+
+#[derive(Serialize, Deserialize)]
+struct FunctionResponse {
+    name: String,
+    response: Value,
+}
+
+#[derive(Serialize)]
+struct SystemInstruction {
+    parts: Vec<Part>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct GenerationConfig {
+    temperature: f32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_p: Option<f32>,
+    max_output_tokens: u32,
+    presence_penalty: f32,
+    frequency_penalty: f32,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    stop_sequences: Vec<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GenerateResponse {
+    #[serde(default)]
+    candidates: Vec<Candidate>,
+    #[serde(default)]
+    prompt_feedback: Option<PromptFeedback>,
+    #[serde(default)]
+    usage_metadata: Option<UsageMetadata>,
+    #[serde(default)]
+    model_version: Option<String>,
+}
+
+impl GenerateResponse {
+    /// Le prompt lui-même a été bloqué : aucun candidat n'est renvoyé
+    fn is_blocked(&self) -> bool {
+        self.prompt_feedback
+            .as_ref()
+            .is_some_and(|feedback| feedback.block_reason.is_some())
+    }
+
+    fn finish_reason(&self) -> FinishReason {
+        if self.is_blocked() {
+            return FinishReason::ContentFilter;
+        }
+        map_finish_reason(
+            self.candidates
+                .first()
+                .and_then(|candidate| candidate.finish_reason.as_deref()),
+        )
+    }
+
+    fn usage(&self) -> TokenUsage {
+        let usage = self.usage_metadata.as_ref();
+        let prompt_tokens = usage.map_or(0, |u| u.prompt_token_count);
+        let completion_tokens = usage.map_or(0, |u| u.candidates_token_count);
+
+        TokenUsage {
+            prompt_tokens,
+            completion_tokens,
+            total_tokens: usage
+                .map_or(0, |u| u.total_token_count)
+                .max(prompt_tokens + completion_tokens),
+        }
+    }
+
+    /// Raisons de blocage et évaluations de sécurité, à plat dans les métadonnées.
+    ///
+    /// Clés produites : `block_reason`, `finish_reason`, `safety.<catégorie>` pour le
+    /// candidat et `prompt_safety.<catégorie>` pour le prompt. Les valeurs sont la
+    /// probabilité, suffixée de `:blocked` quand la catégorie a causé le blocage.
+    fn safety_metadata(&self) -> HashMap<String, String> {
+        let mut metadata = HashMap::new();
+
+        if let Some(feedback) = &self.prompt_feedback {
+            if let Some(reason) = &feedback.block_reason {
+                metadata.insert("block_reason".to_string(), reason.clone());
+            }
+            insert_ratings(&mut metadata, "prompt_safety", &feedback.safety_ratings);
+        }
+        if let Some(candidate) = self.candidates.first() {
+            if let Some(reason) = &candidate.finish_reason {
+                metadata.insert("finish_reason".to_string(), reason.clone());
+            }
+            insert_ratings(&mut metadata, "safety", &candidate.safety_ratings);
+        }
+
+        metadata
+    }
+}
+
+fn insert_ratings(metadata: &mut HashMap<String, String>, prefix: &str, ratings: &[SafetyRating]) {
+    for rating in ratings {
+        let value = if rating.blocked {
+            format!("{}:blocked", rating.probability)
+        } else {
+            rating.probability.clone()
+        };
+        metadata.insert(format!("{}.{}", prefix, rating.category), value);
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Candidate {
+    #[serde(default)]
+    content: Option<Content>,
+    #[serde(default)]
+    finish_reason: Option<String>,
+    #[serde(default)]
+    safety_ratings: Vec<SafetyRating>,
+}
+
+impl Candidate {
+    fn text(&self) -> String {
+        self.parts().filter_map(|part| part.text.as_deref()).collect()
+    }
+
+    /// Appels d'outils du candidat. L'API ne fournit pas d'identifiant : il est
+    /// construit à partir du nom et du rang de l'appel dans la réponse (`offset`).
+    fn tool_calls(&self, offset: usize) -> Vec<ToolCall> {
+        self.parts()
+            .filter_map(|part| part.function_call.as_ref())
+            .enumerate()
+            .map(|(i, call)| ToolCall {
+                id: format!("{}_{}", call.name, offset + i),
+                name: call.name.clone(),
+                arguments: call.args.clone(),
+            })
+            .collect()
+    }
+
+    fn parts(&self) -> impl Iterator<Item = &Part> {
+        self.content.iter().flat_map(|content| &content.parts)
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PromptFeedback {
+    #[serde(default)]
+    block_reason: Option<String>,
+    #[serde(default)]
+    safety_ratings: Vec<SafetyRating>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SafetyRating {
+    category: String,
+    #[serde(default)]
+    probability: String,
+    #[serde(default)]
+    blocked: bool,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct UsageMetadata {
+    #[serde(default)]
+    prompt_token_count: u32,
+    #[serde(default)]
+    candidates_token_count: u32,
+    #[serde(default)]
+    total_token_count: u32,
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::test_support::{config, message, request, user_request};
+    use crate::llm::LLMProviderType;
+    use wiremock::matchers::{header, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    const GENERATE_PATH: &str = "/v1beta/models/gemini-2.5-flash:generateContent";
+
+    fn provider(server: &MockServer) -> GeminiProvider {
+        let config = config(LLMProviderType::Gemini, "gemini-2.5-flash", &server.uri());
+        GeminiProvider::new(config).unwrap()
+    }
+
+    async fn mount(server: &MockServer, body: Value) {
+        Mock::given(method("POST"))
+            .and(path(GENERATE_PATH))
+            .and(header("x-goog-api-key", "test-key"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(body))
+            .mount(server)
+            .await;
+    }
+
+    async fn sent_body(server: &MockServer) -> Value {
+        server.received_requests().await.unwrap()[0].body_json().unwrap()
+    }
+
+    #[tokio::test]
+    async fn generate_maps_roles_and_usage() {
+        let server = MockServer::start().await;
+        mount(
+            &server,
+            json!({
+                "candidates": [{
+                    "content": { "role": "model", "parts": [{ "text": "Bonjour" }] },
+                    "finishReason": "STOP"
+                }],
+                "usageMetadata": {
+                    "promptTokenCount": 8,
+                    "candidatesTokenCount": 2,
+                    "totalTokenCount": 10
+                },
+                "modelVersion": "gemini-2.5-flash-001"
+            }),
+        )
+        .await;
+
+        let request = request(vec![
+            message(Role::System, "Réponds en français."),
+            message(Role::User, "Hello"),
+            message(Role::Assistant, "Bonjour !"),
+            message(Role::User, "Et encore ?"),
+        ]);
+        let response = provider(&server).generate(request).await.unwrap();
+        assert_eq!(response.content, "Bonjour");
+        assert_eq!(response.finish_reason, FinishReason::Stop);
+        assert_eq!(response.usage.total_tokens, 10);
+        assert_eq!(response.model, "gemini-2.5-flash-001");
+
+        let body = sent_body(&server).await;
+        assert_eq!(body["systemInstruction"]["parts"][0]["text"], "Réponds en français.");
+        let roles: Vec<_> = body["contents"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|content| content["role"].as_str().unwrap())
+            .collect();
+        assert_eq!(roles, ["user", "model", "user"]);
+        assert_eq!(body["generationConfig"]["maxOutputTokens"], 4096);
+    }
+
+    #[tokio::test]
+    async fn safety_blocks_are_content_filter() {
+        let server = MockServer::start().await;
+        mount(
+            &server,
+            json!({
+                "candidates": [{
+                    "finishReason": "SAFETY",
+                    "safetyRatings": [{
+                        "category": "HARM_CATEGORY_DANGEROUS_CONTENT",
+                        "probability": "HIGH",
+                        "blocked": true
+                    }]
+                }]
+            }),
+        )
+        .await;
+
+        let response = provider(&server).generate(user_request("...")).await.unwrap();
+        assert_eq!(response.finish_reason, FinishReason::ContentFilter);
+        let metadata = response.metadata.unwrap();
+        assert_eq!(metadata["finish_reason"], "SAFETY");
+        assert_eq!(metadata["safety.HARM_CATEGORY_DANGEROUS_CONTENT"], "HIGH:blocked");
+    }
+
+    #[tokio::test]
+    async fn blocked_prompt_has_no_candidate() {
+        let server = MockServer::start().await;
+        mount(
+            &server,
+            json!({ "promptFeedback": { "blockReason": "PROHIBITED_CONTENT" } }),
+        )
+        .await;
+
+        let response = provider(&server).generate(user_request("...")).await.unwrap();
+        assert_eq!(response.content, "");
+        assert_eq!(response.finish_reason, FinishReason::ContentFilter);
+        assert_eq!(response.metadata.unwrap()["block_reason"], "PROHIBITED_CONTENT");
+    }
+
+    fn weather_call(id: &str, city: &str) -> ToolCall {
+        ToolCall {
+            id: id.to_string(),
+            name: "weather".to_string(),
+            arguments: json!({ "city": city }),
+        }
+    }
+
+    fn tool_result(id: &str, text: &str) -> LLMMessage {
+        LLMMessage {
+            tool_call_id: Some(id.to_string()),
+            ..message(Role::Tool, text)
+        }
+    }
+
+    #[tokio::test]
+    async fn function_calls_are_tool_use() {
+        let server = MockServer::start().await;
+        mount(
+            &server,
+            json!({
+                "candidates": [{
+                    "content": {
+                        "role": "model",
+                        "parts": [
+                            { "functionCall": { "name": "weather", "args": { "city": "Paris" } } },
+                            { "functionCall": { "name": "weather", "args": { "city": "Lyon" } } }
+                        ]
+                    },
+                    "finishReason": "STOP"
+                }]
+            }),
+        )
+        .await;
+
+        let mut request = user_request("Quel temps ?");
+        request.tools = vec![crate::llm::ToolDefinition {
+            name: "weather".to_string(),
+            description: "Météo d'une ville".to_string(),
+            parameters: json!({ "type": "object" }),
+        }];
+        request.tool_choice = Some(ToolChoice::Tool("weather".to_string()));
+        let response = provider(&server).generate(request).await.unwrap();
+
+        assert_eq!(response.finish_reason, FinishReason::ToolUse);
+        assert_eq!(
+            response.tool_calls,
+            [weather_call("weather_0", "Paris"), weather_call("weather_1", "Lyon")]
+        );
+
+        let body = sent_body(&server).await;
+        assert_eq!(body["tools"][0]["functionDeclarations"][0]["name"], "weather");
+        assert_eq!(body["toolConfig"]["functionCallingConfig"]["mode"], "ANY");
+        assert_eq!(
+            body["toolConfig"]["functionCallingConfig"]["allowedFunctionNames"],
+            json!(["weather"])
+        );
+    }
+
+    #[tokio::test]
+    async fn consecutive_tool_results_share_one_turn() {
+        let server = MockServer::start().await;
+        mount(
+            &server,
+            json!({
+                "candidates": [{
+                    "content": { "role": "model", "parts": [{ "text": "Il fait beau." }] },
+                    "finishReason": "STOP"
+                }]
+            }),
+        )
+        .await;
+
+        let calls = vec![weather_call("weather_0", "Paris"), weather_call("weather_1", "Lyon")];
+        let request = request(vec![
+            message(Role::User, "Quel temps ?"),
+            LLMMessage {
+                tool_calls: calls,
+                ..message(Role::Assistant, "")
+            },
+            tool_result("weather_0", r#"{"temp": 21}"#),
+            tool_result("weather_1", "nuageux"),
+        ]);
+        provider(&server).generate(request).await.unwrap();
+
+        let body = sent_body(&server).await;
+        let contents = body["contents"].as_array().unwrap();
+        assert_eq!(contents.len(), 3);
+        assert_eq!(contents[1]["parts"].as_array().unwrap().len(), 2);
+        assert_eq!(
+            contents[2],
+            json!({
+                "role": "user",
+                "parts": [
+                    { "functionResponse": { "name": "weather", "response": { "temp": 21 } } },
+                    {
+                        "functionResponse": {
+                            "name": "weather",
+                            "response": { "content": "nuageux" }
+                        }
+                    }
+                ]
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn tool_result_without_call_is_rejected() {
+        let server = MockServer::start().await;
+        let request = request(vec![
+            message(Role::User, "Quel temps ?"),
+            tool_result("inconnu_0", "nuageux"),
+        ]);
+
+        let error = provider(&server).generate(request).await.unwrap_err();
+        assert!(matches!(error, LLMError::InvalidConfig(_)));
+        assert!(server.received_requests().await.unwrap().is_empty());
+    }
+
+    #[test]
+    fn images_are_inline_and_files_need_a_mime_type() {
+        let image = Part::try_from(ContentPart::image_bytes("image/png", b"png")).unwrap();
+        assert_eq!(
+            serde_json::to_value(image).unwrap(),
+            json!({ "inlineData": { "mimeType": "image/png", "data": "cG5n" } })
+        );
+
+        let file = Part::try_from(ContentPart::File {
+            uri: "gs://bucket/spec.pdf".to_string(),
+            media_type: Some("application/pdf".to_string()),
+        })
+        .unwrap();
+        assert_eq!(
+            serde_json::to_value(file).unwrap(),
+            json!({
+                "fileData": { "mimeType": "application/pdf", "fileUri": "gs://bucket/spec.pdf" }
+            })
+        );
+
+        let untyped = ContentPart::File {
+            uri: "gs://bucket/spec.pdf".to_string(),
+            media_type: None,
+        };
+        assert!(matches!(Part::try_from(untyped), Err(LLMError::InvalidConfig(_))));
+    }
+}
@@ -0,0 +1,392 @@
+// Provider llama.cpp (serveur HTTP natif `llama-server`)
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use tokio::sync::OnceCell;
+
+use super::openai_compat::{chat_stream, ChatRequest, ChatResponse};
+use super::{configured_api_key, count_tokens, ensure_no_fim, resolve_parameters, HttpTransport};
+use crate::llm::{LLMError, LLMProvider, LLMProviderConfig, LLMRequest, LLMResponse, LLMStream};
+
+const DEFAULT_BASE_URL: &str = "http://localhost:8080";
+
+/// Marge approximative de tokens ajoutée par le template de chat pour chaque message
+const TEMPLATE_TOKENS_PER_MESSAGE: u32 = 4;
+
+
+/// Informations exposées par le serveur via `/props`
+#[derive(Debug, Clone)]
+pub struct ServerProps {
+    /// Taille du contexte d'un slot (`n_ctx`)
+    pub context_size: u32,
+    /// Nombre de slots de génération parallèles
+    pub total_slots: u32,
+    /// Chemin du modèle chargé par le serveur
+    pub model_path: Option<String>,
+}
+
+
+/// Provider pour un serveur llama.cpp.
+///
+/// La génération passe par l'endpoint compatible OpenAI (`/v1/chat/completions`)
+/// afin que le serveur applique le template de chat du modèle. Avant chaque génération,
+/// le prompt est compté avec le tokenizer natif (`/tokenize`) et refusé s'il ne tient
+/// pas dans le contexte d'un slot.
+pub struct LlamaCppProvider {
+    config: LLMProviderConfig,
+    transport: HttpTransport,
+    props: OnceCell<Option<ServerProps>>,
+}
+
+impl LlamaCppProvider {
+    pub fn new(config: LLMProviderConfig) -> Result<Self, LLMError> {
+        let mut default_headers = Vec::new();
+        if let Some(api_key) = configured_api_key(&config)? {
+            default_headers.push(("authorization", format!("Bearer {}", api_key)));
+        }
+
+        let transport = HttpTransport::new(&config, DEFAULT_BASE_URL, default_headers)?;
+
+        Ok(LlamaCppProvider {
+            config,
+            transport,
+            props: OnceCell::new(),
+        })
+    }
+
+    /// Tokenise un texte avec le tokenizer du modèle chargé par le serveur
+    pub async fn tokenize(&self, text: &str) -> Result<Vec<u32>, LLMError> {
+        let body = TokenizeRequest {
+            content: text,
+            add_special: false,
+        };
+        let response = self.transport.post_json("/tokenize", &body, false).await?;
+
+        let parsed: TokenizeResponse = response
+            .json()
+            .await
+            .map_err(|e| LLMError::ParseError(format!("Réponse /tokenize invalide: {}", e)))?;
+
+        Ok(parsed.tokens)
+    }
+
+    /// Récupère (une seule fois) la taille de contexte et les slots du serveur.
+    ///
+    /// `None` si le serveur ne les expose pas (pas de `/props`, réponse illisible ou sans
+    /// `n_ctx`) : ce résultat est aussi conservé, pour ne pas interroger `/props` à
+    /// chaque requête. Seules les erreurs réseau sont retentées à l'appel suivant.
+    pub async fn server_props(&self) -> Result<Option<&ServerProps>, LLMError> {
+        let props = self
+            .props
+            .get_or_try_init(|| async {
+                let response = match self.transport.get("/props").await {
+                    Ok(response) => response,
+                    Err(LLMError::APIError { status: 404, .. }) => return Ok(None),
+                    Err(error) => return Err(error),
+                };
+                let parsed: PropsResponse = match response.json().await {
+                    Ok(parsed) => parsed,
+                    Err(e) => {
+                        tracing::debug!("Réponse /props illisible, contexte non vérifié: {}", e);
+                        return Ok(None);
+                    }
+                };
+
+                let context_size = parsed
+                    .default_generation_settings
+                    .and_then(|settings| settings.n_ctx)
+                    .or(parsed.n_ctx);
+                if context_size.is_none() {
+                    tracing::debug!("n_ctx absent de /props, contexte non vérifié");
+                }
+
+                Ok::<_, LLMError>(context_size.map(|context_size| ServerProps {
+                    context_size,
+                    total_slots: parsed.total_slots.unwrap_or(1),
+                    model_path: parsed.model_path,
+                }))
+            })
+            .await?;
+        Ok(props.as_ref())
+    }
+
+    /// Vérifie avant envoi que le prompt et `max_tokens` tiennent dans le contexte d'un slot.
+    ///
+    /// Les messages sont tokenisés en un seul appel. Retourne le nombre de tokens du
+    /// prompt (`None` si le serveur n'expose pas sa taille de contexte), ou
+    /// `LLMError::TokenLimitExceeded`.
+    pub async fn check_context(&self, request: &LLMRequest) -> Result<Option<u32>, LLMError> {
+        let Some(props) = self.server_props().await? else {
+            return Ok(None);
+        };
+        let parameters = resolve_parameters(&self.config, request);
+
+        let text = request
+            .messages
+            .iter()
+            .map(|message| message.content.text())
+            .collect::<Vec<_>>()
+            .join("\n");
+        let template_tokens = request.messages.len() as u32 * TEMPLATE_TOKENS_PER_MESSAGE;
+        let prompt_tokens = self.count_tokens_exact(&text).await? + template_tokens;
+
+        if prompt_tokens + parameters.max_tokens > props.context_size {
+            return Err(LLMError::TokenLimitExceeded);
+        }
+        Ok(Some(prompt_tokens))
+    }
+
+    /// `check_context` avant génération ; un serveur sans `/tokenize` (404) n'est pas
+    /// vérifié
+    async fn ensure_fits(&self, request: &LLMRequest) -> Result<(), LLMError> {
+        match self.check_context(request).await {
+            Ok(_) | Err(LLMError::APIError { status: 404, .. }) => Ok(()),
+            Err(error) => Err(error),
+        }
+    }
+
+    fn build_body(&self, request: &LLMRequest, stream: bool) -> Result<ChatRequest, LLMError> {
+        ChatRequest::new(
+            Some(self.config.model_name.clone()),
+            &request.messages,
+            resolve_parameters(&self.config, request),
+            stream,
+        )
+        .map(|body| body.with_tools(&request.tools, request.tool_choice.as_ref()))
+    }
+}
+
+#[async_trait]
+impl LLMProvider for LlamaCppProvider {
+    async fn generate(&self, request: LLMRequest) -> Result<LLMResponse, LLMError> {
+        ensure_no_fim(&request, self.provider_name())?;
+        self.ensure_fits(&request).await?;
+        let body = self.build_body(&request, false)?;
+        let response = self.transport.post_json("/v1/chat/completions", &body, false).await?;
+
+        let parsed: ChatResponse = response
+            .json()
+            .await
+            .map_err(|e| LLMError::ParseError(format!("Réponse llama.cpp invalide: {}", e)))?;
+
+        parsed.into_llm_response(&self.config.model_name)
+    }
+
+    async fn generate_stream(&self, request: LLMRequest) -> Result<LLMStream, LLMError> {
+        ensure_no_fim(&request, self.provider_name())?;
+        self.ensure_fits(&request).await?;
+        let body = self.build_body(&request, true)?;
+        let response = self.transport.post_json("/v1/chat/completions", &body, true).await?;
+
+        Ok(chat_stream(response))
+    }
+
+    /// Estimation locale, sans appel réseau (voir `count_tokens_exact`)
+    fn count_tokens(&self, text: &str) -> Result<u32, LLMError> {
+        Ok(count_tokens(&self.config, text))
+    }
+
+    /// Compte exact par le tokenizer du modèle chargé (`/tokenize`)
+    async fn count_tokens_exact(&self, text: &str) -> Result<u32, LLMError> {
+        Ok(self.tokenize(text).await?.len() as u32)
+    }
+
+    fn provider_name(&self) -> &str {
+        "llamacpp"
+    }
+
+    fn model_name(&self) -> &str {
+        &self.config.model_name
+    }
+
+    /// Vérifie que le serveur a fini de charger le modèle (`/health` répond 503 sinon)
+    async fn health_check(&self) -> Result<(), LLMError> {
+        self.transport.get("/health").await?;
+        Ok(())
+    }
+}
+
+
+// Format "wire" des endpoints natifs de llama.cpp
+
+#[derive(Serialize)]
+struct TokenizeRequest<'a> {
+    content: &'a str,
+    add_special: bool,
+}
+
+#[derive(Deserialize)]
+struct TokenizeResponse {
+    tokens: Vec<u32>,
+}
+
+#[derive(Deserialize)]
+struct PropsResponse {
+    #[serde(default)]
+    default_generation_settings: Option<GenerationSettings>,
+    #[serde(default)]
+    n_ctx: Option<u32>,
+    #[serde(default)]
+    total_slots: Option<u32>,
+    #[serde(default)]
+    model_path: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct GenerationSettings {
+    #[serde(default)]
+    n_ctx: Option<u32>,
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::retry::RetryProvider;
+    use crate::llm::test_support::{config, message, request, user_request};
+    use crate::llm::{LLMProviderType, Role};
+    use serde_json::json;
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn provider(server: &MockServer) -> LlamaCppProvider {
+        let mut config = config(LLMProviderType::LlamaCpp, "qwen2.5-coder", &server.uri());
+        config.parameters.max_tokens = 100;
+        LlamaCppProvider::new(config).unwrap()
+    }
+
+    async fn mount_server(server: &MockServer, n_ctx: u32, tokens: usize) {
+        Mock::given(method("GET"))
+            .and(path("/props"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "default_generation_settings": { "n_ctx": n_ctx },
+                "total_slots": 1
+            })))
+            .mount(server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/tokenize"))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_json(json!({ "tokens": vec![1; tokens] })),
+            )
+            .mount(server)
+            .await;
+    }
+
+    fn completion() -> ResponseTemplate {
+        ResponseTemplate::new(200).set_body_json(json!({
+            "choices": [{ "message": { "content": "ok" }, "finish_reason": "stop" }]
+        }))
+    }
+
+    async fn calls_to(server: &MockServer, route: &str) -> usize {
+        let requests = server.received_requests().await.unwrap();
+        requests.iter().filter(|request| request.url.path() == route).count()
+    }
+
+    #[tokio::test]
+    async fn generate_checks_context_with_one_tokenize_call() {
+        let server = MockServer::start().await;
+        mount_server(&server, 4096, 50).await;
+        Mock::given(method("POST"))
+            .and(path("/v1/chat/completions"))
+            .respond_with(completion())
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let request = request(vec![
+            message(Role::System, "Tu es un assistant."),
+            message(Role::User, "Bonjour"),
+            message(Role::Assistant, "Salut"),
+            message(Role::User, "Explique ce code"),
+        ]);
+        let response = provider(&server).generate(request).await.unwrap();
+        assert_eq!(response.content, "ok");
+        assert_eq!(calls_to(&server, "/tokenize").await, 1);
+    }
+
+    #[tokio::test]
+    async fn oversized_prompt_is_refused_before_dispatch() {
+        let server = MockServer::start().await;
+        mount_server(&server, 512, 500).await;
+        Mock::given(method("POST"))
+            .and(path("/v1/chat/completions"))
+            .respond_with(completion())
+            .expect(0)
+            .mount(&server)
+            .await;
+
+        let provider = provider(&server);
+        let result = provider.generate(user_request("long")).await;
+        assert!(matches!(result, Err(LLMError::TokenLimitExceeded)));
+        let result = provider.generate_stream(user_request("long")).await;
+        assert!(matches!(result, Err(LLMError::TokenLimitExceeded)));
+    }
+
+    #[tokio::test]
+    async fn servers_without_props_are_not_checked() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/props"))
+            .respond_with(ResponseTemplate::new(404))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/v1/chat/completions"))
+            .respond_with(completion())
+            .mount(&server)
+            .await;
+
+        let provider = provider(&server);
+        for _ in 0..2 {
+            let response = provider.generate(user_request("Salut")).await.unwrap();
+            assert_eq!(response.content, "ok");
+        }
+        assert_eq!(calls_to(&server, "/tokenize").await, 0);
+    }
+
+    #[tokio::test]
+    async fn props_without_context_size_do_not_block_generation() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/props"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "total_slots": 4 })))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/v1/chat/completions"))
+            .respond_with(completion())
+            .mount(&server)
+            .await;
+
+        let provider = provider(&server);
+        assert!(provider.server_props().await.unwrap().is_none());
+        let response = provider.generate(user_request("Salut")).await.unwrap();
+        assert_eq!(response.content, "ok");
+        let stream = provider.generate_stream(user_request("Salut")).await;
+        assert!(stream.is_ok());
+        assert_eq!(calls_to(&server, "/tokenize").await, 0);
+    }
+
+    #[tokio::test]
+    async fn exact_count_uses_the_server_tokenizer() {
+        let server = MockServer::start().await;
+        mount_server(&server, 4096, 7).await;
+
+        // Tel que le gestionnaire le détient : derrière `RetryProvider`, en `dyn LLMProvider`
+        let config = config(LLMProviderType::LlamaCpp, "qwen2.5-coder", &server.uri());
+        let provider: Box<dyn LLMProvider> = Box::new(RetryProvider::from_config(
+            Box::new(LlamaCppProvider::new(config.clone()).unwrap()),
+            &config,
+        ));
+        assert_eq!(provider.count_tokens_exact("fn main() {}").await.unwrap(), 7);
+        assert_eq!(calls_to(&server, "/tokenize").await, 1);
+
+        // L'estimation synchrone reste locale
+        assert!(provider.count_tokens("fn main() {}").unwrap() > 0);
+        assert_eq!(calls_to(&server, "/tokenize").await, 1);
+    }
+}
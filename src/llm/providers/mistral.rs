@@ -0,0 +1,226 @@
+// Provider Mistral (chat et complétions fill-in-the-middle)
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+
+use super::openai_compat::{chat_stream, ChatRequest, ChatResponse};
+use super::{api_key, count_tokens, resolve_parameters, HttpTransport};
+use crate::llm::{
+    FillInTheMiddle, LLMError, LLMProvider, LLMProviderConfig, LLMRequest, LLMResponse, LLMStream,
+    ModelParameters,
+};
+
+const DEFAULT_BASE_URL: &str = "https://api.mistral.ai/v1";
+
+
+/// Provider pour l'API Mistral.
+///
+/// Une requête portant `fim` est envoyée à `/fim/completions` (modèles Codestral),
+/// les autres à `/chat/completions`.
+pub struct MistralProvider {
+    config: LLMProviderConfig,
+    transport: HttpTransport,
+}
+
+impl MistralProvider {
+    pub fn new(config: LLMProviderConfig) -> Result<Self, LLMError> {
+        let default_headers = match api_key(&config, "Mistral")? {
+            Some(api_key) => vec![("authorization", format!("Bearer {}", api_key))],
+            None => Vec::new(),
+        };
+
+        let transport = HttpTransport::new(&config, DEFAULT_BASE_URL, default_headers)?;
+
+        Ok(MistralProvider { config, transport })
+    }
+
+    /// Envoie la requête sur l'endpoint adapté (chat ou FIM)
+    async fn send(&self, request: &LLMRequest, stream: bool) -> Result<reqwest::Response, LLMError> {
+        let parameters = resolve_parameters(&self.config, request);
+
+        match &request.fim {
+            Some(fim) => {
+                let body = FimRequest::new(&self.config.model_name, fim, parameters, stream);
+                self.transport.post_json("/fim/completions", &body, stream).await
+            }
+            None => {
+                let mut body = ChatRequest::new(
+                    Some(self.config.model_name.clone()),
+                    &request.messages,
+                    parameters,
+                    stream,
+                )?
+                .with_tools(&request.tools, request.tool_choice.as_ref());
+                // Mistral renvoie l'usage dans le dernier chunk sans `stream_options`
+                body.stream_options = None;
+                self.transport.post_json("/chat/completions", &body, stream).await
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl LLMProvider for MistralProvider {
+    async fn generate(&self, request: LLMRequest) -> Result<LLMResponse, LLMError> {
+        let response = self.send(&request, false).await?;
+
+        let parsed: ChatResponse = response
+            .json()
+            .await
+            .map_err(|e| LLMError::ParseError(format!("Réponse Mistral invalide: {}", e)))?;
+
+        parsed.into_llm_response(&self.config.model_name)
+    }
+
+    async fn generate_stream(&self, request: LLMRequest) -> Result<LLMStream, LLMError> {
+        let response = self.send(&request, true).await?;
+
+        Ok(chat_stream(response))
+    }
+
+    fn count_tokens(&self, text: &str) -> Result<u32, LLMError> {
+        Ok(count_tokens(&self.config, text))
+    }
+
+    fn provider_name(&self) -> &str {
+        "mistral"
+    }
+
+    fn model_name(&self) -> &str {
+        &self.config.model_name
+    }
+
+    async fn health_check(&self) -> Result<(), LLMError> {
+        let response = self.transport.get("/models").await?;
+        let models: ModelList = response
+            .json()
+            .await
+            .map_err(|e| LLMError::ParseError(format!("Liste des modèles invalide: {}", e)))?;
+
+        if models.data.iter().any(|model| model.id == self.config.model_name) {
+            Ok(())
+        } else {
+            Err(LLMError::ModelNotFound(self.config.model_name.clone()))
+        }
+    }
+}
+
+
+// Format "wire" de l'endpoint FIM
+
+/// Corps d'une requête `/fim/completions` (les pénalités ne sont pas supportées)
+#[derive(Serialize)]
+struct FimRequest {
+    model: String,
+    prompt: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    suffix: Option<String>,
+    temperature: f32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_p: Option<f32>,
+    max_tokens: u32,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    stop: Vec<String>,
+    stream: bool,
+}
+
+impl FimRequest {
+    fn new(model: &str, fim: &FillInTheMiddle, parameters: ModelParameters, stream: bool) -> Self {
+        FimRequest {
+            model: model.to_string(),
+            prompt: fim.prefix.clone(),
+            suffix: fim.suffix.clone(),
+            temperature: parameters.temperature,
+            top_p: parameters.top_p,
+            max_tokens: parameters.max_tokens,
+            stop: parameters.stop_sequences,
+            stream,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct ModelList {
+    #[serde(default)]
+    data: Vec<ModelEntry>,
+}
+
+#[derive(Deserialize)]
+struct ModelEntry {
+    id: String,
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::test_support::{config, request, user_request};
+    use crate::llm::LLMProviderType;
+    use serde_json::{json, Value};
+    use wiremock::matchers::{header, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn provider(server: &MockServer) -> MistralProvider {
+        MistralProvider::new(config(LLMProviderType::Mistral, "codestral-latest", &server.uri()))
+            .unwrap()
+    }
+
+    fn completion(content: &str) -> ResponseTemplate {
+        ResponseTemplate::new(200).set_body_json(json!({
+            "id": "cmpl-1",
+            "model": "codestral-latest",
+            "choices": [{ "message": { "content": content }, "finish_reason": "stop" }],
+            "usage": { "prompt_tokens": 6, "completion_tokens": 3, "total_tokens": 9 }
+        }))
+    }
+
+    async fn sent_body(server: &MockServer) -> Value {
+        server.received_requests().await.unwrap()[0].body_json().unwrap()
+    }
+
+    #[tokio::test]
+    async fn fim_requests_use_the_fim_endpoint() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/fim/completions"))
+            .and(header("authorization", "Bearer test-key"))
+            .respond_with(completion("a + b"))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let request = LLMRequest {
+            fim: Some(FillInTheMiddle {
+                prefix: "fn add(a: i32, b: i32) -> i32 {\n    ".to_string(),
+                suffix: Some("\n}".to_string()),
+            }),
+            ..request(Vec::new())
+        };
+        let response = provider(&server).generate(request).await.unwrap();
+        assert_eq!(response.content, "a + b");
+        assert_eq!(response.usage.total_tokens, 9);
+
+        let body = sent_body(&server).await;
+        assert_eq!(body["prompt"], "fn add(a: i32, b: i32) -> i32 {\n    ");
+        assert_eq!(body["suffix"], "\n}");
+        assert!(body.get("messages").is_none());
+    }
+
+    #[tokio::test]
+    async fn chat_requests_omit_stream_options() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/chat/completions"))
+            .respond_with(completion("Bonjour"))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let response = provider(&server).generate(user_request("Salut")).await.unwrap();
+        assert_eq!(response.content, "Bonjour");
+
+        let body = sent_body(&server).await;
+        assert_eq!(body["model"], "codestral-latest");
+        assert!(body.get("stream_options").is_none());
+    }
+}
@@ -0,0 +1,377 @@
+// Implémentations des providers LLM et utilitaires HTTP partagés
+
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::time::Duration;
+
+use super::secrets::is_sensitive_header;
+use super::tokenizer::tokenizer_for;
+use super::{
+    LLMError, LLMProvider, LLMProviderConfig, LLMProviderType, LLMRequest, MessageContent,
+    ModelParameters, Role, TokenUsage,
+};
+#[cfg(any(feature = "gemini", feature = "ollama"))]
+use super::LLMMessage;
+
+pub mod custom;
+#[cfg(feature = "claude")]
+pub mod claude;
+#[cfg(feature = "openai")]
+pub mod openai;
+#[cfg(feature = "gemini")]
+pub mod gemini;
+#[cfg(feature = "AzureOpenAI")]
+pub mod azure;
+#[cfg(feature = "mistral")]
+pub mod mistral;
+#[cfg(any(feature = "openai", feature = "AzureOpenAI", feature = "mistral", feature = "llamacpp"))]
+pub(crate) mod openai_compat;
+#[cfg(feature = "ollama")]
+pub mod ollama;
+#[cfg(feature = "llamacpp")]
+pub mod llamacpp;
+
+
+/// Construit le provider correspondant au type déclaré dans la configuration
+#[allow(unreachable_patterns)]
+pub fn create_provider(config: LLMProviderConfig) -> Result<Box<dyn LLMProvider>, LLMError> {
+    match config.provider_type {
+        #[cfg(feature = "claude")]
+        LLMProviderType::Claude => Ok(Box::new(claude::ClaudeProvider::new(config)?)),
+        #[cfg(feature = "openai")]
+        LLMProviderType::OpenAI => Ok(Box::new(openai::OpenAIProvider::new(config)?)),
+        #[cfg(feature = "gemini")]
+        LLMProviderType::Gemini => Ok(Box::new(gemini::GeminiProvider::new(config)?)),
+        #[cfg(feature = "ollama")]
+        LLMProviderType::Ollama => Ok(Box::new(ollama::OllamaProvider::new(config)?)),
+        #[cfg(feature = "llamacpp")]
+        LLMProviderType::LlamaCpp => Ok(Box::new(llamacpp::LlamaCppProvider::new(config)?)),
+        #[cfg(feature = "mistral")]
+        LLMProviderType::Mistral => Ok(Box::new(mistral::MistralProvider::new(config)?)),
+        #[cfg(feature = "AzureOpenAI")]
+        LLMProviderType::AzureOpenAI => Ok(Box::new(azure::AzureOpenAIProvider::new(config)?)),
+        LLMProviderType::Custom => Ok(Box::new(custom::CustomProvider::new(config)?)),
+        other => Err(LLMError::InvalidConfig(format!(
+            "Le provider {:?} n'est pas disponible dans cette compilation (feature désactivée)",
+            other
+        ))),
+    }
+}
+
+
+/// Client HTTP partagé par les providers : URL de base, headers et timeout
+pub(crate) struct HttpTransport {
+    client: reqwest::Client,
+    base_url: String,
+    headers: HeaderMap,
+    timeout: Option<Duration>,
+}
+
+impl HttpTransport {
+    /// Crée le transport à partir de la configuration.
+    ///
+    /// Les `default_headers` du provider sont posés en premier, puis écrasés
+    /// par les headers déclarés dans `LLMProviderConfig.headers`.
+    pub(crate) fn new(
+        config: &LLMProviderConfig,
+        default_base_url: &str,
+        default_headers: Vec<(&str, String)>,
+    ) -> Result<Self, LLMError> {
+        let timeout = (config.timeout_seconds > 0).then(|| Duration::from_secs(config.timeout_seconds));
+
+        let mut builder = reqwest::Client::builder();
+        if let Some(timeout) = timeout {
+            builder = builder.connect_timeout(timeout);
+        }
+        let client = builder
+            .build()
+            .map_err(|e| LLMError::InvalidConfig(format!("Impossible de créer le client HTTP: {}", e)))?;
+
+        let mut headers = HeaderMap::new();
+        for (name, value) in default_headers {
+            insert_header(&mut headers, name, &value)?;
+        }
+        for (name, value) in &config.headers {
+            insert_header(&mut headers, name, value)?;
+        }
+
+        let base_url = config
+            .base_url
+            .clone()
+            .filter(|url| !url.trim().is_empty())
+            .unwrap_or_else(|| default_base_url.to_string());
+
+        Ok(HttpTransport {
+            client,
+            base_url: base_url.trim_end_matches('/').to_string(),
+            headers,
+            timeout,
+        })
+    }
+
+    /// URL complète pour un chemin relatif à l'URL de base
+    pub(crate) fn url(&self, path: &str) -> String {
+        format!("{}/{}", self.base_url, path.trim_start_matches('/'))
+    }
+
+    /// Envoie une requête POST JSON et vérifie le statut de la réponse.
+    ///
+    /// Le timeout global n'est pas appliqué en streaming : seule la connexion est bornée.
+    pub(crate) async fn post_json<T: Serialize + ?Sized>(
+        &self,
+        path: &str,
+        body: &T,
+        streaming: bool,
+    ) -> Result<reqwest::Response, LLMError> {
+        let mut request = self
+            .client
+            .post(self.url(path))
+            .headers(self.headers.clone())
+            .json(body);
+        if let (Some(timeout), false) = (self.timeout, streaming) {
+            request = request.timeout(timeout);
+        }
+
+        let response = request.send().await.map_err(map_reqwest_error)?;
+        check_status(response).await
+    }
+
+    /// Envoie une requête GET et vérifie le statut de la réponse
+    pub(crate) async fn get(&self, path: &str) -> Result<reqwest::Response, LLMError> {
+        let mut request = self.client.get(self.url(path)).headers(self.headers.clone());
+        if let Some(timeout) = self.timeout {
+            request = request.timeout(timeout);
+        }
+
+        let response = request.send().await.map_err(map_reqwest_error)?;
+        check_status(response).await
+    }
+}
+
+fn insert_header(headers: &mut HeaderMap, name: &str, value: &str) -> Result<(), LLMError> {
+    let header_name = HeaderName::from_bytes(name.as_bytes())
+        .map_err(|_| LLMError::InvalidConfig(format!("Nom de header invalide: {}", name)))?;
+    let mut header_value = HeaderValue::from_str(value)
+        .map_err(|_| LLMError::InvalidConfig(format!("Valeur invalide pour le header {}", name)))?;
+
+    if is_sensitive_header(name) {
+        header_value.set_sensitive(true);
+    }
+
+    headers.insert(header_name, header_value);
+    Ok(())
+}
+
+
+/// Convertit une erreur reqwest en `LLMError`
+pub(crate) fn map_reqwest_error(err: reqwest::Error) -> LLMError {
+    if err.is_timeout() {
+        LLMError::Timeout
+    } else if err.is_decode() {
+        LLMError::ParseError(err.to_string())
+    } else {
+        LLMError::NetworkError(err.to_string())
+    }
+}
+
+/// Retourne la réponse si son statut est un succès, sinon l'erreur correspondante
+pub(crate) async fn check_status(response: reqwest::Response) -> Result<reqwest::Response, LLMError> {
+    if response.status().is_success() {
+        Ok(response)
+    } else {
+        Err(error_from_response(response).await)
+    }
+}
+
+/// Construit une `LLMError` à partir d'une réponse HTTP en erreur.
+///
+/// Le message est extrait du corps JSON (`{"error": {"message": ...}}`,
+/// `{"error": "..."}` ou `{"message": ...}`) lorsque c'est possible.
+pub(crate) async fn error_from_response(response: reqwest::Response) -> LLMError {
+    let status = response.status();
+    let retry_after = parse_retry_after(response.headers());
+    let body = response.text().await.unwrap_or_default();
+
+    let message = extract_error_message(&body).unwrap_or_else(|| {
+        if body.trim().is_empty() {
+            status.canonical_reason().unwrap_or("erreur inconnue").to_string()
+        } else {
+            body.trim().to_string()
+        }
+    });
+
+    error_for_status(status.as_u16(), message, retry_after)
+}
+
+/// Erreur correspondant à un statut HTTP (aussi utilisée pour les erreurs signalées
+/// au milieu d'un flux, qui arrivent avec un statut 200)
+pub(crate) fn error_for_status(
+    status: u16,
+    message: String,
+    retry_after: Option<Duration>,
+) -> LLMError {
+    match status {
+        401 | 403 => LLMError::AuthenticationError(message),
+        status => LLMError::APIError {
+            status,
+            message,
+            retry_after,
+        },
+    }
+}
+
+/// Lit le délai demandé par le serveur : `retry-after-ms`, puis `Retry-After`
+/// (en secondes ou sous forme de date HTTP)
+fn parse_retry_after(headers: &HeaderMap) -> Option<Duration> {
+    let header = |name: &str| headers.get(name).and_then(|value| value.to_str().ok()).map(str::trim);
+
+    if let Some(millis) = header("retry-after-ms").and_then(|value| value.parse::<u64>().ok()) {
+        return Some(Duration::from_millis(millis));
+    }
+
+    let value = header("retry-after")?;
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+    let date = chrono::DateTime::parse_from_rfc2822(value).ok()?;
+    let delay = date.with_timezone(&chrono::Utc) - chrono::Utc::now();
+    Some(delay.to_std().unwrap_or(Duration::ZERO))
+}
+
+fn extract_error_message(body: &str) -> Option<String> {
+    let value: serde_json::Value = serde_json::from_str(body).ok()?;
+    let error = value.get("error").unwrap_or(&value);
+
+    match error {
+        serde_json::Value::String(message) => Some(message.clone()),
+        _ => error
+            .get("message")
+            .and_then(serde_json::Value::as_str)
+            .map(str::to_string),
+    }
+}
+
+
+/// Clé API du provider ; elle n'est facultative que pour un déploiement local
+/// (proxy ou serveur d'inférence compatible sans authentification)
+#[cfg(any(feature = "claude", feature = "gemini", feature = "mistral"))]
+pub(crate) fn api_key(
+    config: &LLMProviderConfig,
+    provider: &str,
+) -> Result<Option<String>, LLMError> {
+    use super::deployment::classify_base_url;
+    use super::DeploymentMode;
+
+    let local = match config.deployment {
+        DeploymentMode::Local => true,
+        DeploymentMode::Remote => false,
+        DeploymentMode::Auto => config
+            .base_url
+            .as_deref()
+            .and_then(classify_base_url)
+            .is_some_and(|mode| mode == DeploymentMode::Local),
+    };
+
+    match configured_api_key(config)? {
+        Some(api_key) => Ok(Some(api_key)),
+        None if local => Ok(None),
+        None => Err(LLMError::InvalidConfig(format!(
+            "Clé API manquante pour le provider {}",
+            provider
+        ))),
+    }
+}
+
+/// Clé API lue à sa source (variable, fichier, trousseau), `None` si absente ou vide
+pub(crate) fn configured_api_key(config: &LLMProviderConfig) -> Result<Option<String>, LLMError> {
+    match &config.api_key {
+        Some(source) => {
+            let secret = source.resolve()?;
+            Ok((!secret.is_empty()).then(|| secret.expose().to_string()))
+        }
+        None => Ok(None),
+    }
+}
+
+/// Paramètres effectifs d'une requête : ceux de la requête, sinon ceux de la configuration
+pub(crate) fn resolve_parameters(config: &LLMProviderConfig, request: &LLMRequest) -> ModelParameters {
+    request
+        .parameters
+        .clone()
+        .unwrap_or_else(|| config.parameters.clone())
+}
+
+/// Refuse les requêtes "fill-in-the-middle" pour les providers qui ne les supportent pas
+pub(crate) fn ensure_no_fim(request: &LLMRequest, provider: &str) -> Result<(), LLMError> {
+    if request.fim.is_some() {
+        return Err(LLMError::InvalidConfig(format!(
+            "Le provider {} ne supporte pas les complétions fill-in-the-middle",
+            provider
+        )));
+    }
+    Ok(())
+}
+
+/// Refuse les requêtes utilisant des outils pour les providers qui ne les supportent pas
+pub(crate) fn ensure_no_tools(request: &LLMRequest, provider: &str) -> Result<(), LLMError> {
+    let uses_tools = !request.tools.is_empty()
+        || request
+            .messages
+            .iter()
+            .any(|m| m.role == Role::Tool || !m.tool_calls.is_empty());
+
+    if uses_tools {
+        return Err(LLMError::InvalidConfig(format!(
+            "Le provider {} ne supporte pas les appels d'outils",
+            provider
+        )));
+    }
+    Ok(())
+}
+
+/// Texte d'un message pour les providers (ou rôles) qui ne supportent pas les images
+/// et les fichiers
+pub(crate) fn text_only(content: &MessageContent, provider: &str) -> Result<String, LLMError> {
+    if let Some(part) = content.first_non_text() {
+        return Err(LLMError::InvalidConfig(format!(
+            "Le provider {} ne supporte pas ce contenu ({}) : seul le texte est accepté",
+            provider,
+            part.kind()
+        )));
+    }
+    Ok(content.text())
+}
+
+/// Refuse les requêtes contenant des images ou des fichiers
+pub(crate) fn ensure_text_messages(request: &LLMRequest, provider: &str) -> Result<(), LLMError> {
+    for message in &request.messages {
+        text_only(&message.content, provider)?;
+    }
+    Ok(())
+}
+
+/// Nom de l'outil correspondant à `tool_call_id`, retrouvé dans les appels de l'assistant
+/// (pour les API qui identifient un résultat par le nom de l'outil)
+#[cfg(any(feature = "gemini", feature = "ollama"))]
+pub(crate) fn tool_name_for<'a>(messages: &'a [LLMMessage], tool_call_id: &str) -> Option<&'a str> {
+    messages
+        .iter()
+        .flat_map(|m| &m.tool_calls)
+        .find(|call| call.id == tool_call_id)
+        .map(|call| call.name.as_str())
+}
+
+/// Représentation de l'usage des tokens dans les métadonnées d'un chunk final
+pub(crate) fn usage_metadata(usage: &TokenUsage) -> HashMap<String, String> {
+    let mut metadata = HashMap::new();
+    metadata.insert("prompt_tokens".to_string(), usage.prompt_tokens.to_string());
+    metadata.insert("completion_tokens".to_string(), usage.completion_tokens.to_string());
+    metadata.insert("total_tokens".to_string(), usage.total_tokens.to_string());
+    metadata
+}
+
+/// Nombre de tokens d'un texte pour le modèle configuré (voir `tokenizer::tokenizer_for`)
+pub(crate) fn count_tokens(config: &LLMProviderConfig, text: &str) -> u32 {
+    tokenizer_for(&config.provider_type, &config.model_name).count(text)
+}
@@ -0,0 +1,453 @@
+// Provider Ollama pour les modèles exécutés localement
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+
+use super::{count_tokens, ensure_no_fim, resolve_parameters, tool_name_for, HttpTransport};
+use crate::llm::streaming::{decode_response, StreamEvent, StreamFormat, StreamHandler, StreamUpdate};
+use crate::llm::{
+    ContentPart, FinishReason, LLMError, LLMMessage, LLMProvider, LLMProviderConfig, LLMRequest,
+    LLMResponse, LLMStream, ModelParameters, Role, TokenUsage, ToolCall, ToolDefinition,
+};
+
+const DEFAULT_BASE_URL: &str = "http://localhost:11434";
+
+
+/// Provider pour un serveur Ollama (`/api/chat`, `/api/tags`)
+pub struct OllamaProvider {
+    config: LLMProviderConfig,
+    transport: HttpTransport,
+}
+
+impl OllamaProvider {
+    pub fn new(config: LLMProviderConfig) -> Result<Self, LLMError> {
+        let transport = HttpTransport::new(&config, DEFAULT_BASE_URL, Vec::new())?;
+
+        Ok(OllamaProvider { config, transport })
+    }
+
+    /// Construit la requête `/api/chat` ; `tool_choice` n'a pas d'équivalent et est ignoré
+    fn build_body(&self, request: &LLMRequest, stream: bool) -> Result<ChatRequest, LLMError> {
+        Ok(ChatRequest {
+            model: self.config.model_name.clone(),
+            messages: request
+                .messages
+                .iter()
+                .map(|m| WireMessage::from_message(m, &request.messages))
+                .collect::<Result<_, _>>()?,
+            stream,
+            options: resolve_parameters(&self.config, request).into(),
+            tools: request.tools.iter().map(WireTool::from).collect(),
+        })
+    }
+
+    /// Indique si un modèle listé par `/api/tags` correspond au modèle configuré.
+    ///
+    /// Un nom sans tag (`codellama`) correspond au tag implicite `:latest`.
+    fn matches_model(&self, name: &str) -> bool {
+        let configured = &self.config.model_name;
+        name == configured
+            || (!configured.contains(':') && name == format!("{}:latest", configured))
+    }
+}
+
+#[async_trait]
+impl LLMProvider for OllamaProvider {
+    async fn generate(&self, request: LLMRequest) -> Result<LLMResponse, LLMError> {
+        ensure_no_fim(&request, self.provider_name())?;
+        let body = self.build_body(&request, false)?;
+        let response = self.transport.post_json("/api/chat", &body, false).await?;
+
+        let parsed: ChatResponse = response
+            .json()
+            .await
+            .map_err(|e| LLMError::ParseError(format!("Réponse Ollama invalide: {}", e)))?;
+
+        let mut metadata = HashMap::new();
+        if let Some(duration) = parsed.total_duration {
+            metadata.insert("total_duration_ns".to_string(), duration.to_string());
+        }
+
+        let tool_calls = parsed.tool_calls(0);
+        let finish_reason = match map_done_reason(parsed.done_reason.as_deref()) {
+            FinishReason::Stop if !tool_calls.is_empty() => FinishReason::ToolUse,
+            reason => reason,
+        };
+
+        Ok(LLMResponse {
+            content: parsed.message.as_ref().map(|m| m.content.clone()).unwrap_or_default(),
+            finish_reason,
+            usage: parsed.usage(),
+            model: parsed.model,
+            metadata: Some(metadata),
+            tool_calls,
+        })
+    }
+
+    async fn generate_stream(&self, request: LLMRequest) -> Result<LLMStream, LLMError> {
+        ensure_no_fim(&request, self.provider_name())?;
+        let body = self.build_body(&request, true)?;
+        let response = self.transport.post_json("/api/chat", &body, true).await?;
+
+        Ok(decode_response(response, StreamFormat::Ndjson, OllamaStreamHandler::default()))
+    }
+
+    fn count_tokens(&self, text: &str) -> Result<u32, LLMError> {
+        Ok(count_tokens(&self.config, text))
+    }
+
+    fn provider_name(&self) -> &str {
+        "ollama"
+    }
+
+    fn model_name(&self) -> &str {
+        &self.config.model_name
+    }
+
+    /// Vérifie que le serveur répond et que le modèle configuré a été téléchargé
+    async fn health_check(&self) -> Result<(), LLMError> {
+        let response = self.transport.get("/api/tags").await?;
+        let tags: TagsResponse = response
+            .json()
+            .await
+            .map_err(|e| LLMError::ParseError(format!("Liste des modèles Ollama invalide: {}", e)))?;
+
+        if tags.models.iter().any(|model| self.matches_model(&model.name)) {
+            Ok(())
+        } else {
+            Err(LLMError::ModelNotFound(format!(
+                "{} (exécuter `ollama pull {}`)",
+                self.config.model_name, self.config.model_name
+            )))
+        }
+    }
+}
+
+
+fn map_done_reason(reason: Option<&str>) -> FinishReason {
+    match reason {
+        Some("length") => FinishReason::Length,
+        _ => FinishReason::Stop,
+    }
+}
+
+/// Interprétation des lignes NDJSON de `/api/chat` ; la dernière (`done`) porte l'usage.
+/// Les appels d'outils arrivent complets dans une ligne intermédiaire.
+#[derive(Default)]
+struct OllamaStreamHandler {
+    /// Nombre d'appels d'outils déjà reçus (pour numéroter les identifiants)
+    tool_calls: usize,
+}
+
+impl StreamHandler for OllamaStreamHandler {
+    fn on_event(&mut self, event: &StreamEvent) -> Result<StreamUpdate, LLMError> {
+        let parsed: ChatResponse = serde_json::from_str(&event.data)
+            .map_err(|e| LLMError::ParseError(format!("Chunk Ollama invalide: {}", e)))?;
+
+        if let Some(error) = parsed.error {
+            return Err(LLMError::APIError {
+                status: 500,
+                message: error,
+                retry_after: None,
+            });
+        }
+
+        let mut update = StreamUpdate::delta(
+            parsed.message.as_ref().map(|m| m.content.clone()).unwrap_or_default(),
+        );
+        update.tool_calls = parsed.tool_calls(self.tool_calls);
+        self.tool_calls += update.tool_calls.len();
+        if parsed.done {
+            update.finish_reason = Some(match map_done_reason(parsed.done_reason.as_deref()) {
+                FinishReason::Stop if self.tool_calls > 0 => FinishReason::ToolUse,
+                reason => reason,
+            });
+            update.usage = Some(parsed.usage());
+            update.done = true;
+        }
+
+        Ok(update)
+    }
+}
+
+
+// Format "wire" de l'API Ollama
+
+#[derive(Serialize)]
+struct ChatRequest {
+    model: String,
+    messages: Vec<WireMessage>,
+    stream: bool,
+    options: WireOptions,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    tools: Vec<WireTool>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct WireMessage {
+    role: String,
+    #[serde(default)]
+    content: String,
+    /// Images encodées en base64 (modèles de vision)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    images: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    tool_calls: Vec<WireToolCall>,
+    /// Nom de l'outil dont le message `tool` porte le résultat
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    tool_name: Option<String>,
+}
+
+impl WireMessage {
+    /// `history` sert à retrouver le nom de l'outil d'un message `Role::Tool`.
+    /// Les images sont jointes au message ; les références de fichiers ne sont pas supportées.
+    fn from_message(message: &LLMMessage, history: &[LLMMessage]) -> Result<Self, LLMError> {
+        let mut images = Vec::new();
+        for part in message.content.parts() {
+            match part {
+                ContentPart::Text { .. } => {}
+                ContentPart::Image { data, .. } => images.push(data),
+                ContentPart::File { uri, .. } => {
+                    return Err(LLMError::InvalidConfig(format!(
+                        "Le provider ollama ne supporte pas les références de fichiers ({})",
+                        uri
+                    )))
+                }
+            }
+        }
+
+        let role = match message.role {
+            Role::System => "system",
+            Role::User => "user",
+            Role::Assistant => "assistant",
+            Role::Tool => "tool",
+        };
+
+        Ok(WireMessage {
+            role: role.to_string(),
+            content: message.content.text(),
+            images,
+            tool_calls: message
+                .tool_calls
+                .iter()
+                .map(|call| WireToolCall {
+                    function: WireCall {
+                        name: call.name.clone(),
+                        arguments: call.arguments.clone(),
+                    },
+                })
+                .collect(),
+            tool_name: message
+                .tool_call_id
+                .as_deref()
+                .and_then(|id| tool_name_for(history, id))
+                .map(str::to_string),
+        })
+    }
+}
+
+#[derive(Serialize)]
+struct WireTool {
+    #[serde(rename = "type")]
+    kind: &'static str,
+    function: WireFunction,
+}
+
+#[derive(Serialize)]
+struct WireFunction {
+    name: String,
+    description: String,
+    parameters: Value,
+}
+
+impl From<&ToolDefinition> for WireTool {
+    fn from(tool: &ToolDefinition) -> Self {
+        WireTool {
+            kind: "function",
+            function: WireFunction {
+                name: tool.name.clone(),
+                description: tool.description.clone(),
+                parameters: tool.parameters.clone(),
+            },
+        }
+    }
+}
+
+/// Appel d'outil ; contrairement à l'API OpenAI, les arguments sont un objet JSON
+#[derive(Serialize, Deserialize)]
+struct WireToolCall {
+    function: WireCall,
+}
+
+#[derive(Serialize, Deserialize)]
+struct WireCall {
+    name: String,
+    #[serde(default)]
+    arguments: Value,
+}
+
+#[derive(Serialize)]
+struct WireOptions {
+    temperature: f32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_p: Option<f32>,
+    num_predict: u32,
+    presence_penalty: f32,
+    frequency_penalty: f32,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    stop: Vec<String>,
+}
+
+impl From<ModelParameters> for WireOptions {
+    fn from(parameters: ModelParameters) -> Self {
+        WireOptions {
+            temperature: parameters.temperature,
+            top_p: parameters.top_p,
+            num_predict: parameters.max_tokens,
+            presence_penalty: parameters.presence_penalty,
+            frequency_penalty: parameters.frequency_penalty,
+            stop: parameters.stop_sequences,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct ChatResponse {
+    #[serde(default)]
+    model: String,
+    #[serde(default)]
+    message: Option<WireMessage>,
+    #[serde(default)]
+    done: bool,
+    #[serde(default)]
+    done_reason: Option<String>,
+    #[serde(default)]
+    prompt_eval_count: u32,
+    #[serde(default)]
+    eval_count: u32,
+    #[serde(default)]
+    total_duration: Option<u64>,
+    #[serde(default)]
+    error: Option<String>,
+}
+
+impl ChatResponse {
+    /// Appels d'outils du message. Ollama ne fournit pas d'identifiant : il est
+    /// construit à partir du nom et du rang de l'appel dans la réponse (`offset`).
+    fn tool_calls(&self, offset: usize) -> Vec<ToolCall> {
+        self.message
+            .iter()
+            .flat_map(|message| &message.tool_calls)
+            .enumerate()
+            .map(|(i, call)| ToolCall {
+                id: format!("{}_{}", call.function.name, offset + i),
+                name: call.function.name.clone(),
+                arguments: call.function.arguments.clone(),
+            })
+            .collect()
+    }
+
+    fn usage(&self) -> TokenUsage {
+        TokenUsage {
+            prompt_tokens: self.prompt_eval_count,
+            completion_tokens: self.eval_count,
+            total_tokens: self.prompt_eval_count + self.eval_count,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct TagsResponse {
+    #[serde(default)]
+    models: Vec<TagEntry>,
+}
+
+#[derive(Deserialize)]
+struct TagEntry {
+    name: String,
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::streaming::collect_response;
+    use crate::llm::test_support::{config, user_request};
+    use crate::llm::LLMProviderType;
+    use serde_json::json;
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn provider(server: &MockServer, model: &str) -> OllamaProvider {
+        OllamaProvider::new(config(LLMProviderType::Ollama, model, &server.uri())).unwrap()
+    }
+
+    #[tokio::test]
+    async fn generate_maps_eval_counts() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/api/chat"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "model": "llama3.1",
+                "message": { "role": "assistant", "content": "Bonjour" },
+                "done": true,
+                "done_reason": "length",
+                "prompt_eval_count": 11,
+                "eval_count": 4
+            })))
+            .mount(&server)
+            .await;
+
+        let response = provider(&server, "llama3.1").generate(user_request("Salut")).await.unwrap();
+        assert_eq!(response.content, "Bonjour");
+        assert_eq!(response.finish_reason, FinishReason::Length);
+        assert_eq!(response.usage.total_tokens, 15);
+
+        let body: Value = server.received_requests().await.unwrap()[0].body_json().unwrap();
+        assert_eq!(body["stream"], false);
+        assert_eq!(body["options"]["num_predict"], 4096);
+    }
+
+    #[tokio::test]
+    async fn stream_reads_ndjson_until_done() {
+        let server = MockServer::start().await;
+        let lines = [
+            json!({ "message": { "role": "assistant", "content": "Bon" }, "done": false }),
+            json!({ "message": { "role": "assistant", "content": "jour" }, "done": false }),
+            json!({ "done": true, "done_reason": "stop", "prompt_eval_count": 3, "eval_count": 2 }),
+        ];
+        let body: String = lines.iter().map(|line| format!("{}\n", line)).collect();
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(body, "application/x-ndjson"))
+            .mount(&server)
+            .await;
+
+        let provider = provider(&server, "llama3.1");
+        let stream = provider.generate_stream(user_request("Salut")).await.unwrap();
+        let response = collect_response(stream, "llama3.1").await.unwrap();
+        assert_eq!(response.content, "Bonjour");
+        assert_eq!(response.finish_reason, FinishReason::Stop);
+        assert_eq!(response.usage.total_tokens, 5);
+    }
+
+    #[tokio::test]
+    async fn health_check_requires_pulled_model() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/api/tags"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "models": [{ "name": "codellama:latest" }, { "name": "llama3.1:8b" }]
+            })))
+            .mount(&server)
+            .await;
+
+        assert!(provider(&server, "codellama").health_check().await.is_ok());
+        assert!(provider(&server, "llama3.1:8b").health_check().await.is_ok());
+        assert!(matches!(
+            provider(&server, "llama3.1").health_check().await,
+            Err(LLMError::ModelNotFound(_))
+        ));
+    }
+}
@@ -0,0 +1,243 @@
+// Provider OpenAI (API Chat Completions) et serveurs compatibles (vLLM, LM Studio...)
+
+use async_trait::async_trait;
+use serde::Deserialize;
+
+use super::openai_compat::{chat_stream, ChatRequest, ChatResponse};
+use super::{configured_api_key, count_tokens, ensure_no_fim, resolve_parameters, HttpTransport};
+use crate::llm::{LLMError, LLMProvider, LLMProviderConfig, LLMRequest, LLMResponse, LLMStream};
+
+const DEFAULT_BASE_URL: &str = "https://api.openai.com/v1";
+
+
+/// Provider pour l'API Chat Completions d'OpenAI.
+///
+/// `base_url` peut pointer vers n'importe quel serveur compatible ; la clé API
+/// n'est alors exigée que pour l'API officielle.
+pub struct OpenAIProvider {
+    config: LLMProviderConfig,
+    transport: HttpTransport,
+}
+
+impl OpenAIProvider {
+    pub fn new(config: LLMProviderConfig) -> Result<Self, LLMError> {
+        let api_key = configured_api_key(&config)?;
+        if api_key.is_none() && config.base_url.is_none() {
+            return Err(LLMError::InvalidConfig(
+                "Clé API manquante pour le provider OpenAI".to_string(),
+            ));
+        }
+
+        let mut default_headers = Vec::new();
+        if let Some(api_key) = api_key {
+            default_headers.push(("authorization", format!("Bearer {}", api_key)));
+        }
+
+        let transport = HttpTransport::new(&config, DEFAULT_BASE_URL, default_headers)?;
+
+        Ok(OpenAIProvider { config, transport })
+    }
+
+    fn build_body(&self, request: &LLMRequest, stream: bool) -> Result<ChatRequest, LLMError> {
+        let body = ChatRequest::new(
+            Some(self.config.model_name.clone()),
+            &request.messages,
+            resolve_parameters(&self.config, request),
+            stream,
+        )?
+        .with_tools(&request.tools, request.tool_choice.as_ref());
+
+        Ok(if is_reasoning_model(&self.config.model_name) {
+            body.for_reasoning_model()
+        } else {
+            body
+        })
+    }
+}
+
+#[async_trait]
+impl LLMProvider for OpenAIProvider {
+    async fn generate(&self, request: LLMRequest) -> Result<LLMResponse, LLMError> {
+        ensure_no_fim(&request, self.provider_name())?;
+        let body = self.build_body(&request, false)?;
+        let response = self.transport.post_json("/chat/completions", &body, false).await?;
+
+        let parsed: ChatResponse = response
+            .json()
+            .await
+            .map_err(|e| LLMError::ParseError(format!("Réponse OpenAI invalide: {}", e)))?;
+
+        parsed.into_llm_response(&self.config.model_name)
+    }
+
+    async fn generate_stream(&self, request: LLMRequest) -> Result<LLMStream, LLMError> {
+        ensure_no_fim(&request, self.provider_name())?;
+        let body = self.build_body(&request, true)?;
+        let response = self.transport.post_json("/chat/completions", &body, true).await?;
+
+        Ok(chat_stream(response))
+    }
+
+    fn count_tokens(&self, text: &str) -> Result<u32, LLMError> {
+        Ok(count_tokens(&self.config, text))
+    }
+
+    fn provider_name(&self) -> &str {
+        "openai"
+    }
+
+    fn model_name(&self) -> &str {
+        &self.config.model_name
+    }
+
+    async fn health_check(&self) -> Result<(), LLMError> {
+        let response = self.transport.get("/models").await?;
+        let models: ModelList = response
+            .json()
+            .await
+            .map_err(|e| LLMError::ParseError(format!("Liste des modèles invalide: {}", e)))?;
+
+        if models.data.iter().any(|model| model.id == self.config.model_name) {
+            Ok(())
+        } else {
+            Err(LLMError::ModelNotFound(self.config.model_name.clone()))
+        }
+    }
+}
+
+
+/// Modèles de raisonnement (o1, o3, o4-mini, gpt-5...) : `max_tokens` et les paramètres
+/// d'échantillonnage y sont refusés (`gpt-5-chat` fait exception)
+fn is_reasoning_model(model: &str) -> bool {
+    let model = model.rsplit('/').next().unwrap_or(model);
+    let o_series = model.strip_prefix('o').is_some_and(|rest| {
+        rest.starts_with(|c: char| c.is_ascii_digit())
+    });
+    o_series || (model.starts_with("gpt-5") && !model.starts_with("gpt-5-chat"))
+}
+
+
+#[derive(Deserialize)]
+struct ModelList {
+    #[serde(default)]
+    data: Vec<ModelEntry>,
+}
+
+#[derive(Deserialize)]
+struct ModelEntry {
+    id: String,
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::streaming::collect_response;
+    use crate::llm::test_support::{config, user_request};
+    use crate::llm::{FinishReason, LLMProviderType};
+    use serde_json::{json, Value};
+    use wiremock::matchers::{header, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn provider(server: &MockServer, model: &str) -> OpenAIProvider {
+        OpenAIProvider::new(config(LLMProviderType::OpenAI, model, &server.uri())).unwrap()
+    }
+
+    fn completion() -> Value {
+        json!({
+            "id": "chatcmpl-1",
+            "model": "gpt-4o-2024-08-06",
+            "choices": [{
+                "message": { "role": "assistant", "content": "Bonjour" },
+                "finish_reason": "length"
+            }],
+            "usage": { "prompt_tokens": 9, "completion_tokens": 3, "total_tokens": 12 }
+        })
+    }
+
+    async fn mount_completion(server: &MockServer) {
+        Mock::given(method("POST"))
+            .and(path("/chat/completions"))
+            .and(header("authorization", "Bearer test-key"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(completion()))
+            .mount(server)
+            .await;
+    }
+
+    async fn sent_body(server: &MockServer) -> Value {
+        server.received_requests().await.unwrap()[0].body_json().unwrap()
+    }
+
+    #[tokio::test]
+    async fn generate_against_overridden_base_url() {
+        let server = MockServer::start().await;
+        mount_completion(&server).await;
+
+        let response = provider(&server, "gpt-4o").generate(user_request("Salut")).await.unwrap();
+        assert_eq!(response.content, "Bonjour");
+        assert_eq!(response.finish_reason, FinishReason::Length);
+        assert_eq!(response.usage.total_tokens, 12);
+        assert_eq!(response.model, "gpt-4o-2024-08-06");
+
+        let body = sent_body(&server).await;
+        assert_eq!(body["model"], "gpt-4o");
+        assert_eq!(body["max_tokens"], 4096);
+        assert!(body.get("max_completion_tokens").is_none());
+        assert!(body.get("top_p").is_none());
+    }
+
+    #[tokio::test]
+    async fn reasoning_models_use_max_completion_tokens() {
+        let server = MockServer::start().await;
+        mount_completion(&server).await;
+
+        provider(&server, "o4-mini").generate(user_request("Salut")).await.unwrap();
+
+        let body = sent_body(&server).await;
+        assert_eq!(body["max_completion_tokens"], 4096);
+        assert!(body.get("max_tokens").is_none());
+        assert!(body.get("temperature").is_none());
+        assert!(body.get("presence_penalty").is_none());
+    }
+
+    #[tokio::test]
+    async fn stream_reports_usage_on_final_chunk() {
+        let server = MockServer::start().await;
+        let events = [
+            json!({ "choices": [{ "delta": { "content": "Bon" }, "finish_reason": null }] }),
+            json!({ "choices": [{ "delta": { "content": "jour" }, "finish_reason": "stop" }] }),
+            json!({
+                "choices": [],
+                "usage": { "prompt_tokens": 4, "completion_tokens": 2, "total_tokens": 6 }
+            }),
+        ];
+        let body: String = events
+            .iter()
+            .map(|event| format!("data: {}\n\n", event))
+            .chain(std::iter::once("data: [DONE]\n\n".to_string()))
+            .collect();
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(body, "text/event-stream"))
+            .mount(&server)
+            .await;
+
+        let provider = provider(&server, "gpt-4o");
+        let stream = provider.generate_stream(user_request("Salut")).await.unwrap();
+        let response = collect_response(stream, "gpt-4o").await.unwrap();
+
+        assert_eq!(response.content, "Bonjour");
+        assert_eq!(response.finish_reason, FinishReason::Stop);
+        assert_eq!(response.usage.total_tokens, 6);
+        assert_eq!(sent_body(&server).await["stream_options"]["include_usage"], true);
+    }
+
+    #[test]
+    fn reasoning_model_names() {
+        for model in ["o1", "o3-mini", "o4-mini-2025-04-16", "gpt-5", "gpt-5-mini"] {
+            assert!(is_reasoning_model(model), "{}", model);
+        }
+        for model in ["gpt-4o", "gpt-4.1-nano", "gpt-5-chat-latest", "omni-moderation"] {
+            assert!(!is_reasoning_model(model), "{}", model);
+        }
+    }
+}
@@ -0,0 +1,650 @@
+// Format "wire" de l'API Chat Completions, partagé par les providers compatibles OpenAI
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, HashMap};
+
+use crate::llm::streaming::{decode_response, StreamEvent, StreamFormat, StreamHandler, StreamUpdate};
+use crate::llm::{
+    ContentPart, FinishReason, LLMError, LLMMessage, LLMResponse, LLMStream, MessageContent,
+    ModelParameters, Role, TokenUsage, ToolCall, ToolChoice, ToolDefinition,
+};
+
+
+/// Corps d'une requête Chat Completions
+#[derive(Serialize)]
+pub(crate) struct ChatRequest {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    pub messages: Vec<ChatMessage>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub top_p: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_tokens: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_completion_tokens: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub presence_penalty: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub frequency_penalty: Option<f32>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub stop: Vec<String>,
+    pub stream: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stream_options: Option<StreamOptions>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub tools: Vec<WireTool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tool_choice: Option<Value>,
+}
+
+impl ChatRequest {
+    /// Construit la requête à partir des messages et de tous les `ModelParameters`.
+    ///
+    /// `model` peut être omis pour les API où le modèle est porté par l'URL.
+    pub(crate) fn new(
+        model: Option<String>,
+        messages: &[LLMMessage],
+        parameters: ModelParameters,
+        stream: bool,
+    ) -> Result<Self, LLMError> {
+        Ok(ChatRequest {
+            model,
+            messages: messages
+                .iter()
+                .map(ChatMessage::try_from)
+                .collect::<Result<_, _>>()?,
+            temperature: Some(parameters.temperature),
+            top_p: parameters.top_p,
+            max_tokens: Some(parameters.max_tokens),
+            max_completion_tokens: None,
+            presence_penalty: Some(parameters.presence_penalty),
+            frequency_penalty: Some(parameters.frequency_penalty),
+            stop: parameters.stop_sequences,
+            stream,
+            stream_options: stream.then_some(StreamOptions { include_usage: true }),
+            tools: Vec::new(),
+            tool_choice: None,
+        })
+    }
+
+    /// Adapte la requête à un modèle de raisonnement : la limite passe dans
+    /// `max_completion_tokens` et les paramètres d'échantillonnage, refusés par ces
+    /// modèles, sont retirés
+    #[cfg(feature = "openai")]
+    pub(crate) fn for_reasoning_model(mut self) -> Self {
+        self.max_completion_tokens = self.max_tokens.take();
+        self.temperature = None;
+        self.top_p = None;
+        self.presence_penalty = None;
+        self.frequency_penalty = None;
+        self
+    }
+
+    /// Déclare les outils de la requête et la contrainte `tool_choice`
+    pub(crate) fn with_tools(
+        mut self,
+        tools: &[ToolDefinition],
+        choice: Option<&ToolChoice>,
+    ) -> Self {
+        self.tools = tools.iter().map(WireTool::from).collect();
+        self.tool_choice = choice.map(|choice| match choice {
+            ToolChoice::Auto => json!("auto"),
+            ToolChoice::None => json!("none"),
+            ToolChoice::Required => json!("required"),
+            ToolChoice::Tool(name) => json!({ "type": "function", "function": { "name": name } }),
+        });
+        self
+    }
+}
+
+#[derive(Serialize)]
+pub(crate) struct StreamOptions {
+    pub include_usage: bool,
+}
+
+#[derive(Serialize)]
+pub(crate) struct ChatMessage {
+    pub role: &'static str,
+    /// Absent pour un message de l'assistant ne contenant que des appels d'outils
+    pub content: Option<WireContent>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub tool_calls: Vec<WireToolCall>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tool_call_id: Option<String>,
+}
+
+impl TryFrom<&LLMMessage> for ChatMessage {
+    type Error = LLMError;
+
+    fn try_from(message: &LLMMessage) -> Result<Self, LLMError> {
+        let tool_calls: Vec<WireToolCall> =
+            message.tool_calls.iter().map(WireToolCall::from).collect();
+        let content = if message.content.is_empty() && !tool_calls.is_empty() {
+            None
+        } else {
+            Some(WireContent::new(&message.content, &message.role)?)
+        };
+
+        Ok(ChatMessage {
+            role: match message.role {
+                Role::System => "system",
+                Role::User => "user",
+                Role::Assistant => "assistant",
+                Role::Tool => "tool",
+            },
+            content,
+            tool_calls,
+            tool_call_id: message.tool_call_id.clone(),
+        })
+    }
+}
+
+/// Contenu d'un message : chaîne simple, ou liste de parts pour un message multimodal
+#[derive(Serialize)]
+#[serde(untagged)]
+pub(crate) enum WireContent {
+    Text(String),
+    Parts(Vec<WirePart>),
+}
+
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub(crate) enum WirePart {
+    Text { text: String },
+    ImageUrl { image_url: ImageUrl },
+    File { file: FileRef },
+}
+
+#[derive(Serialize)]
+pub(crate) struct ImageUrl {
+    pub url: String,
+}
+
+#[derive(Serialize)]
+pub(crate) struct FileRef {
+    pub file_id: String,
+}
+
+impl WireContent {
+    /// Les images sont envoyées en URL `data:` ; un fichier est une URL d'image
+    /// ou l'identifiant d'un fichier téléversé. Seuls les messages `user` acceptent
+    /// autre chose que du texte.
+    fn new(content: &MessageContent, role: &Role) -> Result<Self, LLMError> {
+        let MessageContent::Parts(parts) = content else {
+            return Ok(WireContent::Text(content.text()));
+        };
+        if *role != Role::User {
+            if let Some(part) = content.first_non_text() {
+                return Err(LLMError::InvalidConfig(format!(
+                    "Contenu {} non supporté dans un message {:?} (réservé aux messages user)",
+                    part.kind(),
+                    role
+                )));
+            }
+            return Ok(WireContent::Text(content.text()));
+        }
+
+        let parts = parts
+            .iter()
+            .map(|part| match part {
+                ContentPart::Text { text } => Ok(WirePart::Text { text: text.clone() }),
+                ContentPart::Image { media_type, data } => Ok(WirePart::ImageUrl {
+                    image_url: ImageUrl {
+                        url: format!("data:{};base64,{}", media_type, data),
+                    },
+                }),
+                ContentPart::File { uri, media_type } => {
+                    let is_url = uri.starts_with("http://") || uri.starts_with("https://");
+                    let is_image = match media_type.as_deref() {
+                        Some(media_type) => media_type.starts_with("image/"),
+                        None => true,
+                    };
+                    match (is_url, is_image) {
+                        (true, true) => Ok(WirePart::ImageUrl {
+                            image_url: ImageUrl { url: uri.clone() },
+                        }),
+                        (false, _) => Ok(WirePart::File {
+                            file: FileRef { file_id: uri.clone() },
+                        }),
+                        (true, false) => Err(LLMError::InvalidConfig(format!(
+                            "Document par URL non supporté ({}) : utiliser l'identifiant \
+                             d'un fichier téléversé",
+                            uri
+                        ))),
+                    }
+                }
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(WireContent::Parts(parts))
+    }
+}
+
+/// Outil au format `{"type": "function", "function": {...}}`
+#[derive(Serialize)]
+pub(crate) struct WireTool {
+    #[serde(rename = "type")]
+    pub kind: &'static str,
+    pub function: WireFunction,
+}
+
+#[derive(Serialize)]
+pub(crate) struct WireFunction {
+    pub name: String,
+    pub description: String,
+    pub parameters: Value,
+}
+
+impl From<&ToolDefinition> for WireTool {
+    fn from(tool: &ToolDefinition) -> Self {
+        WireTool {
+            kind: "function",
+            function: WireFunction {
+                name: tool.name.clone(),
+                description: tool.description.clone(),
+                parameters: tool.parameters.clone(),
+            },
+        }
+    }
+}
+
+/// Appel d'outil ; les arguments sont transmis sous forme de chaîne JSON
+#[derive(Serialize, Deserialize)]
+pub(crate) struct WireToolCall {
+    #[serde(default)]
+    pub id: String,
+    #[serde(rename = "type", default = "function_type")]
+    pub kind: String,
+    pub function: WireCall,
+}
+
+#[derive(Serialize, Deserialize)]
+pub(crate) struct WireCall {
+    pub name: String,
+    #[serde(default)]
+    pub arguments: String,
+}
+
+fn function_type() -> String {
+    "function".to_string()
+}
+
+impl From<&ToolCall> for WireToolCall {
+    fn from(call: &ToolCall) -> Self {
+        WireToolCall {
+            id: call.id.clone(),
+            kind: function_type(),
+            function: WireCall {
+                name: call.name.clone(),
+                arguments: call.arguments.to_string(),
+            },
+        }
+    }
+}
+
+impl From<WireToolCall> for ToolCall {
+    fn from(call: WireToolCall) -> Self {
+        ToolCall {
+            id: call.id,
+            name: call.function.name,
+            arguments: parse_arguments(&call.function.arguments),
+        }
+    }
+}
+
+/// Les arguments invalides (JSON tronqué, etc.) sont conservés tels quels en chaîne
+fn parse_arguments(arguments: &str) -> Value {
+    if arguments.trim().is_empty() {
+        return json!({});
+    }
+    serde_json::from_str(arguments).unwrap_or_else(|_| Value::String(arguments.to_string()))
+}
+
+
+/// Réponse (non streaming) de l'API Chat Completions
+#[derive(Deserialize)]
+pub(crate) struct ChatResponse {
+    #[serde(default)]
+    pub id: Option<String>,
+    #[serde(default)]
+    pub model: Option<String>,
+    pub choices: Vec<ChatChoice>,
+    #[serde(default)]
+    pub usage: Option<WireUsage>,
+}
+
+#[derive(Deserialize)]
+pub(crate) struct ChatChoice {
+    pub message: ChoiceMessage,
+    pub finish_reason: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub(crate) struct ChoiceMessage {
+    #[serde(default)]
+    pub content: Option<String>,
+    #[serde(default)]
+    pub tool_calls: Vec<WireToolCall>,
+}
+
+#[derive(Deserialize, Clone, Copy, Default)]
+pub(crate) struct WireUsage {
+    #[serde(default)]
+    pub prompt_tokens: u32,
+    #[serde(default)]
+    pub completion_tokens: u32,
+    #[serde(default)]
+    pub total_tokens: u32,
+}
+
+impl From<WireUsage> for TokenUsage {
+    fn from(usage: WireUsage) -> Self {
+        TokenUsage {
+            prompt_tokens: usage.prompt_tokens,
+            completion_tokens: usage.completion_tokens,
+            total_tokens: usage.total_tokens.max(usage.prompt_tokens + usage.completion_tokens),
+        }
+    }
+}
+
+impl ChatResponse {
+    /// Convertit la réponse en `LLMResponse` (seul le premier choix est retenu)
+    pub(crate) fn into_llm_response(self, default_model: &str) -> Result<LLMResponse, LLMError> {
+        let choice = self
+            .choices
+            .into_iter()
+            .next()
+            .ok_or_else(|| LLMError::ParseError("Réponse sans aucun choix".to_string()))?;
+
+        let metadata = self.id.map(|id| HashMap::from([("id".to_string(), id)]));
+
+        Ok(LLMResponse {
+            content: choice.message.content.unwrap_or_default(),
+            finish_reason: map_finish_reason(choice.finish_reason.as_deref()),
+            usage: self.usage.unwrap_or_default().into(),
+            model: self.model.unwrap_or_else(|| default_model.to_string()),
+            metadata,
+            tool_calls: choice.message.tool_calls.into_iter().map(ToolCall::from).collect(),
+        })
+    }
+}
+
+pub(crate) fn map_finish_reason(reason: Option<&str>) -> FinishReason {
+    match reason {
+        Some("length") => FinishReason::Length,
+        Some("content_filter") => FinishReason::ContentFilter,
+        Some("tool_calls") | Some("function_call") => FinishReason::ToolUse,
+        _ => FinishReason::Stop,
+    }
+}
+
+
+/// Convertit un flux SSE Chat Completions en flux de `LLMStreamChunk`.
+///
+/// L'usage, envoyé après le dernier choix avec `include_usage`, est reporté sur
+/// le chunk final émis par le moteur de streaming, de même que les appels d'outils
+/// reconstitués à partir de leurs fragments.
+pub(crate) fn chat_stream(response: reqwest::Response) -> LLMStream {
+    decode_response(response, StreamFormat::Sse, ChatStreamHandler::default())
+}
+
+#[derive(Default)]
+struct ChatStreamHandler {
+    /// Appels d'outils en cours, indexés par `index`
+    tool_calls: BTreeMap<usize, PartialToolCall>,
+}
+
+#[derive(Default)]
+struct PartialToolCall {
+    id: String,
+    name: String,
+    arguments: String,
+}
+
+impl StreamHandler for ChatStreamHandler {
+    fn on_event(&mut self, event: &StreamEvent) -> Result<StreamUpdate, LLMError> {
+        let chunk: ChatChunk = serde_json::from_str(&event.data)
+            .map_err(|e| LLMError::ParseError(format!("Chunk Chat Completions invalide: {}", e)))?;
+
+        if let Some(error) = chunk.error {
+            return Err(LLMError::APIError {
+                status: 500,
+                message: error.message,
+                retry_after: None,
+            });
+        }
+
+        let mut update = StreamUpdate {
+            usage: chunk.usage.map(TokenUsage::from),
+            ..Default::default()
+        };
+        for choice in chunk.choices {
+            if let Some(content) = choice.delta.content {
+                update.delta.push_str(&content);
+            }
+            for (position, fragment) in choice.delta.tool_calls.into_iter().enumerate() {
+                let call = self
+                    .tool_calls
+                    .entry(fragment.index.unwrap_or(position))
+                    .or_default();
+                if let Some(id) = fragment.id {
+                    call.id = id;
+                }
+                if let Some(function) = fragment.function {
+                    call.name.push_str(&function.name.unwrap_or_default());
+                    call.arguments.push_str(&function.arguments.unwrap_or_default());
+                }
+            }
+            if let Some(reason) = choice.finish_reason {
+                update.finish_reason = Some(map_finish_reason(Some(&reason)));
+                update.tool_calls = std::mem::take(&mut self.tool_calls)
+                    .into_values()
+                    .map(|call| ToolCall {
+                        id: call.id,
+                        name: call.name,
+                        arguments: parse_arguments(&call.arguments),
+                    })
+                    .collect();
+            }
+        }
+
+        Ok(update)
+    }
+}
+
+#[derive(Deserialize)]
+struct ChatChunk {
+    #[serde(default)]
+    choices: Vec<ChunkChoice>,
+    #[serde(default)]
+    usage: Option<WireUsage>,
+    #[serde(default)]
+    error: Option<ChunkError>,
+}
+
+#[derive(Deserialize)]
+struct ChunkChoice {
+    #[serde(default)]
+    delta: ChunkDelta,
+    finish_reason: Option<String>,
+}
+
+#[derive(Deserialize, Default)]
+struct ChunkDelta {
+    #[serde(default)]
+    content: Option<String>,
+    #[serde(default)]
+    tool_calls: Vec<ToolCallFragment>,
+}
+
+#[derive(Deserialize)]
+struct ToolCallFragment {
+    #[serde(default)]
+    index: Option<usize>,
+    #[serde(default)]
+    id: Option<String>,
+    #[serde(default)]
+    function: Option<FunctionFragment>,
+}
+
+#[derive(Deserialize)]
+struct FunctionFragment {
+    #[serde(default)]
+    name: Option<String>,
+    #[serde(default)]
+    arguments: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ChunkError {
+    message: String,
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::test_support::message;
+
+    fn event(data: Value) -> StreamEvent {
+        StreamEvent {
+            event: None,
+            data: data.to_string(),
+        }
+    }
+
+    #[test]
+    fn tool_messages_use_the_chat_format() {
+        let call = ToolCall {
+            id: "call_1".to_string(),
+            name: "weather".to_string(),
+            arguments: json!({ "city": "Paris" }),
+        };
+        let assistant = LLMMessage {
+            tool_calls: vec![call],
+            ..message(Role::Assistant, "")
+        };
+        let result = LLMMessage {
+            tool_call_id: Some("call_1".to_string()),
+            ..message(Role::Tool, "21°C")
+        };
+
+        let assistant = serde_json::to_value(ChatMessage::try_from(&assistant).unwrap()).unwrap();
+        assert_eq!(
+            assistant,
+            json!({
+                "role": "assistant",
+                "content": null,
+                "tool_calls": [{
+                    "id": "call_1",
+                    "type": "function",
+                    "function": { "name": "weather", "arguments": "{\"city\":\"Paris\"}" }
+                }]
+            })
+        );
+        let result = serde_json::to_value(ChatMessage::try_from(&result).unwrap()).unwrap();
+        assert_eq!(
+            result,
+            json!({ "role": "tool", "content": "21°C", "tool_call_id": "call_1" })
+        );
+    }
+
+    #[test]
+    fn streamed_tool_call_fragments_are_reassembled() {
+        let mut handler = ChatStreamHandler::default();
+        let fragments = [
+            json!({ "choices": [{ "delta": { "tool_calls": [
+                { "index": 0, "id": "call_1", "function": { "name": "weather", "arguments": "" } },
+                { "index": 1, "id": "call_2", "function": { "name": "time", "arguments": "{}" } }
+            ] } }] }),
+            json!({ "choices": [{ "delta": { "tool_calls": [
+                { "index": 0, "function": { "arguments": "{\"city\":" } }
+            ] } }] }),
+            json!({ "choices": [{ "delta": { "tool_calls": [
+                { "index": 0, "function": { "arguments": "\"Paris\"}" } }
+            ] } }] }),
+        ];
+        for fragment in fragments {
+            let update = handler.on_event(&event(fragment)).unwrap();
+            assert!(update.tool_calls.is_empty());
+        }
+
+        let last = json!({ "choices": [{ "delta": {}, "finish_reason": "tool_calls" }] });
+        let update = handler.on_event(&event(last)).unwrap();
+        assert_eq!(update.finish_reason, Some(FinishReason::ToolUse));
+        let calls: Vec<_> = update
+            .tool_calls
+            .iter()
+            .map(|call| (call.id.as_str(), call.name.as_str(), call.arguments.clone()))
+            .collect();
+        assert_eq!(
+            calls,
+            [
+                ("call_1", "weather", json!({ "city": "Paris" })),
+                ("call_2", "time", json!({})),
+            ]
+        );
+    }
+
+    #[test]
+    fn invalid_arguments_are_kept_as_text() {
+        assert_eq!(parse_arguments("  "), json!({}));
+        assert_eq!(parse_arguments("{\"city\":"), json!("{\"city\":"));
+    }
+
+    #[test]
+    fn user_parts_become_image_urls_and_file_ids() {
+        let user = message(Role::User, "");
+        let user = LLMMessage {
+            content: MessageContent::from(vec![
+                ContentPart::text("Décris"),
+                ContentPart::image_bytes("image/png", b"png"),
+                ContentPart::File {
+                    uri: "https://exemple.fr/schema.webp".to_string(),
+                    media_type: None,
+                },
+                ContentPart::File {
+                    uri: "file-abc".to_string(),
+                    media_type: Some("application/pdf".to_string()),
+                },
+            ]),
+            ..user
+        };
+
+        let wire = serde_json::to_value(ChatMessage::try_from(&user).unwrap()).unwrap();
+        assert_eq!(
+            wire["content"],
+            json!([
+                { "type": "text", "text": "Décris" },
+                { "type": "image_url", "image_url": { "url": "data:image/png;base64,cG5n" } },
+                { "type": "image_url", "image_url": { "url": "https://exemple.fr/schema.webp" } },
+                { "type": "file", "file": { "file_id": "file-abc" } }
+            ])
+        );
+    }
+
+    #[test]
+    fn non_text_parts_are_user_only() {
+        let document = ContentPart::File {
+            uri: "https://exemple.fr/doc.pdf".to_string(),
+            media_type: Some("application/pdf".to_string()),
+        };
+        let user = LLMMessage {
+            content: MessageContent::from(vec![document]),
+            ..message(Role::User, "")
+        };
+        assert!(ChatMessage::try_from(&user).is_err());
+
+        let assistant = LLMMessage {
+            content: MessageContent::from(vec![ContentPart::image_bytes("image/png", b"png")]),
+            ..message(Role::Assistant, "")
+        };
+        assert!(matches!(
+            ChatMessage::try_from(&assistant),
+            Err(LLMError::InvalidConfig(_))
+        ));
+    }
+}
@@ -0,0 +1,354 @@
+// Enregistrement et rejeu des échanges avec un provider (tests sans réseau)
+
+use async_trait::async_trait;
+use futures::StreamExt;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use super::cache::cache_key;
+use super::streaming::{collect_response, response_stream};
+use super::tokenizer::tokenizer_for;
+use super::{
+    LLMError, LLMProvider, LLMProviderType, LLMRequest, LLMResponse, LLMStream, LLMStreamChunk,
+};
+
+/// Longueur maximale de l'extrait de prompt cité dans une erreur de rejeu
+const EXCERPT_CHARS: usize = 80;
+
+
+/// Échanges enregistrés, sérialisés en JSON
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Cassette {
+    pub interactions: Vec<Interaction>,
+}
+
+/// Requête et réponse enregistrées
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Interaction {
+    /// Nom et modèle du provider enregistré
+    pub provider: String,
+    pub model: String,
+    pub request: LLMRequest,
+    pub response: RecordedResponse,
+}
+
+/// Réponse complète ou chunks d'un flux, dans leur ordre de réception
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RecordedResponse {
+    Complete(LLMResponse),
+    Stream(Vec<LLMStreamChunk>),
+}
+
+impl Cassette {
+    pub fn load(path: &Path) -> Result<Self, LLMError> {
+        let content = std::fs::read_to_string(path).map_err(|e| {
+            LLMError::InvalidConfig(format!(
+                "Lecture de la cassette {} impossible: {}",
+                path.display(),
+                e
+            ))
+        })?;
+        serde_json::from_str(&content).map_err(|e| {
+            LLMError::ParseError(format!("Cassette {} invalide: {}", path.display(), e))
+        })
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), LLMError> {
+        let write_error = |e: std::io::Error| {
+            LLMError::InternalError(format!(
+                "Écriture de la cassette {} impossible: {}",
+                path.display(),
+                e
+            ))
+        };
+
+        if let Some(parent) = path.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent).map_err(write_error)?;
+        }
+        let content = serde_json::to_string_pretty(self)
+            .map_err(|e| LLMError::InternalError(format!("Cassette: {}", e)))?;
+        std::fs::write(path, content).map_err(write_error)
+    }
+}
+
+
+/// Critère de correspondance entre une requête et un enregistrement.
+///
+/// Dans les deux modes, l'indicateur de streaming et les métadonnées des messages sont
+/// ignorés : un flux enregistré peut servir une requête complète et inversement.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum MatchMode {
+    /// Messages, paramètres, outils et FIM identiques
+    #[default]
+    Exact,
+    /// Comme `Exact`, sans tenir compte des paramètres de génération
+    IgnoreParameters,
+}
+
+impl MatchMode {
+    fn key(&self, request: &LLMRequest) -> String {
+        match self {
+            MatchMode::Exact => cache_key("", "", request),
+            MatchMode::IgnoreParameters => cache_key(
+                "",
+                "",
+                &LLMRequest {
+                    parameters: None,
+                    ..request.clone()
+                },
+            ),
+        }
+    }
+}
+
+
+/// Provider qui transmet les appels à un provider réel et enregistre chaque échange
+/// réussi dans une cassette.
+///
+/// Les échanges sont ajoutés à la cassette existante ; le fichier est réécrit après
+/// chaque réponse complète ou flux lu jusqu'au bout.
+pub struct RecordingProvider {
+    inner: Box<dyn LLMProvider>,
+    path: PathBuf,
+    cassette: Arc<Mutex<Cassette>>,
+}
+
+impl RecordingProvider {
+    pub fn new(inner: Box<dyn LLMProvider>, path: impl Into<PathBuf>) -> Result<Self, LLMError> {
+        let path = path.into();
+        let cassette = if path.exists() {
+            Cassette::load(&path)?
+        } else {
+            Cassette::default()
+        };
+
+        Ok(RecordingProvider {
+            inner,
+            path,
+            cassette: Arc::new(Mutex::new(cassette)),
+        })
+    }
+
+    fn interaction(&self, request: LLMRequest, response: RecordedResponse) -> Interaction {
+        Interaction {
+            provider: self.inner.provider_name().to_string(),
+            model: self.inner.model_name().to_string(),
+            request,
+            response,
+        }
+    }
+}
+
+/// Ajoute un échange à la cassette et la réécrit
+fn record(
+    cassette: &Mutex<Cassette>,
+    path: &Path,
+    interaction: Interaction,
+) -> Result<(), LLMError> {
+    let mut cassette = cassette
+        .lock()
+        .map_err(|_| LLMError::InternalError("Cassette verrouillée".to_string()))?;
+    cassette.interactions.push(interaction);
+    cassette.save(path)
+}
+
+#[async_trait]
+impl LLMProvider for RecordingProvider {
+    async fn generate(&self, request: LLMRequest) -> Result<LLMResponse, LLMError> {
+        let response = self.inner.generate(request.clone()).await?;
+        let interaction = self.interaction(request, RecordedResponse::Complete(response.clone()));
+        record(&self.cassette, &self.path, interaction)?;
+        Ok(response)
+    }
+
+    async fn generate_stream(&self, request: LLMRequest) -> Result<LLMStream, LLMError> {
+        let stream = self.inner.generate_stream(request.clone()).await?;
+
+        // Les chunks sont copiés au passage ; l'échange n'est enregistré qu'à la fin d'un
+        // flux sans erreur
+        let chunks = Arc::new(Mutex::new(Some(Vec::new())));
+        let received = Arc::clone(&chunks);
+        let stream = stream.map(move |chunk| {
+            if let Ok(mut received) = received.lock() {
+                match (&chunk, received.as_mut()) {
+                    (Ok(chunk), Some(chunks)) => chunks.push(chunk.clone()),
+                    (Ok(_), None) => {}
+                    (Err(_), _) => *received = None,
+                }
+            }
+            chunk
+        });
+
+        let mut interaction = self.interaction(request, RecordedResponse::Stream(Vec::new()));
+        let cassette = Arc::clone(&self.cassette);
+        let path = self.path.clone();
+        let end = futures::stream::once(async move {
+            let recorded = chunks.lock().ok().and_then(|mut chunks| chunks.take());
+            if let Some(recorded) = recorded {
+                interaction.response = RecordedResponse::Stream(recorded);
+                if let Err(error) = record(&cassette, &path, interaction) {
+                    tracing::warn!("Flux non enregistré: {}", error);
+                }
+            }
+        })
+        .filter_map(|_| async { None::<Result<LLMStreamChunk, LLMError>> });
+
+        Ok(Box::new(Box::pin(stream.chain(end))))
+    }
+
+    fn count_tokens(&self, text: &str) -> Result<u32, LLMError> {
+        self.inner.count_tokens(text)
+    }
+
+    async fn count_tokens_exact(&self, text: &str) -> Result<u32, LLMError> {
+        self.inner.count_tokens_exact(text).await
+    }
+
+    fn provider_name(&self) -> &str {
+        self.inner.provider_name()
+    }
+
+    fn model_name(&self) -> &str {
+        self.inner.model_name()
+    }
+
+    async fn health_check(&self) -> Result<(), LLMError> {
+        self.inner.health_check().await
+    }
+}
+
+
+/// Provider qui rejoue les échanges d'une cassette, sans réseau.
+///
+/// Une requête enregistrée plusieurs fois reçoit les réponses dans l'ordre
+/// d'enregistrement, puis la dernière. Une requête absente de la cassette échoue avec
+/// `LLMError::ReplayMiss`.
+pub struct ReplayProvider {
+    source: String,
+    cassette: Cassette,
+    mode: MatchMode,
+    provider_name: String,
+    model_name: String,
+    /// Clé de correspondance de chaque échange, dans l'ordre de la cassette
+    keys: Vec<String>,
+    /// Nombre de fois où chaque clé a été servie
+    served: Mutex<HashMap<String, usize>>,
+}
+
+impl ReplayProvider {
+    pub fn new(path: &Path, mode: MatchMode) -> Result<Self, LLMError> {
+        let cassette = Cassette::load(path)?;
+        Ok(Self::from_cassette(cassette, mode, path.display().to_string()))
+    }
+
+    /// Rejoue une cassette déjà chargée ; `source` la désigne dans les erreurs
+    pub fn from_cassette(cassette: Cassette, mode: MatchMode, source: impl Into<String>) -> Self {
+        let keys = cassette
+            .interactions
+            .iter()
+            .map(|interaction| mode.key(&interaction.request))
+            .collect();
+        let (provider_name, model_name) = cassette
+            .interactions
+            .first()
+            .map(|interaction| (interaction.provider.clone(), interaction.model.clone()))
+            .unwrap_or_else(|| ("replay".to_string(), "replay".to_string()));
+
+        ReplayProvider {
+            source: source.into(),
+            cassette,
+            mode,
+            provider_name,
+            model_name,
+            keys,
+            served: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn find(&self, request: &LLMRequest) -> Result<&RecordedResponse, LLMError> {
+        let key = self.mode.key(request);
+        let matches: Vec<usize> = self
+            .keys
+            .iter()
+            .enumerate()
+            .filter(|(_, candidate)| **candidate == key)
+            .map(|(index, _)| index)
+            .collect();
+
+        let Some(last) = matches.last() else {
+            return Err(LLMError::ReplayMiss(format!(
+                "{} (mode {:?}, {} échange(s) enregistré(s)) ; dernier message: \"{}\"",
+                self.source,
+                self.mode,
+                self.keys.len(),
+                excerpt(request)
+            )));
+        };
+
+        let mut served = self
+            .served
+            .lock()
+            .map_err(|_| LLMError::InternalError("Cassette verrouillée".to_string()))?;
+        let count = served.entry(key).or_insert(0);
+        let index = matches.get(*count).unwrap_or(last);
+        *count += 1;
+        Ok(&self.cassette.interactions[*index].response)
+    }
+}
+
+/// Début du dernier message de la requête (ou du préfixe FIM)
+fn excerpt(request: &LLMRequest) -> String {
+    let text = match (request.messages.last(), &request.fim) {
+        (_, Some(fim)) => fim.prefix.clone(),
+        (Some(message), None) => message.content.text(),
+        (None, None) => String::new(),
+    };
+
+    let mut excerpt: String = text.chars().take(EXCERPT_CHARS).collect();
+    if text.chars().count() > EXCERPT_CHARS {
+        excerpt.push('…');
+    }
+    excerpt
+}
+
+#[async_trait]
+impl LLMProvider for ReplayProvider {
+    async fn generate(&self, request: LLMRequest) -> Result<LLMResponse, LLMError> {
+        match self.find(&request)? {
+            RecordedResponse::Complete(response) => Ok(response.clone()),
+            RecordedResponse::Stream(chunks) => {
+                let chunks: Vec<_> = chunks.iter().cloned().map(Ok).collect();
+                collect_response(Box::new(futures::stream::iter(chunks)), &self.model_name).await
+            }
+        }
+    }
+
+    async fn generate_stream(&self, request: LLMRequest) -> Result<LLMStream, LLMError> {
+        match self.find(&request)? {
+            RecordedResponse::Complete(response) => Ok(response_stream(response.clone())),
+            RecordedResponse::Stream(chunks) => {
+                let chunks: Vec<_> = chunks.iter().cloned().map(Ok).collect();
+                Ok(Box::new(futures::stream::iter(chunks)))
+            }
+        }
+    }
+
+    fn count_tokens(&self, text: &str) -> Result<u32, LLMError> {
+        Ok(tokenizer_for(&LLMProviderType::Custom, &self.model_name).count(text))
+    }
+
+    fn provider_name(&self) -> &str {
+        &self.provider_name
+    }
+
+    fn model_name(&self) -> &str {
+        &self.model_name
+    }
+
+    async fn health_check(&self) -> Result<(), LLMError> {
+        Ok(())
+    }
+}
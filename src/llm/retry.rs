@@ -0,0 +1,368 @@
+// Nouvelles tentatives avec backoff exponentiel autour de n'importe quel provider
+
+use async_trait::async_trait;
+use futures::{Future, StreamExt};
+use std::collections::hash_map::RandomState;
+use std::collections::HashMap;
+use std::hash::{BuildHasher, Hasher};
+use std::time::Duration;
+
+use super::{LLMError, LLMProvider, LLMProviderConfig, LLMRequest, LLMResponse, LLMStream};
+
+
+/// Indique si une erreur est transitoire et justifie une nouvelle tentative.
+///
+/// Sont retentées : les erreurs réseau, les timeouts et les erreurs API 408, 425,
+/// 429 et 5xx. Les erreurs d'authentification ou de configuration ne le sont jamais.
+pub fn is_retryable(error: &LLMError) -> bool {
+    match error {
+        LLMError::NetworkError(_) | LLMError::Timeout => true,
+        LLMError::APIError { status, .. } => matches!(status, 408 | 425 | 429 | 500..=599),
+        _ => false,
+    }
+}
+
+
+/// Politique de nouvelles tentatives
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    /// Nombre de tentatives supplémentaires après la première
+    pub max_retries: u32,
+    /// Délai avant la première nouvelle tentative
+    pub initial_backoff: Duration,
+    /// Délai maximal entre deux tentatives
+    pub max_backoff: Duration,
+    /// Facteur multiplicatif entre deux tentatives
+    pub multiplier: f64,
+    /// Au-delà de ce délai `Retry-After`, l'erreur est renvoyée sans attendre
+    pub max_retry_after: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            max_retries: 3,
+            initial_backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(30),
+            multiplier: 2.0,
+            max_retry_after: Duration::from_secs(120),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Politique par défaut avec le `max_retries` de la configuration
+    pub fn from_config(config: &LLMProviderConfig) -> Self {
+        RetryPolicy {
+            max_retries: config.max_retries,
+            ..Default::default()
+        }
+    }
+
+    /// Délai avant la tentative suivant l'échec numéro `attempt` (à partir de 1).
+    ///
+    /// Le `Retry-After` du serveur est respecté tel quel ; sinon le backoff
+    /// exponentiel est tiré aléatoirement dans `[backoff / 2, backoff]`.
+    /// Retourne `None` si le serveur demande d'attendre plus que `max_retry_after`.
+    pub fn delay(&self, attempt: u32, error: &LLMError) -> Option<Duration> {
+        if let LLMError::APIError {
+            retry_after: Some(retry_after),
+            ..
+        } = error
+        {
+            return (*retry_after <= self.max_retry_after).then_some(*retry_after);
+        }
+
+        let exponent = attempt.saturating_sub(1).min(32) as i32;
+        let backoff = self
+            .initial_backoff
+            .mul_f64(self.multiplier.powi(exponent))
+            .min(self.max_backoff);
+
+        Some(backoff.mul_f64(0.5 + 0.5 * jitter()))
+    }
+}
+
+/// Valeur pseudo-aléatoire dans `[0, 1)` pour étaler les tentatives
+fn jitter() -> f64 {
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_u128(
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_nanos(),
+    );
+    (hasher.finish() >> 11) as f64 / (1u64 << 53) as f64
+}
+
+
+/// Provider qui applique `max_retries` et `timeout_seconds` autour d'un autre provider.
+///
+/// Le nombre de tentatives est ajouté à `LLMResponse.metadata` (clé `attempts`) et,
+/// en streaming, aux métadonnées du chunk final. Un flux n'est retenté que tant
+/// qu'il n'a pas été établi.
+pub struct RetryProvider {
+    inner: Box<dyn LLMProvider>,
+    policy: RetryPolicy,
+    timeout: Option<Duration>,
+}
+
+impl RetryProvider {
+    pub fn new(
+        inner: Box<dyn LLMProvider>,
+        policy: RetryPolicy,
+        timeout: Option<Duration>,
+    ) -> Self {
+        RetryProvider {
+            inner,
+            policy,
+            timeout,
+        }
+    }
+
+    /// Enveloppe un provider avec les valeurs `max_retries` et `timeout_seconds` de sa configuration
+    pub fn from_config(inner: Box<dyn LLMProvider>, config: &LLMProviderConfig) -> Self {
+        let timeout = (config.timeout_seconds > 0)
+            .then(|| Duration::from_secs(config.timeout_seconds));
+        Self::new(inner, RetryPolicy::from_config(config), timeout)
+    }
+
+    async fn with_timeout<T, F>(&self, future: F) -> Result<T, LLMError>
+    where
+        F: Future<Output = Result<T, LLMError>>,
+    {
+        match self.timeout {
+            Some(timeout) => tokio::time::timeout(timeout, future)
+                .await
+                .unwrap_or(Err(LLMError::Timeout)),
+            None => future.await,
+        }
+    }
+
+    /// Retourne le délai avant la prochaine tentative, ou l'erreur si on abandonne
+    fn next_delay(&self, attempt: u32, error: LLMError) -> Result<Duration, LLMError> {
+        if attempt > self.policy.max_retries || !is_retryable(&error) {
+            return Err(error);
+        }
+        let delay = self.policy.delay(attempt, &error).ok_or_else(|| error.clone())?;
+
+        tracing::warn!(
+            provider = self.inner.provider_name(),
+            attempt,
+            delay_ms = delay.as_millis() as u64,
+            "Échec transitoire, nouvelle tentative: {}",
+            error
+        );
+        Ok(delay)
+    }
+}
+
+#[async_trait]
+impl LLMProvider for RetryProvider {
+    async fn generate(&self, request: LLMRequest) -> Result<LLMResponse, LLMError> {
+        let mut attempt = 0;
+        loop {
+            attempt += 1;
+            match self.with_timeout(self.inner.generate(request.clone())).await {
+                Ok(mut response) => {
+                    response
+                        .metadata
+                        .get_or_insert_with(HashMap::new)
+                        .insert("attempts".to_string(), attempt.to_string());
+                    return Ok(response);
+                }
+                Err(error) => {
+                    let delay = self.next_delay(attempt, error)?;
+                    tokio::time::sleep(delay).await;
+                }
+            }
+        }
+    }
+
+    async fn generate_stream(&self, request: LLMRequest) -> Result<LLMStream, LLMError> {
+        let mut attempt = 0;
+        let stream = loop {
+            attempt += 1;
+            match self.with_timeout(self.inner.generate_stream(request.clone())).await {
+                Ok(stream) => break stream,
+                Err(error) => {
+                    let delay = self.next_delay(attempt, error)?;
+                    tokio::time::sleep(delay).await;
+                }
+            }
+        };
+
+        let stream = stream.map(move |chunk| {
+            chunk.map(|mut chunk| {
+                if chunk.finish_reason.is_some() {
+                    chunk
+                        .metadata
+                        .get_or_insert_with(HashMap::new)
+                        .insert("attempts".to_string(), attempt.to_string());
+                }
+                chunk
+            })
+        });
+        Ok(Box::new(stream))
+    }
+
+    fn count_tokens(&self, text: &str) -> Result<u32, LLMError> {
+        self.inner.count_tokens(text)
+    }
+
+    async fn count_tokens_exact(&self, text: &str) -> Result<u32, LLMError> {
+        self.inner.count_tokens_exact(text).await
+    }
+
+    fn provider_name(&self) -> &str {
+        self.inner.provider_name()
+    }
+
+    fn model_name(&self) -> &str {
+        self.inner.model_name()
+    }
+
+    async fn health_check(&self) -> Result<(), LLMError> {
+        self.with_timeout(self.inner.health_check()).await
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::test_support::{response, user_request, ScriptedProvider};
+    use std::sync::atomic::Ordering;
+    use std::sync::Arc;
+
+    fn api_error(status: u16, retry_after: Option<Duration>) -> LLMError {
+        LLMError::APIError {
+            status,
+            message: "erreur".to_string(),
+            retry_after,
+        }
+    }
+
+    fn fast_policy(max_retries: u32) -> RetryPolicy {
+        RetryPolicy {
+            max_retries,
+            initial_backoff: Duration::from_millis(1),
+            max_backoff: Duration::from_millis(2),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn retryable_errors() {
+        assert!(is_retryable(&LLMError::NetworkError("reset".to_string())));
+        assert!(is_retryable(&LLMError::Timeout));
+        for status in [408, 425, 429, 500, 503, 529] {
+            assert!(is_retryable(&api_error(status, None)), "{}", status);
+        }
+        for status in [400, 404, 413, 422] {
+            assert!(!is_retryable(&api_error(status, None)), "{}", status);
+        }
+        assert!(!is_retryable(&LLMError::AuthenticationError("clé".to_string())));
+        assert!(!is_retryable(&LLMError::InvalidConfig("modèle".to_string())));
+    }
+
+    #[test]
+    fn exponential_backoff_with_jitter() {
+        let policy = RetryPolicy {
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_millis(1000),
+            ..Default::default()
+        };
+        let error = LLMError::Timeout;
+
+        for (attempt, backoff) in [(1, 100), (2, 200), (3, 400), (4, 800), (5, 1000), (40, 1000)] {
+            let delay = policy.delay(attempt, &error).unwrap();
+            let backoff = Duration::from_millis(backoff);
+            assert!(delay >= backoff / 2 && delay <= backoff, "{}: {:?}", attempt, delay);
+        }
+    }
+
+    #[test]
+    fn retry_after_is_honoured_up_to_the_limit() {
+        let policy = RetryPolicy::default();
+
+        let error = api_error(429, Some(Duration::from_secs(7)));
+        assert_eq!(policy.delay(1, &error), Some(Duration::from_secs(7)));
+
+        let error = api_error(429, Some(Duration::from_secs(600)));
+        assert_eq!(policy.delay(1, &error), None);
+    }
+
+    #[tokio::test]
+    async fn retries_until_success() {
+        let inner = ScriptedProvider::new(
+            "stub",
+            vec![Err(api_error(503, None)), Err(LLMError::Timeout), Ok(response("enfin"))],
+        );
+        let calls = Arc::clone(&inner.calls);
+        let provider = RetryProvider::new(Box::new(inner), fast_policy(3), None);
+
+        let response = provider.generate(user_request("Bonjour")).await.unwrap();
+        assert_eq!(response.content, "enfin");
+        assert_eq!(response.metadata.unwrap()["attempts"], "3");
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn gives_up_after_max_retries() {
+        let errors = (0..5).map(|_| Err(api_error(500, None))).collect();
+        let inner = ScriptedProvider::new("stub", errors);
+        let calls = Arc::clone(&inner.calls);
+        let provider = RetryProvider::new(Box::new(inner), fast_policy(2), None);
+
+        let error = provider.generate(user_request("Bonjour")).await.unwrap_err();
+        assert!(matches!(error, LLMError::APIError { status: 500, .. }));
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn permanent_errors_are_not_retried() {
+        let inner = ScriptedProvider::new("stub", vec![Err(api_error(400, None))]);
+        let calls = Arc::clone(&inner.calls);
+        let provider = RetryProvider::new(Box::new(inner), fast_policy(3), None);
+
+        assert!(provider.generate(user_request("Bonjour")).await.is_err());
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn long_retry_after_is_returned_immediately() {
+        let error = api_error(429, Some(Duration::from_secs(3600)));
+        let inner = ScriptedProvider::new("stub", vec![Err(error)]);
+        let calls = Arc::clone(&inner.calls);
+        let provider = RetryProvider::new(Box::new(inner), fast_policy(3), None);
+
+        let error = provider.generate(user_request("Bonjour")).await.unwrap_err();
+        assert!(matches!(error, LLMError::APIError { status: 429, .. }));
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn timeout_counts_as_a_retryable_failure() {
+        let mut inner = ScriptedProvider::new("stub", Vec::new());
+        inner.delay = Some(Duration::from_millis(200));
+        let calls = Arc::clone(&inner.calls);
+        let timeout = Some(Duration::from_millis(10));
+        let provider = RetryProvider::new(Box::new(inner), fast_policy(1), timeout);
+
+        let error = provider.generate(user_request("Bonjour")).await.unwrap_err();
+        assert!(matches!(error, LLMError::Timeout));
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn stream_final_chunk_reports_attempts() {
+        let inner = ScriptedProvider::new("stub", vec![Err(api_error(502, None))]);
+        let provider = RetryProvider::new(Box::new(inner), fast_policy(1), None);
+
+        let stream = provider.generate_stream(user_request("Bonjour")).await.unwrap();
+        let chunks: Vec<_> = stream.collect().await;
+        let last = chunks.last().unwrap().as_ref().unwrap();
+        assert_eq!(last.metadata.as_ref().unwrap()["attempts"], "2");
+    }
+}
@@ -0,0 +1,458 @@
+// Secrets (clés API) : valeurs masquées dans les logs et sources externes à la configuration
+
+use argon2::Argon2;
+use base64::Engine;
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use super::LLMError;
+
+/// Nom du trousseau dans le répertoire de données de l'utilisateur
+pub const KEYSTORE_FILE: &str = "secrets.enc";
+
+/// Variable d'environnement contenant la phrase de passe du trousseau
+pub const KEYSTORE_PASSPHRASE_ENV: &str = "CODECRAFTER_KEYSTORE_PASSPHRASE";
+
+/// Texte affiché à la place d'un secret
+pub const REDACTED: &str = "***";
+
+const KEYSTORE_VERSION: u32 = 1;
+const SALT_LEN: usize = 16;
+
+
+/// Valeur secrète : masquée par `Debug` et à la sérialisation
+#[derive(Clone, PartialEq, Eq)]
+pub struct Secret(String);
+
+impl Secret {
+    pub fn new(value: impl Into<String>) -> Self {
+        Secret(value.into())
+    }
+
+    /// Valeur en clair, à ne transmettre qu'au service concerné
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.trim().is_empty()
+    }
+}
+
+impl fmt::Debug for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Secret({})", REDACTED)
+    }
+}
+
+impl Serialize for Secret {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(REDACTED)
+    }
+}
+
+impl<'de> Deserialize<'de> for Secret {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer).map(Secret)
+    }
+}
+
+impl From<String> for Secret {
+    fn from(value: String) -> Self {
+        Secret(value)
+    }
+}
+
+impl From<&str> for Secret {
+    fn from(value: &str) -> Self {
+        Secret(value.to_string())
+    }
+}
+
+
+/// Origine d'un secret référencé dans la configuration.
+///
+/// ```toml
+/// api_key = { env = "ANTHROPIC_API_KEY" }
+/// api_key = { file = "~/.config/codecrafter/anthropic.key" }
+/// api_key = { keystore = "anthropic" }
+/// api_key = "sk-..."   # en clair, déconseillé
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum SecretSource {
+    /// Variable d'environnement
+    Env { env: String },
+    /// Fichier lisible par son seul propriétaire (droits 0600 sous unix)
+    File { file: PathBuf },
+    /// Entrée du trousseau chiffré (voir `Keystore`)
+    Keystore { keystore: String },
+    /// Valeur écrite dans la configuration
+    Value(Secret),
+}
+
+impl SecretSource {
+    /// Lit le secret à sa source
+    pub fn resolve(&self) -> Result<Secret, LLMError> {
+        match self {
+            SecretSource::Env { env } => std::env::var(env).map(Secret).map_err(|_| {
+                LLMError::InvalidConfig(format!("Variable d'environnement {} non définie", env))
+            }),
+            SecretSource::File { file } => read_secret_file(&expand_home(file)),
+            SecretSource::Keystore { keystore } => {
+                Keystore::open_default()?.get(keystore).cloned().ok_or_else(|| {
+                    LLMError::InvalidConfig(format!(
+                        "Secret `{}` absent du trousseau (codecrafter secret set {})",
+                        keystore, keystore
+                    ))
+                })
+            }
+            SecretSource::Value(secret) => Ok(secret.clone()),
+        }
+    }
+}
+
+impl From<Secret> for SecretSource {
+    fn from(secret: Secret) -> Self {
+        SecretSource::Value(secret)
+    }
+}
+
+impl From<&str> for SecretSource {
+    fn from(value: &str) -> Self {
+        SecretSource::Value(Secret::from(value))
+    }
+}
+
+/// Header ou option susceptible de transporter un secret : `authorization`, ou nom dont le
+/// dernier segment (séparé par `-` ou `_`) est `key`, `apikey`, `token`, `secret` ou
+/// `password` (`x-api-key`, `access_token`, mais pas `max_tokens`)
+pub fn is_sensitive_header(name: &str) -> bool {
+    const SENSITIVE: &[&str] = &["authorization", "key", "apikey", "token", "secret", "password"];
+    let name = name.to_ascii_lowercase();
+    let last = name.rsplit(['-', '_']).next().unwrap_or_default();
+    SENSITIVE.contains(&last)
+}
+
+fn expand_home(path: &Path) -> PathBuf {
+    match (path.strip_prefix("~"), directories::BaseDirs::new()) {
+        (Ok(rest), Some(dirs)) => dirs.home_dir().join(rest),
+        _ => path.to_path_buf(),
+    }
+}
+
+/// Contenu d'un fichier de secret, sans le saut de ligne final.
+///
+/// Sous unix, le fichier est refusé s'il est accessible au groupe ou aux autres.
+fn read_secret_file(path: &Path) -> Result<Secret, LLMError> {
+    let read_error = |e: std::io::Error| {
+        LLMError::InvalidConfig(format!("Lecture du secret {} impossible: {}", path.display(), e))
+    };
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+
+        let mode = std::fs::metadata(path).map_err(read_error)?.permissions().mode();
+        if mode & 0o077 != 0 {
+            return Err(LLMError::InvalidConfig(format!(
+                "{} est accessible à d'autres utilisateurs (droits {:o}): chmod 600 {}",
+                path.display(),
+                mode & 0o777,
+                path.display()
+            )));
+        }
+    }
+
+    let content = std::fs::read_to_string(path).map_err(read_error)?;
+    Ok(Secret(content.trim_end_matches(['\r', '\n']).to_string()))
+}
+
+
+/// Trousseau local : secrets nommés, chiffrés (ChaCha20-Poly1305) avec une clé dérivée
+/// de la phrase de passe par Argon2
+pub struct Keystore {
+    path: PathBuf,
+    passphrase: Secret,
+    entries: BTreeMap<String, Secret>,
+}
+
+/// Format du fichier du trousseau (JSON, champs binaires en base64)
+#[derive(Serialize, Deserialize)]
+struct KeystoreFile {
+    version: u32,
+    salt: String,
+    nonce: String,
+    ciphertext: String,
+}
+
+impl Keystore {
+    /// Ouvre le trousseau (vide si le fichier n'existe pas encore)
+    pub fn open(path: &Path, passphrase: Secret) -> Result<Self, LLMError> {
+        let entries = match std::fs::read_to_string(path) {
+            Ok(content) => decrypt(&content, &passphrase)?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => BTreeMap::new(),
+            Err(e) => return Err(keystore_error(e)),
+        };
+
+        Ok(Keystore {
+            path: path.to_path_buf(),
+            passphrase,
+            entries,
+        })
+    }
+
+    /// Ouvre le trousseau par défaut avec la phrase de passe de `KEYSTORE_PASSPHRASE_ENV`
+    pub fn open_default() -> Result<Self, LLMError> {
+        let path = Self::default_path().ok_or_else(|| {
+            LLMError::InternalError("Répertoire de données utilisateur introuvable".to_string())
+        })?;
+        let passphrase = std::env::var(KEYSTORE_PASSPHRASE_ENV).map_err(|_| {
+            LLMError::InvalidConfig(format!(
+                "Phrase de passe du trousseau manquante ({} non définie)",
+                KEYSTORE_PASSPHRASE_ENV
+            ))
+        })?;
+        Self::open(&path, Secret(passphrase))
+    }
+
+    /// Emplacement par défaut (`~/.local/share/codecrafter/secrets.enc` sous Linux)
+    pub fn default_path() -> Option<PathBuf> {
+        directories::ProjectDirs::from("", "", "codecrafter")
+            .map(|dirs| dirs.data_dir().join(KEYSTORE_FILE))
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Secret> {
+        self.entries.get(name)
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.entries.keys().map(String::as_str)
+    }
+
+    pub fn set(&mut self, name: impl Into<String>, secret: Secret) {
+        self.entries.insert(name.into(), secret);
+    }
+
+    pub fn remove(&mut self, name: &str) -> bool {
+        self.entries.remove(name).is_some()
+    }
+
+    /// Chiffre et écrit le trousseau, lisible par son seul propriétaire
+    pub fn save(&self) -> Result<(), LLMError> {
+        let content = encrypt(&self.entries, &self.passphrase)?;
+
+        if let Some(parent) = self.path.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent).map_err(keystore_error)?;
+        }
+        let mut options = std::fs::OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(0o600);
+        }
+
+        let mut file = options.open(&self.path).map_err(keystore_error)?;
+        file.write_all(content.as_bytes()).map_err(keystore_error)
+    }
+}
+
+fn derive_key(passphrase: &Secret, salt: &[u8]) -> Result<Key, LLMError> {
+    let mut key = Key::default();
+    Argon2::default()
+        .hash_password_into(passphrase.expose().as_bytes(), salt, &mut key)
+        .map_err(|e| LLMError::InternalError(format!("Dérivation de clé impossible: {}", e)))?;
+    Ok(key)
+}
+
+fn encrypt(entries: &BTreeMap<String, Secret>, passphrase: &Secret) -> Result<String, LLMError> {
+    // `Secret` se sérialise masqué : les valeurs sont exposées pour le seul chiffrement
+    let plain: BTreeMap<&str, &str> =
+        entries.iter().map(|(name, secret)| (name.as_str(), secret.expose())).collect();
+    let plaintext = serde_json::to_vec(&plain)
+        .map_err(|e| LLMError::InternalError(format!("Trousseau: {}", e)))?;
+
+    let mut salt = [0u8; SALT_LEN];
+    OsRng.fill_bytes(&mut salt);
+    let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+    let cipher = ChaCha20Poly1305::new(&derive_key(passphrase, &salt)?);
+    let ciphertext = cipher
+        .encrypt(&nonce, plaintext.as_slice())
+        .map_err(|_| LLMError::InternalError("Chiffrement du trousseau impossible".to_string()))?;
+
+    let base64 = base64::engine::general_purpose::STANDARD;
+    let file = KeystoreFile {
+        version: KEYSTORE_VERSION,
+        salt: base64.encode(salt),
+        nonce: base64.encode(nonce),
+        ciphertext: base64.encode(ciphertext),
+    };
+    serde_json::to_string_pretty(&file)
+        .map_err(|e| LLMError::InternalError(format!("Trousseau: {}", e)))
+}
+
+fn decrypt(content: &str, passphrase: &Secret) -> Result<BTreeMap<String, Secret>, LLMError> {
+    let corrupted = || LLMError::InvalidConfig("Trousseau illisible ou corrompu".to_string());
+
+    let file: KeystoreFile = serde_json::from_str(content).map_err(|_| corrupted())?;
+    if file.version != KEYSTORE_VERSION {
+        return Err(LLMError::InvalidConfig(format!(
+            "Version de trousseau non supportée: {}",
+            file.version
+        )));
+    }
+
+    let base64 = base64::engine::general_purpose::STANDARD;
+    let salt = base64.decode(&file.salt).map_err(|_| corrupted())?;
+    let nonce = base64.decode(&file.nonce).map_err(|_| corrupted())?;
+    let ciphertext = base64.decode(&file.ciphertext).map_err(|_| corrupted())?;
+    if nonce.len() != 12 {
+        return Err(corrupted());
+    }
+
+    let cipher = ChaCha20Poly1305::new(&derive_key(passphrase, &salt)?);
+    let plaintext = cipher
+        .decrypt(Nonce::from_slice(&nonce), ciphertext.as_slice())
+        .map_err(|_| {
+            LLMError::InvalidConfig("Phrase de passe incorrecte ou trousseau corrompu".to_string())
+        })?;
+
+    let entries: BTreeMap<String, String> =
+        serde_json::from_slice(&plaintext).map_err(|_| corrupted())?;
+    Ok(entries.into_iter().map(|(name, value)| (name, Secret(value))).collect())
+}
+
+fn keystore_error(error: std::io::Error) -> LLMError {
+    LLMError::InternalError(format!("Trousseau: {}", error))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sensitive_names_match_on_the_last_segment() {
+        for name in [
+            "Authorization",
+            "proxy-authorization",
+            "api-key",
+            "x-api-key",
+            "X-Goog-Api-Key",
+            "Ocp-Apim-Subscription-Key",
+            "apikey",
+            "access_token",
+            "x-auth-token",
+            "client_secret",
+            "db_password",
+        ] {
+            assert!(is_sensitive_header(name), "{}", name);
+        }
+        for name in [
+            "max_tokens",
+            "api_version",
+            "anthropic-version",
+            "content-type",
+            "keys_count",
+            "tokenizer",
+            "secretariat",
+        ] {
+            assert!(!is_sensitive_header(name), "{}", name);
+        }
+    }
+
+    #[test]
+    fn secret_is_masked_by_debug_and_serialization() {
+        let secret = Secret::new("sk-live-123");
+        assert_eq!(format!("{:?}", secret), "Secret(***)");
+        assert_eq!(serde_json::to_string(&secret).unwrap(), "\"***\"");
+
+        let source = SecretSource::from("sk-live-123");
+        assert!(!format!("{:?}", source).contains("sk-live"));
+        let table = toml::to_string(&BTreeMap::from([("api_key", &source)])).unwrap();
+        assert_eq!(table.trim(), "api_key = \"***\"");
+        assert_eq!(secret.expose(), "sk-live-123");
+
+        let parsed: Secret = serde_json::from_str("\"sk-live-123\"").unwrap();
+        assert_eq!(parsed, secret);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn secret_file_must_be_private() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("anthropic.key");
+        std::fs::write(&path, "sk-file\n").unwrap();
+
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o644)).unwrap();
+        let Err(LLMError::InvalidConfig(message)) = read_secret_file(&path) else {
+            panic!("un fichier lisible par tous devrait être refusé");
+        };
+        assert!(message.contains("644"), "{}", message);
+
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o600)).unwrap();
+        let source = SecretSource::File { file: path };
+        assert_eq!(source.resolve().unwrap().expose(), "sk-file");
+    }
+
+    #[test]
+    fn keystore_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("data").join(KEYSTORE_FILE);
+
+        let mut keystore = Keystore::open(&path, Secret::new("phrase de passe")).unwrap();
+        keystore.set("anthropic", Secret::new("sk-ant"));
+        keystore.set("openai", Secret::new("sk-oai"));
+        keystore.save().unwrap();
+
+        let content = std::fs::read_to_string(&path).unwrap();
+        assert!(!content.contains("sk-ant") && !content.contains("anthropic"));
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+
+        let mut reopened = Keystore::open(&path, Secret::new("phrase de passe")).unwrap();
+        assert_eq!(reopened.names().collect::<Vec<_>>(), vec!["anthropic", "openai"]);
+        assert_eq!(reopened.get("anthropic").unwrap().expose(), "sk-ant");
+        assert!(reopened.remove("openai"));
+        assert!(!reopened.remove("openai"));
+
+        match Keystore::open(&path, Secret::new("mauvaise phrase")) {
+            Err(LLMError::InvalidConfig(message)) => {
+                assert!(message.contains("Phrase de passe incorrecte"), "{}", message)
+            }
+            Err(e) => panic!("erreur inattendue: {:?}", e),
+            Ok(_) => panic!("une mauvaise phrase de passe devrait être refusée"),
+        }
+    }
+
+    #[test]
+    fn corrupted_keystore_is_rejected() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(KEYSTORE_FILE);
+        std::fs::write(&path, "pas du json").unwrap();
+        assert!(matches!(
+            Keystore::open(&path, Secret::new("phrase")),
+            Err(LLMError::InvalidConfig(_))
+        ));
+
+        let missing = Keystore::open(&dir.path().join("absent.enc"), Secret::new("phrase"));
+        assert_eq!(missing.unwrap().names().count(), 0);
+    }
+}
@@ -0,0 +1,686 @@
+// Moteur de streaming partagé par les providers LLM (Server-Sent Events et NDJSON)
+
+use futures::{Stream, StreamExt};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use tokio::sync::oneshot;
+
+use super::providers::{map_reqwest_error, usage_metadata};
+use super::{FinishReason, LLMError, LLMResponse, LLMStream, LLMStreamChunk, TokenUsage, ToolCall};
+
+/// Sentinelle de fin de flux utilisée par les API compatibles OpenAI
+const DONE_SENTINEL: &str = "[DONE]";
+
+
+/// Format de transport d'un flux de réponse
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum StreamFormat {
+    /// Server-Sent Events (`data: {...}`)
+    Sse,
+    /// Un document JSON par ligne
+    Ndjson,
+}
+
+
+/// Événement brut extrait du flux
+#[derive(Debug, Clone, PartialEq)]
+pub struct StreamEvent {
+    /// Nom de l'événement (champ SSE `event:`), absent en NDJSON
+    pub event: Option<String>,
+    /// Données de l'événement ; les champs `data:` multiples sont joints par `\n`
+    pub data: String,
+}
+
+
+/// Décodeur incrémental d'un flux d'octets en `StreamEvent`.
+///
+/// Les lignes coupées entre deux paquets (y compris au milieu d'un caractère UTF-8)
+/// sont reconstituées. En SSE, un événement est émis à chaque ligne vide ; les
+/// commentaires (`: keep-alive`) et les champs `id:`/`retry:` sont ignorés.
+pub struct EventDecoder {
+    format: StreamFormat,
+    buffer: Vec<u8>,
+    event: Option<String>,
+    data: Vec<String>,
+}
+
+impl EventDecoder {
+    pub fn new(format: StreamFormat) -> Self {
+        EventDecoder {
+            format,
+            buffer: Vec::new(),
+            event: None,
+            data: Vec::new(),
+        }
+    }
+
+    /// Ajoute des octets reçus et retourne les événements complets
+    pub fn feed(&mut self, bytes: &[u8]) -> Vec<StreamEvent> {
+        self.buffer.extend_from_slice(bytes);
+
+        let mut events = Vec::new();
+        while let Some(pos) = self.buffer.iter().position(|b| *b == b'\n') {
+            let line: Vec<u8> = self.buffer.drain(..=pos).collect();
+            let line = String::from_utf8_lossy(&line);
+            if let Some(event) = self.on_line(line.trim_end_matches(['\r', '\n'])) {
+                events.push(event);
+            }
+        }
+        events
+    }
+
+    /// Termine le décodage : traite la dernière ligne non terminée et l'événement en cours
+    pub fn finish(&mut self) -> Vec<StreamEvent> {
+        let mut events = Vec::new();
+        if !self.buffer.is_empty() {
+            let line = String::from_utf8_lossy(&std::mem::take(&mut self.buffer)).to_string();
+            events.extend(self.on_line(line.trim_end_matches('\r')));
+        }
+        events.extend(self.dispatch());
+        events
+    }
+
+    fn on_line(&mut self, line: &str) -> Option<StreamEvent> {
+        match self.format {
+            StreamFormat::Ndjson => {
+                let line = line.trim();
+                (!line.is_empty()).then(|| StreamEvent {
+                    event: None,
+                    data: line.to_string(),
+                })
+            }
+            StreamFormat::Sse => {
+                if line.is_empty() {
+                    return self.dispatch();
+                }
+                if line.starts_with(':') {
+                    return None;
+                }
+
+                let (field, value) = match line.split_once(':') {
+                    Some((field, value)) => (field, value.strip_prefix(' ').unwrap_or(value)),
+                    None => (line, ""),
+                };
+                match field {
+                    "data" => self.data.push(value.to_string()),
+                    "event" => self.event = Some(value.to_string()),
+                    _ => {}
+                }
+                None
+            }
+        }
+    }
+
+    fn dispatch(&mut self) -> Option<StreamEvent> {
+        let event = self.event.take();
+        if self.data.is_empty() {
+            return None;
+        }
+
+        Some(StreamEvent {
+            event,
+            data: std::mem::take(&mut self.data).join("\n"),
+        })
+    }
+}
+
+
+/// Mise à jour produite par un `StreamHandler` pour un événement
+#[derive(Debug, Default)]
+pub struct StreamUpdate {
+    /// Texte partiel généré
+    pub delta: String,
+    /// Raison de fin, si l'événement l'indique
+    pub finish_reason: Option<FinishReason>,
+    /// Usage cumulé des tokens, si l'événement le fournit
+    pub usage: Option<TokenUsage>,
+    /// Métadonnées à reporter sur le chunk final
+    pub metadata: HashMap<String, String>,
+    /// Appels d'outils complets, à reporter sur le chunk final
+    pub tool_calls: Vec<ToolCall>,
+    /// L'événement marque la fin du flux
+    pub done: bool,
+}
+
+impl StreamUpdate {
+    /// Mise à jour ne portant que du texte
+    pub fn delta(text: impl Into<String>) -> Self {
+        StreamUpdate {
+            delta: text.into(),
+            ..Default::default()
+        }
+    }
+}
+
+/// Interprétation des événements propre à chaque provider
+pub trait StreamHandler: Send + 'static {
+    /// Traite un événement du flux
+    fn on_event(&mut self, event: &StreamEvent) -> Result<StreamUpdate, LLMError>;
+
+    /// La fin des données suffit à terminer le flux, pour les formats sans événement
+    /// de fin. Sinon, un flux interrompu avant sa fin est une erreur.
+    fn ends_at_eof(&self) -> bool {
+        false
+    }
+}
+
+
+/// Décode le corps d'une réponse HTTP en flux de `LLMStreamChunk`
+pub(crate) fn decode_response<H: StreamHandler>(
+    response: reqwest::Response,
+    format: StreamFormat,
+    handler: H,
+) -> LLMStream {
+    let bytes = response
+        .bytes_stream()
+        .map(|chunk| chunk.map_err(map_reqwest_error));
+
+    decode_stream(bytes, format, handler)
+}
+
+/// Décode un flux d'octets en flux de `LLMStreamChunk`.
+///
+/// Chaque texte partiel produit un chunk. À la fin du flux (sentinelle `[DONE]`,
+/// `StreamUpdate::done` ou fin des données après une raison de fin), un chunk final vide
+/// porte la raison de fin, l'usage (`prompt_tokens`, `completion_tokens`, `total_tokens`),
+/// les métadonnées accumulées dans `metadata` et les appels d'outils.
+///
+/// Des données qui s'arrêtent sans aucun de ces signaux (connexion coupée) terminent le
+/// flux par une `LLMError::NetworkError` : une réponse tronquée n'est jamais présentée
+/// comme complète.
+pub fn decode_stream<S, B, H>(bytes: S, format: StreamFormat, handler: H) -> LLMStream
+where
+    S: Stream<Item = Result<B, LLMError>> + Send + 'static,
+    B: AsRef<[u8]> + Send + 'static,
+    H: StreamHandler,
+{
+    let state = DecodeState {
+        bytes: Box::pin(bytes),
+        decoder: EventDecoder::new(format),
+        handler,
+        pending: VecDeque::new(),
+        finish_reason: None,
+        usage: None,
+        metadata: HashMap::new(),
+        tool_calls: Vec::new(),
+        input_done: false,
+        terminated: false,
+        finished: false,
+    };
+
+    let chunks = futures::stream::unfold(state, |mut state| async move {
+        loop {
+            if state.finished {
+                return None;
+            }
+
+            if let Some(event) = state.pending.pop_front() {
+                if state.decoder.format == StreamFormat::Sse && event.data.trim() == DONE_SENTINEL {
+                    state.end_of_input();
+                    continue;
+                }
+
+                match state.handler.on_event(&event) {
+                    Ok(update) => {
+                        if let Some(chunk) = state.apply(update) {
+                            return Some((Ok(chunk), state));
+                        }
+                    }
+                    Err(err) => {
+                        state.finished = true;
+                        return Some((Err(err), state));
+                    }
+                }
+                continue;
+            }
+
+            if state.input_done {
+                state.finished = true;
+                if !state.terminated && !state.handler.ends_at_eof() {
+                    let error = LLMError::NetworkError(
+                        "Flux interrompu avant la fin de la réponse".to_string(),
+                    );
+                    return Some((Err(error), state));
+                }
+                let chunk = state.final_chunk();
+                return Some((Ok(chunk), state));
+            }
+
+            match state.bytes.next().await {
+                Some(Ok(bytes)) => {
+                    let events = state.decoder.feed(bytes.as_ref());
+                    state.pending.extend(events);
+                }
+                Some(Err(err)) => {
+                    state.finished = true;
+                    return Some((Err(err), state));
+                }
+                None => {
+                    let events = state.decoder.finish();
+                    state.pending.extend(events);
+                    state.input_done = true;
+                }
+            }
+        }
+    });
+
+    Box::new(Box::pin(chunks))
+}
+
+struct DecodeState<S, H> {
+    bytes: std::pin::Pin<Box<S>>,
+    decoder: EventDecoder,
+    handler: H,
+    pending: VecDeque<StreamEvent>,
+    finish_reason: Option<FinishReason>,
+    usage: Option<TokenUsage>,
+    metadata: HashMap<String, String>,
+    tool_calls: Vec<ToolCall>,
+    input_done: bool,
+    /// Un signal de fin (sentinelle, `done`, raison de fin) a été reçu
+    terminated: bool,
+    finished: bool,
+}
+
+impl<S, H> DecodeState<S, H> {
+    /// Enregistre une mise à jour et retourne le chunk de texte éventuel
+    fn apply(&mut self, update: StreamUpdate) -> Option<LLMStreamChunk> {
+        if update.finish_reason.is_some() {
+            self.finish_reason = update.finish_reason;
+            self.terminated = true;
+        }
+        if update.usage.is_some() {
+            self.usage = update.usage;
+        }
+        self.metadata.extend(update.metadata);
+        self.tool_calls.extend(update.tool_calls);
+        if update.done {
+            self.end_of_input();
+        }
+
+        (!update.delta.is_empty()).then(|| LLMStreamChunk {
+            delta: update.delta,
+            finish_reason: None,
+            metadata: None,
+            tool_calls: Vec::new(),
+        })
+    }
+
+    /// Ignore les événements restants : le chunk final sera émis au prochain tour
+    fn end_of_input(&mut self) {
+        self.pending.clear();
+        self.input_done = true;
+        self.terminated = true;
+    }
+
+    fn final_chunk(&mut self) -> LLMStreamChunk {
+        let mut metadata = self.usage.as_ref().map(usage_metadata).unwrap_or_default();
+        metadata.extend(std::mem::take(&mut self.metadata));
+
+        LLMStreamChunk {
+            delta: String::new(),
+            finish_reason: Some(self.finish_reason.take().unwrap_or(FinishReason::Stop)),
+            metadata: Some(metadata),
+            tool_calls: std::mem::take(&mut self.tool_calls),
+        }
+    }
+}
+
+
+/// Accumule les chunks d'un flux pour reconstituer une `LLMResponse`.
+///
+/// Les clés d'usage (`prompt_tokens`, `completion_tokens`, `total_tokens`) des
+/// métadonnées sont converties en `TokenUsage` ; les autres sont conservées.
+#[derive(Debug, Default)]
+pub struct StreamAccumulator {
+    content: String,
+    finish_reason: Option<FinishReason>,
+    usage: TokenUsage,
+    metadata: HashMap<String, String>,
+    tool_calls: Vec<ToolCall>,
+}
+
+impl StreamAccumulator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Intègre un chunk reçu
+    pub fn push(&mut self, chunk: &LLMStreamChunk) {
+        self.content.push_str(&chunk.delta);
+        if chunk.finish_reason.is_some() {
+            self.finish_reason = chunk.finish_reason.clone();
+        }
+        self.tool_calls.extend(chunk.tool_calls.iter().cloned());
+
+        for (key, value) in chunk.metadata.iter().flatten() {
+            let count = value.parse::<u32>().ok();
+            match (key.as_str(), count) {
+                ("prompt_tokens", Some(count)) => self.usage.prompt_tokens = count,
+                ("completion_tokens", Some(count)) => self.usage.completion_tokens = count,
+                ("total_tokens", Some(count)) => self.usage.total_tokens = count,
+                _ => {
+                    self.metadata.insert(key.clone(), value.clone());
+                }
+            }
+        }
+    }
+
+    /// Texte accumulé jusqu'ici
+    pub fn content(&self) -> &str {
+        &self.content
+    }
+
+    /// Construit la réponse complète
+    pub fn into_response(self, model: impl Into<String>) -> LLMResponse {
+        let mut usage = self.usage;
+        usage.total_tokens = usage
+            .total_tokens
+            .max(usage.prompt_tokens + usage.completion_tokens);
+
+        LLMResponse {
+            content: self.content,
+            finish_reason: self.finish_reason.unwrap_or(FinishReason::Stop),
+            usage,
+            model: model.into(),
+            metadata: (!self.metadata.is_empty()).then_some(self.metadata),
+            tool_calls: self.tool_calls,
+        }
+    }
+}
+
+
+/// Consomme entièrement un flux et retourne la réponse complète
+pub async fn collect_response(
+    mut stream: LLMStream,
+    model: impl Into<String>,
+) -> Result<LLMResponse, LLMError> {
+    let mut accumulator = StreamAccumulator::new();
+    while let Some(chunk) = stream.next().await {
+        accumulator.push(&chunk?);
+    }
+    Ok(accumulator.into_response(model))
+}
+
+
+/// Flux d'un seul chunk final portant une réponse complète (usage compris), pour servir
+/// en streaming une réponse obtenue autrement (cache, middleware)
+pub fn response_stream(response: LLMResponse) -> LLMStream {
+    let mut metadata = response.metadata.unwrap_or_default();
+    metadata.extend(usage_metadata(&response.usage));
+
+    let chunk = LLMStreamChunk {
+        delta: response.content,
+        finish_reason: Some(response.finish_reason),
+        metadata: Some(metadata),
+        tool_calls: response.tool_calls,
+    };
+    Box::new(futures::stream::iter(vec![Ok(chunk)]))
+}
+
+
+/// Réponse complète d'un flux dupliqué par `tee_response`, disponible à sa fin
+pub struct ResponseCollector {
+    receiver: oneshot::Receiver<Result<LLMResponse, LLMError>>,
+}
+
+impl ResponseCollector {
+    /// Attend la fin du flux et retourne la réponse (ou l'erreur du flux)
+    pub async fn response(self) -> Result<LLMResponse, LLMError> {
+        self.receiver.await.unwrap_or_else(|_| {
+            Err(LLMError::InternalError(
+                "Le flux a été abandonné avant sa fin".to_string(),
+            ))
+        })
+    }
+}
+
+/// Duplique un flux : les chunks sont transmis tels quels à l'appelant (pour
+/// l'affichage) et accumulés pour produire la `LLMResponse` finale.
+pub fn tee_response(stream: LLMStream, model: impl Into<String>) -> (LLMStream, ResponseCollector) {
+    let (sender, receiver) = oneshot::channel();
+    let state = (stream, StreamAccumulator::new(), Some(sender), model.into());
+
+    let chunks = futures::stream::unfold(
+        state,
+        |(mut stream, mut accumulator, mut sender, model)| async move {
+            // Le flux s'arrête après la première erreur
+            sender.as_ref()?;
+
+            match stream.next().await {
+                Some(Ok(chunk)) => {
+                    accumulator.push(&chunk);
+                    Some((Ok(chunk), (stream, accumulator, sender, model)))
+                }
+                Some(Err(err)) => {
+                    if let Some(sender) = sender.take() {
+                        let _ = sender.send(Err(err.clone()));
+                    }
+                    Some((Err(err), (stream, accumulator, sender, model)))
+                }
+                None => {
+                    if let Some(sender) = sender.take() {
+                        let _ = sender.send(Ok(accumulator.into_response(model)));
+                    }
+                    None
+                }
+            }
+        },
+    );
+
+    (Box::new(Box::pin(chunks)), ResponseCollector { receiver })
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Gestionnaire de test : `{"text": ..., "stop": bool}` par événement
+    struct TextHandler;
+
+    impl StreamHandler for TextHandler {
+        fn on_event(&mut self, event: &StreamEvent) -> Result<StreamUpdate, LLMError> {
+            let value: serde_json::Value = serde_json::from_str(&event.data)
+                .map_err(|e| LLMError::ParseError(e.to_string()))?;
+            let stop = value["stop"].as_bool().unwrap_or(false);
+            Ok(StreamUpdate {
+                delta: value["text"].as_str().unwrap_or_default().to_string(),
+                finish_reason: stop.then_some(FinishReason::Stop),
+                ..Default::default()
+            })
+        }
+    }
+
+    fn packets(parts: &[&[u8]]) -> impl Stream<Item = Result<Vec<u8>, LLMError>> + Send {
+        let parts: Vec<_> = parts.iter().map(|part| Ok(part.to_vec())).collect();
+        futures::stream::iter(parts)
+    }
+
+    async fn drain(stream: LLMStream) -> Vec<Result<LLMStreamChunk, LLMError>> {
+        stream.collect().await
+    }
+
+    #[test]
+    fn sse_lines_split_across_packets() {
+        let mut decoder = EventDecoder::new(StreamFormat::Sse);
+        let text = "data: {\"text\":\"é\"}\n\n".as_bytes();
+        // Coupure au milieu du caractère "é" (2 octets)
+        let split = text.iter().position(|b| *b == 0xC3).unwrap() + 1;
+
+        assert!(decoder.feed(&text[..split]).is_empty());
+        let events = decoder.feed(&text[split..]);
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].data, "{\"text\":\"é\"}");
+    }
+
+    #[test]
+    fn sse_comments_fields_and_multiline_data() {
+        let mut decoder = EventDecoder::new(StreamFormat::Sse);
+        let events = decoder.feed(
+            b": keep-alive\r\nid: 7\r\nevent: delta\r\ndata: ligne 1\r\ndata: ligne 2\r\n\r\n",
+        );
+        assert_eq!(
+            events,
+            vec![StreamEvent {
+                event: Some("delta".to_string()),
+                data: "ligne 1\nligne 2".to_string(),
+            }]
+        );
+    }
+
+    #[test]
+    fn ndjson_flushes_last_unterminated_line() {
+        let mut decoder = EventDecoder::new(StreamFormat::Ndjson);
+        assert_eq!(decoder.feed(b"{\"a\":1}\n\n{\"b\"").len(), 1);
+        let events = decoder.finish();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].data, "{\"b\"");
+    }
+
+    #[tokio::test]
+    async fn done_sentinel_emits_final_chunk() {
+        let bytes = packets(&[
+            b"data: {\"text\":\"Bon\"}\n\n",
+            b"data: {\"text\":\"jour\"}\n\ndata: [DONE]\n\n",
+            b"data: {\"text\":\"ignor\xc3\xa9\"}\n\n",
+        ]);
+        let chunks = drain(decode_stream(bytes, StreamFormat::Sse, TextHandler)).await;
+
+        let chunks: Vec<_> = chunks.into_iter().map(Result::unwrap).collect();
+        let deltas: Vec<_> = chunks.iter().map(|chunk| chunk.delta.as_str()).collect();
+        assert_eq!(deltas, ["Bon", "jour", ""]);
+        assert_eq!(chunks[2].finish_reason, Some(FinishReason::Stop));
+    }
+
+    #[tokio::test]
+    async fn finish_reason_then_eof_ends_normally() {
+        let bytes = packets(&[b"{\"text\":\"ok\",\"stop\":true}\n"]);
+        let chunks = drain(decode_stream(bytes, StreamFormat::Ndjson, TextHandler)).await;
+
+        assert_eq!(chunks.len(), 2);
+        assert!(chunks.iter().all(Result::is_ok));
+    }
+
+    #[tokio::test]
+    async fn truncated_stream_is_an_error() {
+        let bytes = packets(&[b"data: {\"text\":\"Bon\"}\n\n"]);
+        let chunks = drain(decode_stream(bytes, StreamFormat::Sse, TextHandler)).await;
+
+        assert_eq!(chunks.len(), 2);
+        assert_eq!(chunks[0].as_ref().unwrap().delta, "Bon");
+        assert!(matches!(chunks[1], Err(LLMError::NetworkError(_))));
+        let bytes = packets(&[b"data: {\"text\":\"Bon\"}\n\n"]);
+        let stream = decode_stream(bytes, StreamFormat::Sse, TextHandler);
+        let response = collect_response(stream, "m").await;
+        assert!(response.is_err());
+    }
+
+    #[tokio::test]
+    async fn handler_errors_stop_the_stream() {
+        let bytes = packets(&[b"data: pas du json\n\ndata: {\"text\":\"x\"}\n\n"]);
+        let chunks = drain(decode_stream(bytes, StreamFormat::Sse, TextHandler)).await;
+
+        assert_eq!(chunks.len(), 1);
+        assert!(matches!(chunks[0], Err(LLMError::ParseError(_))));
+    }
+
+    fn chunk(delta: &str, finish_reason: Option<FinishReason>) -> LLMStreamChunk {
+        LLMStreamChunk {
+            delta: delta.to_string(),
+            finish_reason,
+            metadata: None,
+            tool_calls: Vec::new(),
+        }
+    }
+
+    fn chunks(items: Vec<Result<LLMStreamChunk, LLMError>>) -> LLMStream {
+        Box::new(futures::stream::iter(items))
+    }
+
+    #[test]
+    fn accumulator_splits_usage_from_metadata() {
+        let mut last = chunk("", Some(FinishReason::Length));
+        last.metadata = Some(HashMap::from([
+            ("prompt_tokens".to_string(), "12".to_string()),
+            ("completion_tokens".to_string(), "5".to_string()),
+            ("id".to_string(), "msg_1".to_string()),
+        ]));
+
+        let mut accumulator = StreamAccumulator::new();
+        accumulator.push(&chunk("Bon", None));
+        accumulator.push(&chunk("jour", None));
+        assert_eq!(accumulator.content(), "Bonjour");
+        accumulator.push(&last);
+
+        let response = accumulator.into_response("m");
+        assert_eq!(response.content, "Bonjour");
+        assert_eq!(response.finish_reason, FinishReason::Length);
+        assert_eq!(response.usage.prompt_tokens, 12);
+        assert_eq!(response.usage.completion_tokens, 5);
+        // Total recalculé quand le provider ne le fournit pas
+        assert_eq!(response.usage.total_tokens, 17);
+        assert_eq!(
+            response.metadata,
+            Some(HashMap::from([("id".to_string(), "msg_1".to_string())]))
+        );
+    }
+
+    #[tokio::test]
+    async fn response_stream_round_trips_through_collect() {
+        let mut response = StreamAccumulator::new().into_response("m");
+        response.content = "Salut".to_string();
+        response.finish_reason = FinishReason::ContentFilter;
+        response.usage = TokenUsage {
+            prompt_tokens: 3,
+            completion_tokens: 2,
+            total_tokens: 5,
+        };
+
+        let collected = collect_response(response_stream(response), "m").await.unwrap();
+        assert_eq!(collected.content, "Salut");
+        assert_eq!(collected.finish_reason, FinishReason::ContentFilter);
+        assert_eq!(collected.usage.total_tokens, 5);
+        assert_eq!(collected.metadata, None);
+    }
+
+    #[tokio::test]
+    async fn tee_forwards_chunks_and_collects_response() {
+        let stream = chunks(vec![Ok(chunk("fl", None)), Ok(chunk("ux", Some(FinishReason::Stop)))]);
+        let (stream, collector) = tee_response(stream, "m");
+
+        let deltas: Vec<_> = drain(stream)
+            .await
+            .into_iter()
+            .map(|chunk| chunk.unwrap().delta)
+            .collect();
+        assert_eq!(deltas, ["fl", "ux"]);
+
+        let response = collector.response().await.unwrap();
+        assert_eq!(response.content, "flux");
+        assert_eq!(response.model, "m");
+    }
+
+    #[tokio::test]
+    async fn tee_stops_after_error() {
+        let error = LLMError::NetworkError("coupure".to_string());
+        let stream = chunks(vec![Ok(chunk("a", None)), Err(error), Ok(chunk("b", None))]);
+        let (stream, collector) = tee_response(stream, "m");
+
+        let items = drain(stream).await;
+        assert_eq!(items.len(), 2);
+        assert!(items[1].is_err());
+        assert!(matches!(collector.response().await, Err(LLMError::NetworkError(_))));
+    }
+
+    #[tokio::test]
+    async fn tee_dropped_before_end() {
+        let stream = chunks(vec![Ok(chunk("a", None)), Ok(chunk("b", None))]);
+        let (mut stream, collector) = tee_response(stream, "m");
+
+        stream.next().await.unwrap().unwrap();
+        drop(stream);
+        assert!(matches!(collector.response().await, Err(LLMError::InternalError(_))));
+    }
+}
@@ -0,0 +1,144 @@
+// Constructeurs partagés par les tests unitaires du module llm
+
+use async_trait::async_trait;
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use super::streaming::response_stream;
+use super::{
+    DeploymentMode, FinishReason, LLMError, LLMMessage, LLMProvider, LLMProviderConfig,
+    LLMProviderType, LLMRequest, LLMResponse, LLMStream, ModelParameters, Role, TokenUsage,
+};
+
+/// Configuration minimale d'un provider distant pointant sur `base_url`
+pub(crate) fn config(
+    provider_type: LLMProviderType,
+    model: &str,
+    base_url: &str,
+) -> LLMProviderConfig {
+    LLMProviderConfig {
+        provider_type,
+        model_name: model.to_string(),
+        deployment: DeploymentMode::Remote,
+        base_url: Some(base_url.to_string()),
+        api_key: Some("test-key".into()),
+        headers: HashMap::new(),
+        parameters: ModelParameters::default(),
+        timeout_seconds: 5,
+        max_retries: 0,
+        options: HashMap::new(),
+        template: None,
+    }
+}
+
+pub(crate) fn message(role: Role, text: &str) -> LLMMessage {
+    LLMMessage {
+        role,
+        content: text.into(),
+        metadata: None,
+        tool_calls: Vec::new(),
+        tool_call_id: None,
+    }
+}
+
+pub(crate) fn request(messages: Vec<LLMMessage>) -> LLMRequest {
+    LLMRequest {
+        messages,
+        parameters: None,
+        stream: false,
+        fim: None,
+        tools: Vec::new(),
+        tool_choice: None,
+        cache: false,
+    }
+}
+
+/// Requête d'un seul message utilisateur
+pub(crate) fn user_request(text: &str) -> LLMRequest {
+    request(vec![message(Role::User, text)])
+}
+
+/// Réponse complète terminée par `Stop`
+pub(crate) fn response(content: &str) -> LLMResponse {
+    LLMResponse {
+        content: content.to_string(),
+        finish_reason: FinishReason::Stop,
+        usage: TokenUsage {
+            prompt_tokens: 10,
+            completion_tokens: 5,
+            total_tokens: 15,
+        },
+        model: "test-model".to_string(),
+        metadata: None,
+        tool_calls: Vec::new(),
+    }
+}
+
+
+/// Provider de test qui rejoue une suite de résultats et compte ses appels.
+///
+/// Une fois la suite épuisée, il répond "ok".
+pub(crate) struct ScriptedProvider {
+    name: String,
+    results: Mutex<VecDeque<Result<LLMResponse, LLMError>>>,
+    /// Nombre d'appels à `generate`/`generate_stream`
+    pub(crate) calls: Arc<AtomicUsize>,
+    /// Requêtes reçues, dans l'ordre
+    pub(crate) requests: Arc<Mutex<Vec<LLMRequest>>>,
+    /// Résultat de `health_check`
+    pub(crate) health: Result<(), LLMError>,
+    /// Attente avant chaque réponse
+    pub(crate) delay: Option<Duration>,
+}
+
+impl ScriptedProvider {
+    pub(crate) fn new(name: &str, results: Vec<Result<LLMResponse, LLMError>>) -> Self {
+        ScriptedProvider {
+            name: name.to_string(),
+            results: Mutex::new(results.into()),
+            calls: Arc::new(AtomicUsize::new(0)),
+            requests: Arc::default(),
+            health: Ok(()),
+            delay: None,
+        }
+    }
+
+    async fn next(&self, request: LLMRequest) -> Result<LLMResponse, LLMError> {
+        self.calls.fetch_add(1, Ordering::SeqCst);
+        self.requests.lock().unwrap().push(request);
+        if let Some(delay) = self.delay {
+            tokio::time::sleep(delay).await;
+        }
+        let next = self.results.lock().unwrap().pop_front();
+        next.unwrap_or_else(|| Ok(response("ok")))
+    }
+}
+
+#[async_trait]
+impl LLMProvider for ScriptedProvider {
+    async fn generate(&self, request: LLMRequest) -> Result<LLMResponse, LLMError> {
+        self.next(request).await
+    }
+
+    async fn generate_stream(&self, request: LLMRequest) -> Result<LLMStream, LLMError> {
+        self.next(request).await.map(response_stream)
+    }
+
+    fn count_tokens(&self, text: &str) -> Result<u32, LLMError> {
+        Ok(text.split_whitespace().count() as u32)
+    }
+
+    fn provider_name(&self) -> &str {
+        &self.name
+    }
+
+    fn model_name(&self) -> &str {
+        "test-model"
+    }
+
+    async fn health_check(&self) -> Result<(), LLMError> {
+        self.health.clone()
+    }
+}
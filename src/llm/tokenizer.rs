@@ -0,0 +1,342 @@
+// Comptage des tokens par modèle (BPE tiktoken, sinon estimation calibrée)
+
+use std::convert::Infallible;
+use std::sync::{Arc, OnceLock};
+use tiktoken_rs::CoreBPE;
+
+use super::{ContentPart, LLMMessage, LLMProviderType, LLMRequest};
+
+/// Tokens ajoutés par le gabarit de conversation pour chaque message (rôle, séparateurs)
+const TOKENS_PER_MESSAGE: u32 = 3;
+
+/// Tokens ajoutés en fin de conversation pour amorcer la réponse de l'assistant
+const REPLY_PRIMING_TOKENS: u32 = 3;
+
+/// Coût forfaitaire d'une image ou d'un fichier joint (ordre de grandeur d'une image ~1 Mpx)
+const ATTACHMENT_TOKENS: u32 = 1_000;
+
+
+/// Méthode de comptage des tokens
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TokenizerKind {
+    /// BPE des modèles GPT-4o, GPT-4.1, o1/o3/o4 et suivants
+    O200kBase,
+    /// BPE des modèles GPT-4 et GPT-3.5
+    Cl100kBase,
+    /// BPE des anciens modèles Codex / Davinci
+    P50kBase,
+    /// Estimation à partir du nombre de caractères, calibrée par famille de modèles
+    Heuristic { chars_per_token: f32 },
+}
+
+/// Compteur de tokens pour un modèle donné
+#[derive(Clone)]
+pub struct Tokenizer {
+    kind: TokenizerKind,
+    bpe: Option<Arc<CoreBPE>>,
+}
+
+impl Tokenizer {
+    /// Construit le compteur ; si le BPE ne peut pas être chargé, on se replie sur l'estimation
+    pub fn new(kind: TokenizerKind) -> Self {
+        let bpe = match kind {
+            TokenizerKind::O200kBase => load_bpe(&O200K, tiktoken_rs::o200k_base),
+            TokenizerKind::Cl100kBase => load_bpe(&CL100K, tiktoken_rs::cl100k_base),
+            TokenizerKind::P50kBase => load_bpe(&P50K, tiktoken_rs::p50k_base),
+            TokenizerKind::Heuristic { .. } => None,
+        };
+
+        match (kind, &bpe) {
+            (TokenizerKind::Heuristic { .. }, _) | (_, Some(_)) => Tokenizer { kind, bpe },
+            (_, None) => Tokenizer::new(TokenizerKind::Heuristic {
+                chars_per_token: DEFAULT_CHARS_PER_TOKEN,
+            }),
+        }
+    }
+
+    pub fn kind(&self) -> TokenizerKind {
+        self.kind
+    }
+
+    /// Le comptage est exact (BPE du modèle) et non estimé
+    pub fn is_exact(&self) -> bool {
+        self.bpe.is_some()
+    }
+
+    /// Nombre de tokens d'un texte
+    pub fn count(&self, text: &str) -> u32 {
+        match (&self.bpe, self.kind) {
+            (Some(bpe), _) => bpe.encode_with_special_tokens(text).len() as u32,
+            (None, TokenizerKind::Heuristic { chars_per_token }) => {
+                (text.chars().count() as f32 / chars_per_token).ceil() as u32
+            }
+            (None, _) => (text.chars().count() as f32 / DEFAULT_CHARS_PER_TOKEN).ceil() as u32,
+        }
+    }
+
+    /// Nombre de tokens d'un message, gabarit compris
+    pub fn count_message(&self, message: &LLMMessage) -> u32 {
+        let count = |text: &str| Ok::<_, Infallible>(self.count(text));
+        message_tokens(message, count).unwrap_or_else(|never| match never {})
+    }
+
+    /// Nombre de tokens d'une conversation complète
+    pub fn count_messages(&self, messages: &[LLMMessage]) -> u32 {
+        let count = |text: &str| Ok::<_, Infallible>(self.count(text));
+        conversation_tokens(messages, count).unwrap_or_else(|never| match never {})
+    }
+
+    /// Nombre de tokens du prompt d'une requête : messages, définitions d'outils
+    /// et code "fill-in-the-middle"
+    pub fn count_request(&self, request: &LLMRequest) -> u32 {
+        let mut tokens = self.count_messages(&request.messages);
+        for tool in &request.tools {
+            tokens += self.count(&tool.name)
+                + self.count(&tool.description)
+                + self.count(&tool.parameters.to_string());
+        }
+        if let Some(fim) = &request.fim {
+            tokens += self.count(&fim.prefix);
+            tokens += fim.suffix.as_deref().map_or(0, |suffix| self.count(suffix));
+        }
+        tokens
+    }
+}
+
+
+/// Tokens d'un message, gabarit compris, le texte étant compté par `count`
+fn message_tokens<E>(
+    message: &LLMMessage,
+    mut count: impl FnMut(&str) -> Result<u32, E>,
+) -> Result<u32, E> {
+    let mut tokens = TOKENS_PER_MESSAGE;
+    for part in message.content.parts() {
+        tokens += match part {
+            ContentPart::Text { text } => count(&text)?,
+            ContentPart::Image { .. } | ContentPart::File { .. } => ATTACHMENT_TOKENS,
+        };
+    }
+    for call in &message.tool_calls {
+        tokens += count(&call.name)? + count(&call.arguments.to_string())?;
+    }
+    if let Some(id) = &message.tool_call_id {
+        tokens += count(id)?;
+    }
+    Ok(tokens)
+}
+
+/// Tokens d'une conversation, le texte étant compté par `count` (ex: le
+/// `count_tokens` d'un provider)
+pub(crate) fn conversation_tokens<E>(
+    messages: &[LLMMessage],
+    mut count: impl FnMut(&str) -> Result<u32, E>,
+) -> Result<u32, E> {
+    let mut tokens = REPLY_PRIMING_TOKENS;
+    for message in messages {
+        tokens += message_tokens(message, &mut count)?;
+    }
+    Ok(tokens)
+}
+
+
+/// Choix du tokenizer selon le provider et le nom du modèle.
+///
+/// Les règles sont des préfixes de nom de modèle, évaluées dans l'ordre ; celles
+/// ajoutées par `register` sont prioritaires sur les règles par défaut. Un registre
+/// personnalisé s'installe dans le catalogue (`ModelCatalog::set_tokenizers`) pour
+/// s'appliquer à la validation des requêtes et aux budgets.
+#[derive(Debug, Clone)]
+pub struct TokenizerRegistry {
+    rules: Vec<(String, TokenizerKind)>,
+}
+
+impl Default for TokenizerRegistry {
+    fn default() -> Self {
+        let rules = [
+            ("gpt-4o", TokenizerKind::O200kBase),
+            ("gpt-4.1", TokenizerKind::O200kBase),
+            ("gpt-4.5", TokenizerKind::O200kBase),
+            ("gpt-5", TokenizerKind::O200kBase),
+            ("chatgpt-4o", TokenizerKind::O200kBase),
+            ("o1", TokenizerKind::O200kBase),
+            ("o3", TokenizerKind::O200kBase),
+            ("o4", TokenizerKind::O200kBase),
+            ("gpt-4", TokenizerKind::Cl100kBase),
+            ("gpt-35", TokenizerKind::Cl100kBase),
+            ("gpt-3.5", TokenizerKind::Cl100kBase),
+            ("text-embedding-", TokenizerKind::Cl100kBase),
+            ("text-davinci", TokenizerKind::P50kBase),
+            ("code-davinci", TokenizerKind::P50kBase),
+        ];
+
+        TokenizerRegistry {
+            rules: rules
+                .into_iter()
+                .map(|(prefix, kind)| (prefix.to_string(), kind))
+                .collect(),
+        }
+    }
+}
+
+impl TokenizerRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Associe un préfixe de nom de modèle à un tokenizer (prioritaire sur les règles existantes)
+    pub fn register(&mut self, model_prefix: impl Into<String>, kind: TokenizerKind) {
+        self.rules.insert(0, (model_prefix.into(), kind));
+    }
+
+    /// Tokenizer à utiliser pour un modèle.
+    ///
+    /// Sans règle correspondante, l'estimation est calibrée sur la famille du modèle
+    /// (les tokenizers SentencePiece de Llama/Mistral découpent plus finement que BPE).
+    pub fn resolve(&self, provider_type: &LLMProviderType, model_name: &str) -> TokenizerKind {
+        let model = model_name.to_ascii_lowercase();
+        // Les noms Ollama / Gemini peuvent porter un espace de noms (`models/...`, `library/...`)
+        let model = model.rsplit('/').next().unwrap_or(&model);
+
+        let rule = self
+            .rules
+            .iter()
+            .find(|(prefix, _)| model.starts_with(prefix.as_str()));
+        if let Some((_, kind)) = rule {
+            return *kind;
+        }
+
+        // Déploiements Azure au nom libre : on suppose un modèle GPT récent
+        if matches!(provider_type, LLMProviderType::OpenAI | LLMProviderType::AzureOpenAI) {
+            return TokenizerKind::O200kBase;
+        }
+
+        let chars_per_token = match provider_type {
+            LLMProviderType::Claude => 3.5,
+            LLMProviderType::Gemini => 4.0,
+            LLMProviderType::Mistral => 3.3,
+            _ if ["llama", "mistral", "codestral"].iter().any(|family| model.contains(family)) => 3.3,
+            _ => DEFAULT_CHARS_PER_TOKEN,
+        };
+        TokenizerKind::Heuristic { chars_per_token }
+    }
+
+    pub fn tokenizer(&self, provider_type: &LLMProviderType, model_name: &str) -> Tokenizer {
+        Tokenizer::new(self.resolve(provider_type, model_name))
+    }
+}
+
+/// Tokenizer d'un modèle selon les règles par défaut (utilisé par le `count_tokens`
+/// des providers)
+pub fn tokenizer_for(provider_type: &LLMProviderType, model_name: &str) -> Tokenizer {
+    static REGISTRY: OnceLock<TokenizerRegistry> = OnceLock::new();
+    REGISTRY
+        .get_or_init(TokenizerRegistry::default)
+        .tokenizer(provider_type, model_name)
+}
+
+
+/// Nombre moyen de caractères par token pour un modèle inconnu (texte et code mêlés)
+const DEFAULT_CHARS_PER_TOKEN: f32 = 3.7;
+
+// Les tables BPE sont chargées une seule fois, à la première utilisation
+static O200K: OnceLock<Option<Arc<CoreBPE>>> = OnceLock::new();
+static CL100K: OnceLock<Option<Arc<CoreBPE>>> = OnceLock::new();
+static P50K: OnceLock<Option<Arc<CoreBPE>>> = OnceLock::new();
+
+fn load_bpe<E: std::fmt::Display>(
+    cell: &'static OnceLock<Option<Arc<CoreBPE>>>,
+    load: fn() -> Result<CoreBPE, E>,
+) -> Option<Arc<CoreBPE>> {
+    cell.get_or_init(|| match load() {
+        Ok(bpe) => Some(Arc::new(bpe)),
+        Err(e) => {
+            tracing::warn!("Chargement du tokenizer impossible, estimation utilisée: {}", e);
+            None
+        }
+    })
+    .clone()
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::test_support::{message, user_request};
+    use crate::llm::Role;
+
+    #[test]
+    fn bpe_counts_are_stable() {
+        let cl100k = Tokenizer::new(TokenizerKind::Cl100kBase);
+        let o200k = Tokenizer::new(TokenizerKind::O200kBase);
+        assert!(cl100k.is_exact() && o200k.is_exact());
+
+        assert_eq!(cl100k.count("hello world"), 2);
+        assert_eq!(cl100k.count("tiktoken is great!"), 6);
+        assert_eq!(o200k.count("hello world"), 2);
+        assert_eq!(o200k.count("tiktoken is great!"), 6);
+        // Le vocabulaire o200k découpe moins finement le français
+        let french = "Le gestionnaire de fallback essaie chaque fournisseur configuré.";
+        assert_eq!(cl100k.count(french), 13);
+        assert_eq!(o200k.count(french), 12);
+        assert_eq!(cl100k.count(""), 0);
+    }
+
+    #[test]
+    fn heuristic_rounds_up() {
+        let tokenizer = Tokenizer::new(TokenizerKind::Heuristic {
+            chars_per_token: 4.0,
+        });
+        assert!(!tokenizer.is_exact());
+        assert_eq!(tokenizer.count("abcd"), 1);
+        assert_eq!(tokenizer.count("abcde"), 2);
+    }
+
+    #[test]
+    fn conversation_overhead() {
+        let tokenizer = Tokenizer::new(TokenizerKind::Cl100kBase);
+        assert_eq!(tokenizer.count_messages(&[]), REPLY_PRIMING_TOKENS);
+        assert_eq!(tokenizer.count_message(&message(Role::User, "")), TOKENS_PER_MESSAGE);
+
+        let messages = [
+            message(Role::System, "hello world"),
+            message(Role::User, "tiktoken is great!"),
+        ];
+        assert_eq!(tokenizer.count_messages(&messages), 3 + (3 + 2) + (3 + 6));
+        assert_eq!(tokenizer.count_request(&user_request("hello world")), 3 + 3 + 2);
+    }
+
+    #[test]
+    fn registry_resolution() {
+        let registry = TokenizerRegistry::default();
+        let resolve = |provider: LLMProviderType, model: &str| registry.resolve(&provider, model);
+
+        assert_eq!(resolve(LLMProviderType::OpenAI, "gpt-4o-mini"), TokenizerKind::O200kBase);
+        assert_eq!(resolve(LLMProviderType::OpenAI, "GPT-4-turbo"), TokenizerKind::Cl100kBase);
+        assert_eq!(resolve(LLMProviderType::OpenAI, "o3-mini"), TokenizerKind::O200kBase);
+        assert_eq!(resolve(LLMProviderType::AzureOpenAI, "prod-chat"), TokenizerKind::O200kBase);
+        assert_eq!(
+            resolve(LLMProviderType::Gemini, "models/gemini-2.5-pro"),
+            TokenizerKind::Heuristic {
+                chars_per_token: 4.0
+            }
+        );
+        assert_eq!(
+            resolve(LLMProviderType::Ollama, "library/codellama:13b"),
+            TokenizerKind::Heuristic {
+                chars_per_token: 3.3
+            }
+        );
+    }
+
+    #[test]
+    fn registered_rules_take_priority() {
+        let mut registry = TokenizerRegistry::new();
+        registry.register("gpt-4o-legacy", TokenizerKind::Cl100kBase);
+        registry.register("qwen", TokenizerKind::O200kBase);
+
+        let resolve = |provider: LLMProviderType, model: &str| registry.resolve(&provider, model);
+        assert_eq!(resolve(LLMProviderType::OpenAI, "gpt-4o-legacy"), TokenizerKind::Cl100kBase);
+        assert_eq!(resolve(LLMProviderType::OpenAI, "gpt-4o"), TokenizerKind::O200kBase);
+        assert_eq!(resolve(LLMProviderType::Ollama, "qwen2.5-coder"), TokenizerKind::O200kBase);
+    }
+}
@@ -0,0 +1,406 @@
+// Journal de consommation des LLM (tokens et coût), persisté dans SQLite
+
+use chrono::{DateTime, SecondsFormat, Utc};
+use sqlx::sqlite::{SqliteConnectOptions, SqlitePool, SqlitePoolOptions};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use super::models::ModelInfo;
+use super::{LLMError, LLMResponse};
+
+/// Nom de la base dans le répertoire de données de l'utilisateur
+const LEDGER_FILE: &str = "usage.db";
+
+const CREATE_TABLE: &str = "CREATE TABLE IF NOT EXISTS llm_usage (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    timestamp TEXT NOT NULL,
+    provider TEXT NOT NULL,
+    model TEXT NOT NULL,
+    prompt_tokens INTEGER NOT NULL,
+    completion_tokens INTEGER NOT NULL,
+    cost REAL NOT NULL,
+    command TEXT NOT NULL,
+    project TEXT
+)";
+
+const CREATE_INDEX: &str =
+    "CREATE INDEX IF NOT EXISTS llm_usage_timestamp ON llm_usage (timestamp)";
+
+
+/// Appel LLM enregistré dans le journal
+#[derive(Debug, Clone, PartialEq)]
+pub struct UsageRecord {
+    pub timestamp: DateTime<Utc>,
+    /// Nom du provider dans le `LLMManager` (ex: "claude", "local")
+    pub provider: String,
+    pub model: String,
+    pub prompt_tokens: u32,
+    pub completion_tokens: u32,
+    /// Coût en USD, calculé avec les tarifs du catalogue des modèles
+    pub cost: f64,
+    /// Commande CLI à l'origine de l'appel (ex: "generate", "review")
+    pub command: String,
+    /// Projet sur lequel la commande a été lancée
+    pub project: Option<String>,
+}
+
+impl UsageRecord {
+    /// Entrée pour une réponse du provider `provider` ; le coût est nul pour un
+    /// modèle absent du catalogue
+    pub fn from_response(
+        provider: &str,
+        response: &LLMResponse,
+        model: Option<&ModelInfo>,
+        context: &UsageContext,
+    ) -> Self {
+        UsageRecord {
+            timestamp: Utc::now(),
+            provider: provider.to_string(),
+            model: response.model.clone(),
+            prompt_tokens: response.usage.prompt_tokens,
+            completion_tokens: response.usage.completion_tokens,
+            cost: model.map_or(0.0, |model| model.cost(&response.usage)),
+            command: context.command.clone(),
+            project: context.project.clone(),
+        }
+    }
+}
+
+/// Commande et projet courants, attachés à chaque appel enregistré
+#[derive(Debug, Clone, PartialEq)]
+pub struct UsageContext {
+    pub command: String,
+    pub project: Option<String>,
+}
+
+impl UsageContext {
+    /// Contexte d'une commande lancée dans le répertoire courant (nom du répertoire = projet)
+    pub fn for_command(command: impl Into<String>) -> Self {
+        let project = std::env::current_dir()
+            .ok()
+            .and_then(|dir| dir.file_name().map(|name| name.to_string_lossy().into_owned()));
+        UsageContext {
+            command: command.into(),
+            project,
+        }
+    }
+}
+
+/// Critère de regroupement d'un rapport
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UsageGrouping {
+    /// Par jour (UTC)
+    Day,
+    Provider,
+    Command,
+}
+
+/// Ligne d'un rapport de consommation
+#[derive(Debug, Clone, PartialEq)]
+pub struct UsageSummary {
+    /// Jour, provider ou commande selon le regroupement
+    pub key: String,
+    pub requests: u64,
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+    pub cost: f64,
+}
+
+/// Consommation cumulée sur une période
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct UsageTotals {
+    pub tokens: u64,
+    pub cost: f64,
+}
+
+
+/// Journal SQLite des appels LLM
+#[derive(Debug, Clone)]
+pub struct UsageLedger {
+    pool: SqlitePool,
+}
+
+impl UsageLedger {
+    /// Ouvre (ou crée) le journal dans un fichier
+    pub async fn open(path: &Path) -> Result<Self, LLMError> {
+        if let Some(parent) = path.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent).map_err(|e| {
+                LLMError::InternalError(format!(
+                    "Création de {} impossible: {}",
+                    parent.display(),
+                    e
+                ))
+            })?;
+        }
+
+        let options = SqliteConnectOptions::new().filename(path).create_if_missing(true);
+        Self::connect(options, 4).await
+    }
+
+    /// Ouvre le journal à son emplacement par défaut (voir `default_path`)
+    pub async fn open_default() -> Result<Self, LLMError> {
+        let path = Self::default_path().ok_or_else(|| {
+            LLMError::InternalError("Répertoire de données utilisateur introuvable".to_string())
+        })?;
+        Self::open(&path).await
+    }
+
+    /// Journal en mémoire, perdu à la fermeture
+    pub async fn in_memory() -> Result<Self, LLMError> {
+        let options = SqliteConnectOptions::from_str("sqlite::memory:").map_err(ledger_error)?;
+        // Chaque connexion ouvrirait sa propre base en mémoire
+        Self::connect(options, 1).await
+    }
+
+    /// Emplacement par défaut (`~/.local/share/codecrafter/usage.db` sous Linux)
+    pub fn default_path() -> Option<PathBuf> {
+        directories::ProjectDirs::from("", "", "codecrafter")
+            .map(|dirs| dirs.data_dir().join(LEDGER_FILE))
+    }
+
+    async fn connect(
+        options: SqliteConnectOptions,
+        max_connections: u32,
+    ) -> Result<Self, LLMError> {
+        let pool = SqlitePoolOptions::new()
+            .max_connections(max_connections)
+            .connect_with(options)
+            .await
+            .map_err(ledger_error)?;
+
+        sqlx::query(CREATE_TABLE).execute(&pool).await.map_err(ledger_error)?;
+        sqlx::query(CREATE_INDEX).execute(&pool).await.map_err(ledger_error)?;
+        Ok(UsageLedger { pool })
+    }
+
+    pub async fn record(&self, record: &UsageRecord) -> Result<(), LLMError> {
+        sqlx::query(
+            "INSERT INTO llm_usage (timestamp, provider, model, prompt_tokens,
+                completion_tokens, cost, command, project)
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?)",
+        )
+        .bind(format_timestamp(&record.timestamp))
+        .bind(&record.provider)
+        .bind(&record.model)
+        .bind(record.prompt_tokens)
+        .bind(record.completion_tokens)
+        .bind(record.cost)
+        .bind(&record.command)
+        .bind(&record.project)
+        .execute(&self.pool)
+        .await
+        .map_err(ledger_error)?;
+        Ok(())
+    }
+
+    /// Consommation regroupée, depuis `since` (tout l'historique si `None`), triée par clé
+    pub async fn report(
+        &self,
+        grouping: UsageGrouping,
+        since: Option<DateTime<Utc>>,
+    ) -> Result<Vec<UsageSummary>, LLMError> {
+        let key = match grouping {
+            UsageGrouping::Day => "substr(timestamp, 1, 10)",
+            UsageGrouping::Provider => "provider",
+            UsageGrouping::Command => "command",
+        };
+        let query = format!(
+            "SELECT {} AS key, COUNT(*), SUM(prompt_tokens), SUM(completion_tokens), SUM(cost)
+             FROM llm_usage WHERE timestamp >= ? GROUP BY key ORDER BY key",
+            key
+        );
+
+        let rows: Vec<(String, i64, i64, i64, f64)> = sqlx::query_as(&query)
+            .bind(since.as_ref().map(format_timestamp).unwrap_or_default())
+            .fetch_all(&self.pool)
+            .await
+            .map_err(ledger_error)?;
+
+        Ok(rows
+            .into_iter()
+            .map(|(key, requests, prompt_tokens, completion_tokens, cost)| UsageSummary {
+                key,
+                requests: requests as u64,
+                prompt_tokens: prompt_tokens as u64,
+                completion_tokens: completion_tokens as u64,
+                cost,
+            })
+            .collect())
+    }
+
+    /// Consommation depuis `since`, limitée à un provider et/ou à un projet si précisés
+    pub async fn totals(
+        &self,
+        provider: Option<&str>,
+        project: Option<&str>,
+        since: Option<DateTime<Utc>>,
+    ) -> Result<UsageTotals, LLMError> {
+        let (tokens, cost): (i64, f64) = sqlx::query_as(
+            "SELECT COALESCE(SUM(prompt_tokens + completion_tokens), 0), COALESCE(SUM(cost), 0.0)
+             FROM llm_usage
+             WHERE timestamp >= ?
+               AND (? IS NULL OR provider = ?)
+               AND (? IS NULL OR project = ?)",
+        )
+        .bind(since.as_ref().map(format_timestamp).unwrap_or_default())
+        .bind(provider)
+        .bind(provider)
+        .bind(project)
+        .bind(project)
+        .fetch_one(&self.pool)
+        .await
+        .map_err(ledger_error)?;
+
+        Ok(UsageTotals {
+            tokens: tokens as u64,
+            cost,
+        })
+    }
+}
+
+/// Horodatage RFC 3339 en UTC : l'ordre lexicographique suit l'ordre chronologique
+fn format_timestamp(timestamp: &DateTime<Utc>) -> String {
+    timestamp.to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+fn ledger_error(error: sqlx::Error) -> LLMError {
+    LLMError::InternalError(format!("Journal d'usage: {}", error))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::models::ModelCatalog;
+    use crate::llm::test_support::{response, user_request, ScriptedProvider};
+    use crate::llm::{LLMManager, LLMProviderType};
+    use chrono::TimeZone;
+
+    fn record(provider: &str, command: &str, day: u32, tokens: u32, cost: f64) -> UsageRecord {
+        UsageRecord {
+            timestamp: Utc.with_ymd_and_hms(2025, 3, day, 12, 0, 0).unwrap(),
+            provider: provider.to_string(),
+            model: "test-model".to_string(),
+            prompt_tokens: tokens,
+            completion_tokens: tokens / 2,
+            cost,
+            command: command.to_string(),
+            project: Some(format!("projet-{}", provider)),
+        }
+    }
+
+    async fn ledger() -> UsageLedger {
+        let ledger = UsageLedger::in_memory().await.unwrap();
+        for record in [
+            record("claude", "generate", 1, 100, 0.5),
+            record("claude", "review", 2, 200, 1.0),
+            record("local", "generate", 2, 1000, 0.0),
+        ] {
+            ledger.record(&record).await.unwrap();
+        }
+        ledger
+    }
+
+    #[tokio::test]
+    async fn report_groups_and_filters() {
+        let ledger = ledger().await;
+
+        let by_day = ledger.report(UsageGrouping::Day, None).await.unwrap();
+        let days: Vec<_> = by_day.iter().map(|row| (row.key.as_str(), row.requests)).collect();
+        assert_eq!(days, [("2025-03-01", 1), ("2025-03-02", 2)]);
+
+        let by_provider = ledger.report(UsageGrouping::Provider, None).await.unwrap();
+        assert_eq!(
+            by_provider[0],
+            UsageSummary {
+                key: "claude".to_string(),
+                requests: 2,
+                prompt_tokens: 300,
+                completion_tokens: 150,
+                cost: 1.5,
+            }
+        );
+
+        let since = Utc.with_ymd_and_hms(2025, 3, 2, 0, 0, 0).unwrap();
+        let by_command = ledger.report(UsageGrouping::Command, Some(since)).await.unwrap();
+        let commands: Vec<_> = by_command.iter().map(|row| row.key.as_str()).collect();
+        assert_eq!(commands, ["generate", "review"]);
+        assert_eq!(by_command[0].prompt_tokens, 1000);
+    }
+
+    #[tokio::test]
+    async fn totals_by_provider_and_project() {
+        let ledger = ledger().await;
+
+        let all = ledger.totals(None, None, None).await.unwrap();
+        assert_eq!(all.tokens, 150 + 300 + 1500);
+        let claude = ledger.totals(Some("claude"), None, None).await.unwrap();
+        assert_eq!(claude, UsageTotals { tokens: 450, cost: 1.5 });
+        let project = ledger.totals(None, Some("projet-local"), None).await.unwrap();
+        assert_eq!(project.tokens, 1500);
+
+        let since = Utc.with_ymd_and_hms(2025, 4, 1, 0, 0, 0).unwrap();
+        assert_eq!(ledger.totals(None, None, Some(since)).await.unwrap(), UsageTotals::default());
+    }
+
+    #[tokio::test]
+    async fn file_ledger_persists() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("donnees").join(LEDGER_FILE);
+
+        let ledger = UsageLedger::open(&path).await.unwrap();
+        ledger.record(&record("claude", "generate", 1, 10, 0.1)).await.unwrap();
+        drop(ledger);
+
+        let reopened = UsageLedger::open(&path).await.unwrap();
+        assert_eq!(reopened.totals(None, None, None).await.unwrap().tokens, 15);
+    }
+
+    #[test]
+    fn record_cost_comes_from_the_catalogue() {
+        let catalog = ModelCatalog::builtin();
+        let model = catalog.lookup(&LLMProviderType::Claude, "claude-sonnet-4").unwrap();
+        let context = UsageContext {
+            command: "review".to_string(),
+            project: None,
+        };
+        let response = response("ok");
+
+        let priced = UsageRecord::from_response("claude", &response, Some(model), &context);
+        assert_eq!(priced.cost, model.cost(&response.usage));
+        assert!(priced.cost > 0.0);
+        assert_eq!((priced.prompt_tokens, priced.completion_tokens), (10, 5));
+
+        let unknown = UsageRecord::from_response("local", &response, None, &context);
+        assert_eq!(unknown.cost, 0.0);
+    }
+
+    #[tokio::test]
+    async fn manager_records_each_answer() {
+        let ledger = UsageLedger::in_memory().await.unwrap();
+        let context = UsageContext {
+            command: "generate".to_string(),
+            project: Some("codecrafter".to_string()),
+        };
+        let mut manager = LLMManager::new();
+        manager.register("local", Box::new(ScriptedProvider::new("local", Vec::new())));
+        manager.set_usage_ledger(ledger.clone(), context);
+
+        manager.generate(user_request("Bonjour")).await.unwrap();
+        let stream = manager.generate_stream(user_request("Bonjour")).await.unwrap();
+        crate::llm::streaming::collect_response(stream, "m").await.unwrap();
+
+        // L'enregistrement d'un flux se fait en tâche de fond, à la fin du flux
+        for _ in 0..50 {
+            if ledger.totals(None, None, None).await.unwrap().tokens == 30 {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+        let rows = ledger.report(UsageGrouping::Provider, None).await.unwrap();
+        assert_eq!(rows.len(), 1);
+        assert_eq!((rows[0].key.as_str(), rows[0].requests), ("local", 2));
+        let project = ledger.totals(None, Some("codecrafter"), None).await.unwrap();
+        assert_eq!(project.tokens, 30);
+    }
+}
@@ -0,0 +1,1181 @@
+// Validation de la configuration : tous les problèmes à la fois, avec fichier, ligne
+// et correction suggérée
+
+use std::collections::HashMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use serde::de::DeserializeOwned;
+use toml::{Table, Value};
+
+use super::budget::BudgetLimit;
+use super::cache::CacheConfig;
+use super::config::{CodeCrafterConfig, ConfigLayer, PROVIDER_ENV};
+use super::middleware::MiddlewareConfig;
+use super::deployment::classify_base_url;
+use super::models::ModelCatalog;
+use super::{DeploymentMode, LLMError, LLMProviderConfig, LLMProviderType, ModelParameters};
+
+/// Clé sonde : absente de toute structure, elle fait lister par serde les clés
+/// acceptées (voir `known_keys`)
+const PROBE_KEY: &str = "\u{0}";
+
+/// Sources de secret acceptées pour `api_key` (voir `SecretSource`)
+const SECRET_SOURCES: &[&str] = &["env", "file", "keystore"];
+
+const SECRET_SOURCE_SUGGESTION: &str =
+    "api_key = { env = \"VAR\" }, { file = \"chemin\" } ou { keystore = \"nom\" }";
+
+const PROVIDER_TYPES: &[&str] = &[
+    "claude",
+    "openai",
+    "gemini",
+    "ollama",
+    "llamacpp",
+    "mistral",
+    "azureopenai",
+    "custom",
+];
+
+/// Noms courants mais incorrects des types de provider
+const PROVIDER_TYPE_ALIASES: &[(&str, &str)] = &[
+    ("anthropic", "claude"),
+    ("google", "gemini"),
+    ("azure", "azureopenai"),
+    ("azure-openai", "azureopenai"),
+    ("azure_openai", "azureopenai"),
+    ("llama.cpp", "llamacpp"),
+    ("llama-cpp", "llamacpp"),
+    ("gpt", "openai"),
+];
+
+
+/// Gravité d'un problème : une erreur empêche le chargement de la configuration
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+/// Position d'une valeur dans un fichier de configuration
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourceLocation {
+    pub path: PathBuf,
+    /// Ligne (à partir de 1)
+    pub line: usize,
+}
+
+/// Problème détecté dans la configuration
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConfigIssue {
+    pub severity: Severity,
+    pub location: Option<SourceLocation>,
+    /// Clé concernée (ex: `providers.claude.parameters.temperature`)
+    pub key: String,
+    pub message: String,
+    /// Correction proposée
+    pub suggestion: Option<String>,
+}
+
+impl ConfigIssue {
+    pub fn error(key: impl Into<String>, message: impl Into<String>) -> Self {
+        ConfigIssue {
+            severity: Severity::Error,
+            location: None,
+            key: key.into(),
+            message: message.into(),
+            suggestion: None,
+        }
+    }
+
+    pub fn warning(key: impl Into<String>, message: impl Into<String>) -> Self {
+        ConfigIssue {
+            severity: Severity::Warning,
+            ..Self::error(key, message)
+        }
+    }
+
+    pub fn with_suggestion(mut self, suggestion: impl Into<String>) -> Self {
+        self.suggestion = Some(suggestion.into());
+        self
+    }
+
+    pub fn at(mut self, location: Option<SourceLocation>) -> Self {
+        self.location = location;
+        self
+    }
+}
+
+impl fmt::Display for ConfigIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(location) = &self.location {
+            write!(f, "{}:{}: ", location.path.display(), location.line)?;
+        }
+        if !self.key.is_empty() {
+            write!(f, "{}: ", self.key)?;
+        }
+        write!(f, "{}", self.message)?;
+        if let Some(suggestion) = &self.suggestion {
+            write!(f, " (suggestion: {})", suggestion)?;
+        }
+        Ok(())
+    }
+}
+
+/// Résultat de la vérification d'une configuration
+#[derive(Debug, Clone, Default)]
+pub struct ConfigReport {
+    /// Fichiers lus, dans l'ordre de fusion
+    pub files: Vec<PathBuf>,
+    pub profile: Option<String>,
+    pub issues: Vec<ConfigIssue>,
+}
+
+impl ConfigReport {
+    pub fn has_errors(&self) -> bool {
+        self.errors().next().is_some()
+    }
+
+    pub fn errors(&self) -> impl Iterator<Item = &ConfigIssue> {
+        self.issues.iter().filter(|issue| issue.severity == Severity::Error)
+    }
+
+    pub fn warnings(&self) -> impl Iterator<Item = &ConfigIssue> {
+        self.issues.iter().filter(|issue| issue.severity == Severity::Warning)
+    }
+
+    /// `LLMError::InvalidConfig` listant toutes les erreurs, une par ligne
+    pub fn into_result(self) -> Result<(), LLMError> {
+        let errors: Vec<String> = self.errors().map(ToString::to_string).collect();
+        match errors.len() {
+            0 => Ok(()),
+            1 => Err(LLMError::InvalidConfig(errors[0].clone())),
+            count => Err(LLMError::InvalidConfig(format!(
+                "{} erreurs\n{}",
+                count,
+                errors.join("\n")
+            ))),
+        }
+    }
+}
+
+
+/// Vérifie la configuration fusionnée `root` (profil appliqué).
+///
+/// Les positions sont recherchées dans `layers` : la dernière couche définissant la
+/// clé (ou, à défaut, sa table parente) est celle qui l'a fournie.
+pub(crate) fn validate(
+    root: &Table,
+    layers: &[ConfigLayer],
+    profile: Option<&str>,
+    provider_from_env: bool,
+) -> Vec<ConfigIssue> {
+    let mut checker = Checker {
+        sources: Sources { layers, profile },
+        issues: Vec::new(),
+    };
+    checker.check_root(root, provider_from_env);
+    checker.issues
+}
+
+struct Checker<'a> {
+    sources: Sources<'a>,
+    issues: Vec<ConfigIssue>,
+}
+
+impl Checker<'_> {
+    fn push(&mut self, path: &[String], issue: ConfigIssue) {
+        let location = self.sources.locate(path);
+        self.issues.push(issue.at(location));
+    }
+
+    fn error(&mut self, path: &[String], message: String, suggestion: Option<String>) {
+        let mut issue = ConfigIssue::error(display_key(path), message);
+        issue.suggestion = suggestion;
+        self.push(path, issue);
+    }
+
+    fn warning(&mut self, path: &[String], message: String, suggestion: Option<String>) {
+        let mut issue = ConfigIssue::warning(display_key(path), message);
+        issue.suggestion = suggestion;
+        self.push(path, issue);
+    }
+
+    fn check_root(&mut self, root: &Table, provider_from_env: bool) {
+        self.check_unknown_keys::<CodeCrafterConfig>(&[], root, Table::new());
+
+        let providers = match root.get("providers") {
+            Some(Value::Table(providers)) => providers.clone(),
+            Some(_) => {
+                self.error(&path(&["providers"]), "doit être une table".to_string(), None);
+                Table::new()
+            }
+            None => Table::new(),
+        };
+        if providers.is_empty() {
+            self.warning(
+                &[],
+                "aucun provider configuré".to_string(),
+                Some("ajouter une table [providers.<nom>] avec provider_type et model_name".into()),
+            );
+        }
+        let names: Vec<&str> = providers.keys().map(String::as_str).collect();
+
+        match root.get("default_provider") {
+            Some(Value::String(name)) if !providers.contains_key(name) => {
+                let message = if provider_from_env {
+                    format!("provider inconnu `{}` (variable {})", name, PROVIDER_ENV)
+                } else {
+                    format!("provider inconnu `{}`", name)
+                };
+                let issue = ConfigIssue::error("default_provider", message)
+                    .with_suggestion(name_suggestion(name, &names));
+                if provider_from_env {
+                    self.issues.push(issue);
+                } else {
+                    self.push(&path(&["default_provider"]), issue);
+                }
+            }
+            Some(Value::String(_)) | None => {}
+            Some(_) => self.error(
+                &path(&["default_provider"]),
+                "doit être une chaîne".to_string(),
+                None,
+            ),
+        }
+
+        match root.get("fallback") {
+            Some(Value::Array(items)) => {
+                for (i, item) in items.iter().enumerate() {
+                    let item_path = path(&["fallback", &i.to_string()]);
+                    match item.as_str() {
+                        Some(name) if providers.contains_key(name) => {}
+                        Some(name) => self.error(
+                            &item_path,
+                            format!("provider inconnu `{}`", name),
+                            Some(name_suggestion(name, &names)),
+                        ),
+                        None => self.error(&item_path, "doit être une chaîne".to_string(), None),
+                    }
+                }
+            }
+            Some(_) => self.error(
+                &path(&["fallback"]),
+                "doit être une liste de noms de providers".to_string(),
+                Some("fallback = [\"claude\", \"ollama-local\"]".to_string()),
+            ),
+            None => {}
+        }
+
+        for (name, provider) in &providers {
+            self.check_provider(name, provider);
+        }
+
+        match root.get("budgets") {
+            Some(Value::Array(budgets)) => {
+                for (i, budget) in budgets.iter().enumerate() {
+                    self.check_budget(i, budget, &names);
+                }
+            }
+            Some(_) => self.error(
+                &path(&["budgets"]),
+                "doit être une liste de tables [[budgets]]".to_string(),
+                None,
+            ),
+            None => {}
+        }
+
+        match root.get("cache") {
+            Some(Value::Table(cache)) => self.check_cache(cache),
+            Some(_) => self.error(&path(&["cache"]), "doit être une table".to_string(), None),
+            None => {}
+        }
+
+        match root.get("middleware") {
+            Some(Value::Array(middlewares)) => {
+                for (i, middleware) in middlewares.iter().enumerate() {
+                    self.check_middleware(i, middleware);
+                }
+            }
+            Some(_) => self.error(
+                &path(&["middleware"]),
+                "doit être une liste de tables [[middleware]]".to_string(),
+                None,
+            ),
+            None => {}
+        }
+    }
+
+    fn check_provider(&mut self, name: &str, value: &Value) {
+        let base = path(&["providers", name]);
+        let at = |key: &str| {
+            let mut path = base.clone();
+            path.extend(key.split('.').map(str::to_string));
+            path
+        };
+
+        let Some(provider) = value.as_table() else {
+            self.error(&base, "doit être une table".to_string(), None);
+            return;
+        };
+        self.check_unknown_keys::<LLMProviderConfig>(&base, provider, Table::new());
+
+        let provider_type = match provider.get("provider_type") {
+            None => {
+                self.error(
+                    &base,
+                    "provider_type manquant".to_string(),
+                    Some(format!("provider_type = un de {}", PROVIDER_TYPES.join(", "))),
+                );
+                None
+            }
+            Some(Value::String(kind)) => match parse_provider_type(kind) {
+                Some(provider_type) => Some(provider_type),
+                None => {
+                    self.error(
+                        &at("provider_type"),
+                        format!("type de provider inconnu `{}`", kind),
+                        Some(provider_type_suggestion(kind)),
+                    );
+                    None
+                }
+            },
+            Some(_) => {
+                self.error(&at("provider_type"), "doit être une chaîne".to_string(), None);
+                None
+            }
+        };
+
+        match provider.get("model_name") {
+            Some(Value::String(model)) if !model.trim().is_empty() => {}
+            Some(Value::String(_)) | None => {
+                let example = provider_type
+                    .as_ref()
+                    .and_then(example_model)
+                    .unwrap_or_else(|| "<nom du modèle>".to_string());
+                let target = match provider.contains_key("model_name") {
+                    true => at("model_name"),
+                    false => base.clone(),
+                };
+                self.error(
+                    &target,
+                    "model_name manquant ou vide".to_string(),
+                    Some(format!("model_name = \"{}\"", example)),
+                );
+            }
+            Some(_) => self.error(&at("model_name"), "doit être une chaîne".to_string(), None),
+        }
+
+        let deployment = match provider.get("deployment") {
+            None => Some("auto"),
+            Some(Value::String(mode)) if ["local", "remote", "auto"].contains(&mode.as_str()) => {
+                Some(mode.as_str())
+            }
+            Some(other) => {
+                self.error(
+                    &at("deployment"),
+                    format!("mode de déploiement invalide {}", other),
+                    Some("deployment = \"local\", \"remote\" ou \"auto\"".to_string()),
+                );
+                None
+            }
+        };
+
+        // En mode auto, une URL locale (boucle locale, réseau privé, socket unix)
+        // dispense de clé API
+        let mut local_url = false;
+        let base_url = match provider.get("base_url") {
+            Some(Value::String(url)) if !url.trim().is_empty() => {
+                if let Err(e) = url::Url::parse(url) {
+                    self.error(
+                        &at("base_url"),
+                        format!("URL invalide `{}`: {}", url, e),
+                        Some("base_url = \"https://hôte[:port]\"".to_string()),
+                    );
+                }
+                local_url = classify_base_url(url) == Some(DeploymentMode::Local);
+                true
+            }
+            Some(Value::String(_)) | None => false,
+            Some(_) => {
+                self.error(&at("base_url"), "doit être une chaîne".to_string(), None);
+                false
+            }
+        };
+
+        let api_key = match provider.get("api_key") {
+            Some(Value::String(key)) => !key.trim().is_empty(),
+            Some(Value::Table(source)) => {
+                self.check_secret_source(&at("api_key"), source);
+                true
+            }
+            Some(_) => {
+                self.error(
+                    &at("api_key"),
+                    "doit être une chaîne ou une source de secret".to_string(),
+                    Some(SECRET_SOURCE_SUGGESTION.to_string()),
+                );
+                true
+            }
+            None => false,
+        };
+
+        let has_authorization = provider
+            .get("headers")
+            .and_then(Value::as_table)
+            .is_some_and(|headers| headers.keys().any(|h| h.eq_ignore_ascii_case("authorization")));
+
+        if let (Some(provider_type), Some(deployment)) = (&provider_type, deployment) {
+            let remote = deployment == "remote";
+            let needs_base_url = match provider_type {
+                LLMProviderType::AzureOpenAI | LLMProviderType::Custom => true,
+                LLMProviderType::Ollama | LLMProviderType::LlamaCpp => remote,
+                _ => false,
+            };
+            if needs_base_url && !base_url {
+                self.error(
+                    &base,
+                    "base_url manquant".to_string(),
+                    Some(base_url_suggestion(provider_type)),
+                );
+            }
+
+            let local = deployment == "local" || (deployment == "auto" && local_url);
+            let needs_api_key = !local
+                && match provider_type {
+                    LLMProviderType::Claude
+                    | LLMProviderType::Gemini
+                    | LLMProviderType::Mistral => true,
+                    LLMProviderType::OpenAI => !base_url,
+                    LLMProviderType::AzureOpenAI => !has_authorization,
+                    _ => false,
+                };
+            if needs_api_key && !api_key {
+                let variable = api_key_variable(provider_type);
+                self.error(
+                    &base,
+                    "api_key manquante (ou variable d'environnement non définie)".to_string(),
+                    Some(format!(
+                        "api_key = {{ env = \"{}\" }} et exporter {}",
+                        variable, variable
+                    )),
+                );
+            }
+
+            if *provider_type == LLMProviderType::Custom && !provider.contains_key("template") {
+                self.error(
+                    &base,
+                    "section template manquante pour un provider custom".to_string(),
+                    Some(format!(
+                        "ajouter [providers.{}.template] avec path, body et content",
+                        name
+                    )),
+                );
+            }
+        }
+
+        match provider.get("parameters") {
+            Some(Value::Table(parameters)) => self.check_parameters(&at("parameters"), parameters),
+            Some(_) => self.error(&at("parameters"), "doit être une table".to_string(), None),
+            None => {}
+        }
+
+        for key in ["timeout_seconds", "max_retries"] {
+            match provider.get(key) {
+                Some(Value::Integer(value)) if *value >= 0 => {}
+                Some(other) => self.error(
+                    &at(key),
+                    format!("doit être un entier positif (valeur: {})", other),
+                    None,
+                ),
+                None => {}
+            }
+        }
+
+        for key in ["headers", "options"] {
+            match provider.get(key) {
+                Some(Value::Table(table)) => {
+                    for (entry, value) in table {
+                        if !value.is_str() {
+                            self.error(
+                                &at(&format!("{}.{}", key, entry)),
+                                "doit être une chaîne".to_string(),
+                                Some(format!("{} = \"{}\"", entry, value)),
+                            );
+                        }
+                    }
+                }
+                Some(_) => self.error(&at(key), "doit être une table".to_string(), None),
+                None => {}
+            }
+        }
+    }
+
+    fn check_secret_source(&mut self, base: &[String], source: &Table) {
+        let kinds: Vec<&String> = source
+            .keys()
+            .filter(|key| SECRET_SOURCES.contains(&key.as_str()))
+            .collect();
+        if kinds.len() != 1 {
+            self.error(
+                base,
+                "une source de secret doit préciser exactement une clé env, file ou keystore"
+                    .to_string(),
+                Some(SECRET_SOURCE_SUGGESTION.to_string()),
+            );
+        }
+        for key in source.keys().filter(|key| !SECRET_SOURCES.contains(&key.as_str())) {
+            let mut key_path = base.to_vec();
+            key_path.push(key.clone());
+            let suggestion = unknown_key_suggestion(key, SECRET_SOURCES.iter().copied());
+            self.error(&key_path, "clé inconnue".to_string(), suggestion);
+        }
+
+        for kind in kinds {
+            let mut at = base.to_vec();
+            at.push(kind.clone());
+            match &source[kind] {
+                Value::String(variable) if kind == "env" && std::env::var(variable).is_err() => {
+                    self.warning(
+                        &at,
+                        format!("variable d'environnement {} non définie", variable),
+                        None,
+                    )
+                }
+                Value::String(value) if !value.trim().is_empty() => {}
+                _ => self.error(&at, "doit être une chaîne non vide".to_string(), None),
+            }
+        }
+    }
+
+    fn check_parameters(&mut self, base: &[String], parameters: &Table) {
+        self.check_unknown_keys::<ModelParameters>(base, parameters, Table::new());
+        let at = |key: &str| {
+            let mut path = base.to_vec();
+            path.push(key.to_string());
+            path
+        };
+
+        let ranges = [
+            ("temperature", 0.0, 2.0),
+            ("top_p", 0.0, 1.0),
+            ("presence_penalty", -2.0, 2.0),
+            ("frequency_penalty", -2.0, 2.0),
+        ];
+        for (key, min, max) in ranges {
+            let Some(value) = parameters.get(key) else {
+                continue;
+            };
+            match number(value) {
+                Some(number) if (min..=max).contains(&number) => {}
+                Some(number) => self.error(
+                    &at(key),
+                    format!("{} hors de l'intervalle [{:.1}, {:.1}]", number, min, max),
+                    Some(format!("{} = {}", key, number.clamp(min, max))),
+                ),
+                None => self.error(
+                    &at(key),
+                    format!("doit être un nombre (valeur: {})", value),
+                    None,
+                ),
+            }
+        }
+
+        match parameters.get("max_tokens") {
+            Some(Value::Integer(tokens)) if *tokens > 0 && *tokens <= u32::MAX as i64 => {}
+            Some(other) => self.error(
+                &at("max_tokens"),
+                format!("doit être un entier strictement positif (valeur: {})", other),
+                Some("max_tokens = 4096".to_string()),
+            ),
+            None => {}
+        }
+
+        match parameters.get("stop_sequences") {
+            Some(Value::Array(items)) if items.iter().all(Value::is_str) => {}
+            Some(_) => self.error(
+                &at("stop_sequences"),
+                "doit être une liste de chaînes".to_string(),
+                Some("stop_sequences = [\"\\n\\n\"]".to_string()),
+            ),
+            None => {}
+        }
+    }
+
+    fn check_cache(&mut self, cache: &Table) {
+        let base = path(&["cache"]);
+        self.check_unknown_keys::<CacheConfig>(&base, cache, Table::new());
+        let at = |key: &str| path(&["cache", key]);
+
+        for key in ["enabled", "persist"] {
+            match cache.get(key) {
+                Some(Value::Boolean(_)) | None => {}
+                Some(other) => self.error(
+                    &at(key),
+                    format!("doit être un booléen (valeur: {})", other),
+                    Some(format!("{} = true", key)),
+                ),
+            }
+        }
+        match cache.get("capacity") {
+            Some(Value::Integer(capacity)) if *capacity > 0 => {}
+            Some(other) => self.error(
+                &at("capacity"),
+                format!("doit être un entier strictement positif (valeur: {})", other),
+                Some("capacity = 1000".to_string()),
+            ),
+            None => {}
+        }
+        match cache.get("path") {
+            Some(Value::String(_)) | None => {}
+            Some(_) => self.error(&at("path"), "doit être une chaîne".to_string(), None),
+        }
+    }
+
+    fn check_middleware(&mut self, index: usize, value: &Value) {
+        let base = path(&["middleware", &index.to_string()]);
+        let at = |key: &str| {
+            let mut path = base.clone();
+            path.push(key.to_string());
+            path
+        };
+
+        let Some(middleware) = value.as_table() else {
+            self.error(&base, "doit être une table".to_string(), None);
+            return;
+        };
+        let types = serde_names::<MiddlewareConfig>(Value::Table(Table::from_iter([(
+            "type".to_string(),
+            Value::String(PROBE_KEY.to_string()),
+        )])));
+        let kind = match middleware.get("type") {
+            Some(Value::String(kind)) => kind.as_str(),
+            _ => {
+                self.error(
+                    &base,
+                    "type de middleware manquant".to_string(),
+                    Some(format!("type = un de {}", types.join(", "))),
+                );
+                return;
+            }
+        };
+        if !types.iter().any(|name| name == kind) {
+            let suggestion = match closest(kind, types.iter().map(String::as_str)) {
+                Some(candidate) => format!("vouliez-vous dire `{}` ?", candidate),
+                None => format!("type = un de {}", types.join(", ")),
+            };
+            self.error(
+                &at("type"),
+                format!("type de middleware inconnu `{}`", kind),
+                Some(suggestion),
+            );
+            return;
+        }
+        let tag = Table::from_iter([("type".to_string(), Value::String(kind.to_string()))]);
+        self.check_unknown_keys::<MiddlewareConfig>(&base, middleware, tag);
+
+        match (kind, middleware.get("patterns")) {
+            ("redaction", Some(Value::Array(patterns))) => {
+                for (i, pattern) in patterns.iter().enumerate() {
+                    let mut pattern_path = at("patterns");
+                    pattern_path.push(i.to_string());
+                    match pattern.as_str().map(regex::Regex::new) {
+                        Some(Ok(_)) => {}
+                        Some(Err(e)) => self.error(
+                            &pattern_path,
+                            format!("expression régulière invalide: {}", e),
+                            None,
+                        ),
+                        None => {
+                            self.error(&pattern_path, "doit être une chaîne".to_string(), None)
+                        }
+                    }
+                }
+            }
+            ("redaction", Some(_)) => self.error(
+                &at("patterns"),
+                "doit être une liste d'expressions régulières".to_string(),
+                None,
+            ),
+            _ => {}
+        }
+
+        if kind == "conventions" {
+            match middleware.get("file") {
+                Some(Value::String(file)) if !Path::new(file).is_file() => self.warning(
+                    &at("file"),
+                    format!("fichier de conventions introuvable `{}`", file),
+                    None,
+                ),
+                Some(Value::String(_)) => {}
+                Some(_) => self.error(&at("file"), "doit être une chaîne".to_string(), None),
+                None => self.error(
+                    &base,
+                    "fichier de conventions manquant".to_string(),
+                    Some("file = \"CONVENTIONS.md\"".to_string()),
+                ),
+            }
+        }
+    }
+
+    fn check_budget(&mut self, index: usize, value: &Value, providers: &[&str]) {
+        let base = path(&["budgets", &index.to_string()]);
+        let at = |key: &str| {
+            let mut path = base.clone();
+            path.push(key.to_string());
+            path
+        };
+
+        let Some(budget) = value.as_table() else {
+            self.error(&base, "doit être une table".to_string(), None);
+            return;
+        };
+        self.check_unknown_keys::<BudgetLimit>(&base, budget, Table::new());
+
+        match budget.get("unit").and_then(Value::as_str) {
+            Some("tokens" | "usd") => {}
+            _ => self.error(
+                &base,
+                "unité manquante ou invalide".to_string(),
+                Some("unit = \"tokens\" ou \"usd\"".to_string()),
+            ),
+        }
+
+        match budget.get("period") {
+            None => {}
+            Some(Value::String(period)) if period == "day" || period == "total" => {}
+            Some(_) => self.error(
+                &at("period"),
+                "période invalide".to_string(),
+                Some("period = \"day\" ou \"total\"".to_string()),
+            ),
+        }
+
+        if let Some(Value::String(name)) = budget.get("provider") {
+            if !providers.contains(&name.as_str()) {
+                self.warning(
+                    &at("provider"),
+                    format!("provider inconnu `{}` : ce budget ne s'appliquera jamais", name),
+                    Some(name_suggestion(name, providers)),
+                );
+            }
+        }
+
+        let mut limit = |key: &str| match budget.get(key) {
+            None => None,
+            Some(value) => match number(value).filter(|amount| *amount >= 0.0) {
+                Some(amount) => Some(amount),
+                None => {
+                    self.error(&at(key), "doit être un nombre positif".to_string(), None);
+                    None
+                }
+            },
+        };
+        let soft = limit("soft");
+        let hard = limit("hard");
+
+        match (soft, hard) {
+            (Some(soft), Some(hard)) if soft > hard => self.warning(
+                &at("soft"),
+                format!("seuil d'alerte ({}) supérieur à la limite stricte ({})", soft, hard),
+                Some(format!("soft = {}", hard * 0.8)),
+            ),
+            (None, None) if !budget.contains_key("soft") && !budget.contains_key("hard") => self
+                .warning(
+                    &base,
+                    "ni soft ni hard : ce budget n'a aucun effet".to_string(),
+                    Some("ajouter soft = … et/ou hard = …".to_string()),
+                ),
+            _ => {}
+        }
+    }
+
+    /// Signale les clés de `table` que `T` refuserait (`#[serde(deny_unknown_fields)]`) ;
+    /// `tag` complète la sonde pour les enums étiquetés (ex: `type` d'un middleware)
+    fn check_unknown_keys<T: DeserializeOwned>(
+        &mut self,
+        base: &[String],
+        table: &Table,
+        tag: Table,
+    ) {
+        let mut known: Vec<String> = tag.keys().cloned().collect();
+        known.extend(known_keys::<T>(tag));
+        for key in table.keys().filter(|key| !known.contains(key)) {
+            let mut key_path = base.to_vec();
+            key_path.push(key.clone());
+            let suggestion = unknown_key_suggestion(key, known.iter().map(String::as_str));
+            self.error(&key_path, "clé inconnue".to_string(), suggestion);
+        }
+    }
+}
+
+
+/// Clés acceptées par `T`, lues dans l'erreur que serde produit pour la clé sonde
+fn known_keys<T: DeserializeOwned>(mut probe: Table) -> Vec<String> {
+    probe.insert(PROBE_KEY.to_string(), Value::Boolean(true));
+    serde_names::<T>(Value::Table(probe))
+}
+
+/// Noms cités dans l'erreur de désérialisation de `value` (``unknown field `x`, expected
+/// one of `a`, `b` ``), hors clé sonde ; aucun si `value` est acceptée
+fn serde_names<T: DeserializeOwned>(value: Value) -> Vec<String> {
+    match T::deserialize(value) {
+        Ok(_) => Vec::new(),
+        Err(e) => e
+            .to_string()
+            .split('`')
+            .skip(1)
+            .step_by(2)
+            .filter(|name| *name != PROBE_KEY)
+            .map(str::to_string)
+            .collect(),
+    }
+}
+
+fn unknown_key_suggestion<'a>(key: &str, known: impl Iterator<Item = &'a str>) -> Option<String> {
+    closest(key, known).map(|candidate| format!("vouliez-vous dire `{}` ?", candidate))
+}
+
+
+/// Recherche de la position des clés dans les fichiers fusionnés
+struct Sources<'a> {
+    layers: &'a [ConfigLayer],
+    profile: Option<&'a str>,
+}
+
+impl Sources<'_> {
+    /// Position de la clé, ou à défaut de sa table la plus proche
+    fn locate(&self, target: &[String]) -> Option<SourceLocation> {
+        for depth in (1..=target.len()).rev() {
+            let candidate = &target[..depth];
+            let profiled = self.profile.map(|profile| {
+                let mut path = path(&["profiles", profile]);
+                path.extend_from_slice(candidate);
+                path
+            });
+
+            let plain = candidate.to_vec();
+            for layer in self.layers.iter().rev() {
+                for key_path in profiled.iter().chain(std::iter::once(&plain)) {
+                    if contains_path(&layer.table, key_path) {
+                        return locate_in(&layer.path, &layer.content, key_path);
+                    }
+                }
+            }
+        }
+        None
+    }
+}
+
+/// Position d'une clé dans un fichier donné
+pub(crate) fn locate_in(path: &Path, content: &str, key_path: &[String]) -> Option<SourceLocation> {
+    find_line(content, key_path).map(|line| SourceLocation {
+        path: path.to_path_buf(),
+        line,
+    })
+}
+
+fn contains_path(table: &Table, path: &[String]) -> bool {
+    let Some((first, rest)) = path.split_first() else {
+        return true;
+    };
+    let mut current = match table.get(first) {
+        Some(value) => value,
+        None => return false,
+    };
+    for segment in rest {
+        let next = match current {
+            Value::Table(table) => table.get(segment),
+            Value::Array(items) => segment.parse::<usize>().ok().and_then(|i| items.get(i)),
+            _ => None,
+        };
+        match next {
+            Some(value) => current = value,
+            None => return false,
+        }
+    }
+    true
+}
+
+/// Ligne (à partir de 1) où la clé est définie, par lecture des en-têtes de tables
+/// (`[a.b]`, `[[a]]`) et des clés (`c.d = …`).
+///
+/// À défaut de la clé elle-même, retourne la ligne de la table parente la plus proche.
+fn find_line(content: &str, target: &[String]) -> Option<usize> {
+    let mut prefix: Vec<String> = Vec::new();
+    let mut array_counts: HashMap<Vec<String>, usize> = HashMap::new();
+    let mut best: Option<(usize, usize)> = None;
+
+    for (index, raw) in content.lines().enumerate() {
+        let line = raw.trim();
+        let full = if let Some(header) = line.strip_prefix("[[") {
+            let table = parse_key(header.split("]]").next().unwrap_or_default());
+            let count = array_counts.entry(table.clone()).or_insert(0);
+            prefix = table;
+            prefix.push(count.to_string());
+            *count += 1;
+            prefix.clone()
+        } else if let Some(header) = line.strip_prefix('[') {
+            prefix = parse_key(header.split(']').next().unwrap_or_default());
+            prefix.clone()
+        } else if let Some((key, _)) = line.split_once('=').filter(|_| !line.starts_with('#')) {
+            let mut full = prefix.clone();
+            full.extend(parse_key(key));
+            full
+        } else {
+            continue;
+        };
+
+        if full == target {
+            return Some(index + 1);
+        }
+        let deeper = best.filter(|(depth, _)| *depth >= full.len()).is_none();
+        if deeper && target.starts_with(&full) {
+            best = Some((full.len(), index + 1));
+        }
+    }
+
+    best.map(|(_, line)| line)
+}
+
+/// Découpe une clé TOML pointée (`a."b.c".d`) en segments
+fn parse_key(key: &str) -> Vec<String> {
+    let mut segments = Vec::new();
+    let mut current = String::new();
+    let mut quote = None;
+
+    for c in key.chars() {
+        match (quote, c) {
+            (None, '"' | '\'') => quote = Some(c),
+            (Some(open), _) if c == open => quote = None,
+            (None, '.') => segments.push(std::mem::take(&mut current).trim().to_string()),
+            _ => current.push(c),
+        }
+    }
+    segments.push(current.trim().to_string());
+    segments
+}
+
+/// Clé affichée : `budgets[1].hard`
+pub(crate) fn display_key(path: &[String]) -> String {
+    let mut key = String::new();
+    for segment in path {
+        if segment.parse::<usize>().is_ok() && !key.is_empty() {
+            key.push_str(&format!("[{}]", segment));
+        } else {
+            if !key.is_empty() {
+                key.push('.');
+            }
+            key.push_str(segment);
+        }
+    }
+    key
+}
+
+fn path(segments: &[&str]) -> Vec<String> {
+    segments.iter().map(|segment| segment.to_string()).collect()
+}
+
+fn number(value: &Value) -> Option<f64> {
+    match value {
+        Value::Float(number) => Some(*number),
+        Value::Integer(number) => Some(*number as f64),
+        _ => None,
+    }
+}
+
+pub(crate) fn parse_provider_type(kind: &str) -> Option<LLMProviderType> {
+    Value::String(kind.to_string()).try_into().ok()
+}
+
+fn provider_type_suggestion(kind: &str) -> String {
+    let lower = kind.to_ascii_lowercase();
+    let alias = PROVIDER_TYPE_ALIASES
+        .iter()
+        .find(|(alias, _)| *alias == lower)
+        .map(|(_, kind)| *kind);
+    match alias.or_else(|| closest(&lower, PROVIDER_TYPES.iter().copied())) {
+        Some(kind) => format!("provider_type = \"{}\"", kind),
+        None => format!("provider_type = un de {}", PROVIDER_TYPES.join(", ")),
+    }
+}
+
+fn name_suggestion(name: &str, names: &[&str]) -> String {
+    match closest(name, names.iter().copied()) {
+        Some(candidate) => format!("vouliez-vous dire `{}` ?", candidate),
+        None if names.is_empty() => "définir ce provider dans [providers]".to_string(),
+        None => format!("providers définis: {}", names.join(", ")),
+    }
+}
+
+/// Premier modèle du catalogue pour ce type de provider
+fn example_model(provider_type: &LLMProviderType) -> Option<String> {
+    ModelCatalog::builtin()
+        .models()
+        .iter()
+        .find(|model| &model.provider == provider_type)
+        .map(|model| model.name.clone())
+}
+
+fn base_url_suggestion(provider_type: &LLMProviderType) -> String {
+    match provider_type {
+        LLMProviderType::AzureOpenAI => "base_url = \"https://<ressource>.openai.azure.com\"",
+        LLMProviderType::Ollama => "base_url = \"http://<hôte>:11434\" ou deployment = \"local\"",
+        LLMProviderType::LlamaCpp => "base_url = \"http://<hôte>:8080\" ou deployment = \"local\"",
+        _ => "base_url = \"https://<hôte>\"",
+    }
+    .to_string()
+}
+
+fn api_key_variable(provider_type: &LLMProviderType) -> &'static str {
+    match provider_type {
+        LLMProviderType::Claude => "ANTHROPIC_API_KEY",
+        LLMProviderType::OpenAI => "OPENAI_API_KEY",
+        LLMProviderType::Gemini => "GOOGLE_API_KEY",
+        LLMProviderType::Mistral => "MISTRAL_API_KEY",
+        LLMProviderType::AzureOpenAI => "AZURE_OPENAI_API_KEY",
+        _ => "API_KEY",
+    }
+}
+
+/// Candidat le plus proche (distance d'édition d'au plus 2, ou un tiers de la longueur)
+fn closest<'a>(word: &str, candidates: impl Iterator<Item = &'a str>) -> Option<&'a str> {
+    let word = word.to_ascii_lowercase();
+    let threshold = (word.chars().count() / 3).max(2);
+    candidates
+        .map(|candidate| (edit_distance(&word, &candidate.to_ascii_lowercase()), candidate))
+        .filter(|(distance, _)| *distance <= threshold)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, candidate)| candidate)
+}
+
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != *cb);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFIG: &str = r#"default_provider = "claud"
+fallback = ["local"]
+
+[providers.claude]
+provider_type = "claude"
+model_name = "claude-sonnet-4-5"
+api_key = "sk-test"
+max_retry = 1
+
+[providers.claude.parameters]
+temperatur = 0.2
+top_p = 1.5
+
+[[middleware]]
+type = "redaction"
+pattern = ["secret"]
+
+[cache]
+capacity = 0
+"#;
+
+    fn issues(content: &str) -> Vec<ConfigIssue> {
+        let table: Table = content.parse().unwrap();
+        let layer = ConfigLayer {
+            path: PathBuf::from("codecrafter.toml"),
+            content: content.to_string(),
+            table: table.clone(),
+        };
+        validate(&table, &[layer], None, false)
+    }
+
+    fn line(issue: &ConfigIssue) -> usize {
+        issue.location.as_ref().unwrap().line
+    }
+
+    #[test]
+    fn known_keys_come_from_the_serde_structs() {
+        let parameters = known_keys::<ModelParameters>(Table::new());
+        assert!(parameters.contains(&"temperature".to_string()));
+        assert!(parameters.contains(&"stop_sequences".to_string()));
+        assert!(known_keys::<LLMProviderConfig>(Table::new()).contains(&"template".to_string()));
+        assert!(known_keys::<CodeCrafterConfig>(Table::new()).contains(&"cache".to_string()));
+
+        let redaction = Table::from_iter([("type".to_string(), Value::from("redaction"))]);
+        assert_eq!(known_keys::<MiddlewareConfig>(redaction), vec!["patterns"]);
+        let logging = Table::from_iter([("type".to_string(), Value::from("logging"))]);
+        assert!(known_keys::<MiddlewareConfig>(logging).is_empty());
+    }
+
+    #[test]
+    fn every_problem_is_reported_with_its_line() {
+        let issues = issues(CONFIG);
+        let found: Vec<(&str, usize)> = issues
+            .iter()
+            .filter(|issue| issue.severity == Severity::Error)
+            .map(|issue| (issue.key.as_str(), line(issue)))
+            .collect();
+
+        for expected in [
+            ("default_provider", 1),
+            ("fallback[0]", 2),
+            ("providers.claude.max_retry", 8),
+            ("providers.claude.parameters.temperatur", 11),
+            ("providers.claude.parameters.top_p", 12),
+            ("middleware[0].pattern", 16),
+            ("cache.capacity", 19),
+        ] {
+            assert!(found.contains(&expected), "{:?} absent de {:?}", expected, found);
+        }
+        assert_eq!(found.len(), 7, "{:?}", found);
+
+        let unknown = issues
+            .iter()
+            .find(|issue| issue.key == "providers.claude.parameters.temperatur")
+            .unwrap();
+        assert_eq!(unknown.suggestion.as_deref(), Some("vouliez-vous dire `temperature` ?"));
+        let pattern = issues.iter().find(|issue| issue.key == "middleware[0].pattern").unwrap();
+        assert_eq!(pattern.suggestion.as_deref(), Some("vouliez-vous dire `patterns` ?"));
+    }
+
+    #[test]
+    fn report_lists_all_errors_in_one_message() {
+        let report = ConfigReport {
+            issues: issues(CONFIG),
+            ..ConfigReport::default()
+        };
+        let Err(LLMError::InvalidConfig(message)) = report.into_result() else {
+            panic!("la configuration devrait être refusée");
+        };
+        let lines: Vec<&str> = message.lines().collect();
+        assert_eq!(lines[0], "7 erreurs");
+        assert_eq!(lines.len(), 8);
+        assert!(lines.contains(
+            &"codecrafter.toml:8: providers.claude.max_retry: clé inconnue \
+              (suggestion: vouliez-vous dire `max_retries` ?)"
+        ));
+    }
+
+    #[test]
+    fn unknown_middleware_type_is_suggested_from_the_enum() {
+        let issues = issues("[[middleware]]\ntype = \"loging\"\n");
+        let issue = issues.iter().find(|issue| issue.key == "middleware[0].type").unwrap();
+        assert_eq!(line(issue), 2);
+        assert_eq!(issue.suggestion.as_deref(), Some("vouliez-vous dire `logging` ?"));
+    }
+
+    #[test]
+    fn unknown_keys_are_rejected_when_loading() {
+        let table: Table = "[cache]\ncapacty = 10\n".parse().unwrap();
+        assert!(Value::Table(table).try_into::<CodeCrafterConfig>().is_err());
+    }
+}
@@ -1,3 +1,15 @@
-fn main() {
-    println!("Hello, world!");
+// Point d'entrée de la CLI codecrafter
+
+use tracing_subscriber::EnvFilter;
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    // Les avertissements (budgets, fallback) sont affichés par défaut
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("warn"));
+    tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_writer(std::io::stderr)
+        .init();
+
+    codecrafter::cli::run().await
 }
@@ -0,0 +1,225 @@
+// Tests d'intégration sans réseau : les échanges sont rejoués depuis des cassettes
+
+use async_trait::async_trait;
+use futures::StreamExt;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use codecrafter::llm::replay::{
+    Cassette, Interaction, MatchMode, RecordedResponse, RecordingProvider, ReplayProvider,
+};
+use codecrafter::llm::{
+    FinishReason, LLMError, LLMMessage, LLMProvider, LLMRequest, LLMResponse, LLMStream,
+    LLMStreamChunk, ModelParameters, Role, TokenUsage,
+};
+
+fn request(prompt: &str, temperature: f32) -> LLMRequest {
+    LLMRequest {
+        messages: vec![LLMMessage {
+            role: Role::User,
+            content: prompt.into(),
+            metadata: None,
+            tool_calls: Vec::new(),
+            tool_call_id: None,
+        }],
+        parameters: Some(ModelParameters {
+            temperature,
+            ..Default::default()
+        }),
+        stream: false,
+        fim: None,
+        tools: Vec::new(),
+        tool_choice: None,
+        cache: false,
+    }
+}
+
+fn response(content: &str) -> LLMResponse {
+    LLMResponse {
+        content: content.to_string(),
+        finish_reason: FinishReason::Stop,
+        usage: TokenUsage::default(),
+        model: "test-model".to_string(),
+        metadata: None,
+        tool_calls: Vec::new(),
+    }
+}
+
+fn chunk(delta: &str, finish_reason: Option<FinishReason>) -> LLMStreamChunk {
+    LLMStreamChunk {
+        delta: delta.to_string(),
+        finish_reason,
+        metadata: None,
+        tool_calls: Vec::new(),
+    }
+}
+
+fn interaction(request: LLMRequest, response: RecordedResponse) -> Interaction {
+    Interaction {
+        provider: "stub".to_string(),
+        model: "test-model".to_string(),
+        request,
+        response,
+    }
+}
+
+async fn collect_text(mut stream: LLMStream) -> String {
+    let mut text = String::new();
+    while let Some(chunk) = stream.next().await {
+        text.push_str(&chunk.expect("chunk valide").delta);
+    }
+    text
+}
+
+/// Provider de test qui répond "réponse N" au N-ième appel
+struct StubProvider {
+    calls: Arc<AtomicUsize>,
+}
+
+#[async_trait]
+impl LLMProvider for StubProvider {
+    async fn generate(&self, _request: LLMRequest) -> Result<LLMResponse, LLMError> {
+        let call = self.calls.fetch_add(1, Ordering::SeqCst) + 1;
+        Ok(response(&format!("réponse {}", call)))
+    }
+
+    async fn generate_stream(&self, _request: LLMRequest) -> Result<LLMStream, LLMError> {
+        self.calls.fetch_add(1, Ordering::SeqCst);
+        let chunks = vec![Ok(chunk("fl", None)), Ok(chunk("ux", Some(FinishReason::Stop)))];
+        Ok(Box::new(futures::stream::iter(chunks)))
+    }
+
+    fn count_tokens(&self, text: &str) -> Result<u32, LLMError> {
+        Ok(text.split_whitespace().count() as u32)
+    }
+
+    fn provider_name(&self) -> &str {
+        "stub"
+    }
+
+    fn model_name(&self) -> &str {
+        "test-model"
+    }
+
+    async fn health_check(&self) -> Result<(), LLMError> {
+        Ok(())
+    }
+}
+
+
+#[tokio::test]
+async fn replay_exact_match() {
+    let cassette = Cassette {
+        interactions: vec![interaction(
+            request("Bonjour", 0.0),
+            RecordedResponse::Complete(response("Salut")),
+        )],
+    };
+    let provider = ReplayProvider::from_cassette(cassette, MatchMode::Exact, "test");
+
+    let replayed = provider.generate(request("Bonjour", 0.0)).await.unwrap();
+    assert_eq!(replayed.content, "Salut");
+    assert_eq!(provider.provider_name(), "stub");
+    assert_eq!(provider.model_name(), "test-model");
+
+    // Paramètres différents : pas de correspondance exacte
+    let error = provider.generate(request("Bonjour", 0.5)).await.unwrap_err();
+    assert!(matches!(error, LLMError::ReplayMiss(_)));
+}
+
+#[tokio::test]
+async fn replay_ignore_parameters() {
+    let cassette = Cassette {
+        interactions: vec![interaction(
+            request("Bonjour", 0.0),
+            RecordedResponse::Complete(response("Salut")),
+        )],
+    };
+    let provider = ReplayProvider::from_cassette(cassette, MatchMode::IgnoreParameters, "test");
+
+    let replayed = provider.generate(request("Bonjour", 1.2)).await.unwrap();
+    assert_eq!(replayed.content, "Salut");
+}
+
+#[tokio::test]
+async fn replay_miss_reports_request() {
+    let provider =
+        ReplayProvider::from_cassette(Cassette::default(), MatchMode::Exact, "vide.json");
+
+    match provider.generate(request("Une question absente", 0.0)).await {
+        Err(LLMError::ReplayMiss(message)) => {
+            assert!(message.contains("vide.json"));
+            assert!(message.contains("Une question absente"));
+        }
+        other => panic!("ReplayMiss attendu, obtenu {:?}", other.map(|r| r.content)),
+    }
+}
+
+#[tokio::test]
+async fn replay_repeated_requests_in_order() {
+    let cassette = Cassette {
+        interactions: vec![
+            interaction(request("Suite", 0.0), RecordedResponse::Complete(response("un"))),
+            interaction(request("Suite", 0.0), RecordedResponse::Complete(response("deux"))),
+        ],
+    };
+    let provider = ReplayProvider::from_cassette(cassette, MatchMode::Exact, "test");
+
+    let mut contents = Vec::new();
+    for _ in 0..3 {
+        contents.push(provider.generate(request("Suite", 0.0)).await.unwrap().content);
+    }
+    assert_eq!(contents, ["un", "deux", "deux"]);
+}
+
+#[tokio::test]
+async fn replay_between_stream_and_complete() {
+    let cassette = Cassette {
+        interactions: vec![
+            interaction(
+                request("Flux", 0.0),
+                RecordedResponse::Stream(vec![
+                    chunk("Bon", None),
+                    chunk("jour", Some(FinishReason::Stop)),
+                ]),
+            ),
+            interaction(request("Complet", 0.0), RecordedResponse::Complete(response("Salut"))),
+        ],
+    };
+    let provider = ReplayProvider::from_cassette(cassette, MatchMode::Exact, "test");
+
+    let collected = provider.generate(request("Flux", 0.0)).await.unwrap();
+    assert_eq!(collected.content, "Bonjour");
+    assert_eq!(collected.finish_reason, FinishReason::Stop);
+
+    let stream = provider.generate_stream(request("Complet", 0.0)).await.unwrap();
+    assert_eq!(collect_text(stream).await, "Salut");
+}
+
+#[tokio::test]
+async fn record_then_replay() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("cassettes").join("session.json");
+    let calls = Arc::new(AtomicUsize::new(0));
+
+    let stub = StubProvider {
+        calls: Arc::clone(&calls),
+    };
+    let recorder = RecordingProvider::new(Box::new(stub), &path).unwrap();
+    let recorded = recorder.generate(request("Bonjour", 0.0)).await.unwrap();
+    let stream = recorder.generate_stream(request("Flux", 0.0)).await.unwrap();
+    assert_eq!(collect_text(stream).await, "flux");
+    assert_eq!(calls.load(Ordering::SeqCst), 2);
+
+    let cassette = Cassette::load(&path).unwrap();
+    assert_eq!(cassette.interactions.len(), 2);
+
+    let replay = ReplayProvider::new(&path, MatchMode::Exact).unwrap();
+    let replayed = replay.generate(request("Bonjour", 0.0)).await.unwrap();
+    assert_eq!(replayed.content, recorded.content);
+    let stream = replay.generate_stream(request("Flux", 0.0)).await.unwrap();
+    assert_eq!(collect_text(stream).await, "flux");
+
+    // Le rejeu n'appelle jamais le provider enregistré
+    assert_eq!(calls.load(Ordering::SeqCst), 2);
+}